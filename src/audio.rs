@@ -0,0 +1,11 @@
+pub mod ring;
+pub mod wav;
+#[cfg(target_os = "espidf")]
+pub mod dac;
+
+/// Somewhere to send the APU's output, interleaved left and right samples
+pub trait AudioSink {
+    type Error;
+
+    fn write(&mut self, samples: &[i16]) -> Result<(), Self::Error>;
+}
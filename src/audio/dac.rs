@@ -0,0 +1,103 @@
+use core::convert::Infallible;
+use std::thread;
+
+use esp_idf_sys::{self as sys, esp, EspError};
+
+use super::AudioSink;
+use super::ring::{ring_buffer, Consumer, Producer};
+
+// Samples queued up for the audio thread, an eighth of a second at 32kHz
+const RING_SIZE: usize = 4096;
+// Bytes handed to the DAC driver at a time
+const CHUNK_SIZE: usize = 256;
+// The DAC is unsigned 8-bit, this is the middle of its range
+const SILENCE: u8 = 0x80;
+
+/// Plays through the CYD's speaker amplifier on GPIO26, the ESP32's second internal DAC
+/// channel fed by I2S DMA. Samples are mixed down to 8-bit mono and go through a ring
+/// buffer to a thread that keeps the DAC topped up, anything that doesn't fit is dropped.
+#[derive(Debug)]
+pub struct DacSink {
+    samples: Producer<u8>,
+    dropped: usize,
+}
+
+impl DacSink {
+    pub fn new(sample_rate: u32) -> Result<Self, EspError> {
+        let config = sys::dac_continuous_config_t {
+            chan_mask: sys::dac_channel_mask_t_DAC_CHANNEL_MASK_CH1,
+            desc_num: 4,
+            buf_size: CHUNK_SIZE * 4,
+            freq_hz: sample_rate,
+            offset: 0,
+            clk_src: sys::soc_periph_dac_digi_clk_src_t_DAC_DIGI_CLK_SRC_DEFAULT,
+            chan_mode: sys::dac_continuous_channel_mode_t_DAC_CHANNEL_MODE_SIMUL,
+        };
+
+        let mut handle: sys::dac_continuous_handle_t = core::ptr::null_mut();
+        esp!(unsafe { sys::dac_continuous_new_channels(&config, &mut handle) })?;
+        esp!(unsafe { sys::dac_continuous_enable(handle) })?;
+
+        let (producer, consumer) = ring_buffer(RING_SIZE);
+        let dac = DacHandle(handle);
+        thread::Builder::new()
+            .name("audio".into())
+            .stack_size(4096)
+            .spawn(move || feed(dac, consumer))
+            .expect("audio thread");
+
+        Ok(DacSink {
+            samples: producer,
+            dropped: 0,
+        })
+    }
+
+    /// Samples thrown away because the audio thread was behind
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+impl AudioSink for DacSink {
+    type Error = Infallible;
+
+    fn write(&mut self, samples: &[i16]) -> Result<(), Self::Error> {
+        let mut mono = [SILENCE; CHUNK_SIZE];
+        for frames in samples.chunks(CHUNK_SIZE * 2) {
+            let count = frames.len() / 2;
+            for (level, frame) in mono.iter_mut().zip(frames.chunks_exact(2)) {
+                let mixed = (frame[0] as i32 + frame[1] as i32) / 2;
+                *level = ((mixed >> 8) + SILENCE as i32) as u8;
+            }
+
+            self.dropped += count - self.samples.push_slice(&mono[..count]);
+        }
+        Ok(())
+    }
+}
+
+// The driver handle is only ever used from the audio thread once it is made
+struct DacHandle(sys::dac_continuous_handle_t);
+
+unsafe impl Send for DacHandle {}
+
+fn feed(dac: DacHandle, mut samples: Consumer<u8>) {
+    let mut chunk = [SILENCE; CHUNK_SIZE];
+    let mut level = SILENCE;
+
+    loop {
+        // Running dry holds the last level, dropping back to the middle would click
+        let count = samples.pop_slice(&mut chunk);
+        chunk[count..].fill(level);
+        level = chunk[CHUNK_SIZE - 1];
+
+        // Blocks until the DMA has room, which is what paces this thread
+        let mut written = 0;
+        let result = esp!(unsafe {
+            sys::dac_continuous_write(dac.0, chunk.as_mut_ptr(), CHUNK_SIZE, &mut written, -1)
+        });
+        if let Err(e) = result {
+            log::error!("DAC write failed: {}", e);
+        }
+    }
+}
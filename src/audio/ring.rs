@@ -0,0 +1,148 @@
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Head and tail only ever count up, the slot is the count modulo the capacity
+#[derive(Debug)]
+struct Ring<T> {
+    slots: Box<[UnsafeCell<T>]>,
+    // Next slot the producer writes
+    head: AtomicUsize,
+    // Next slot the consumer reads
+    tail: AtomicUsize,
+}
+
+// Each slot is only touched by one side at a time, which head and tail guarantee
+unsafe impl<T: Send> Sync for Ring<T> {}
+
+impl<T> Ring<T> {
+    fn slot(&self, index: usize) -> *mut T {
+        self.slots[index & (self.slots.len() - 1)].get()
+    }
+}
+
+/// Creates a single producer, single consumer queue that never locks,
+/// so neither side can hold up the other
+pub fn ring_buffer<T: Copy + Default>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    assert!(capacity.is_power_of_two(), "ring buffer capacity has to be a power of two");
+
+    let ring = Arc::new(Ring {
+        slots: (0..capacity).map(|_| UnsafeCell::new(T::default())).collect(),
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+    });
+    (Producer { ring: ring.clone() }, Consumer { ring })
+}
+
+#[derive(Debug)]
+pub struct Producer<T> {
+    ring: Arc<Ring<T>>,
+}
+
+impl<T: Copy> Producer<T> {
+    /// Copies in as much as fits, returning how many went in
+    pub fn push_slice(&mut self, values: &[T]) -> usize {
+        let head = self.ring.head.load(Ordering::Relaxed);
+        let tail = self.ring.tail.load(Ordering::Acquire);
+        let free = self.ring.slots.len() - head.wrapping_sub(tail);
+
+        let count = values.len().min(free);
+        for (i, &value) in values[..count].iter().enumerate() {
+            unsafe { *self.ring.slot(head.wrapping_add(i)) = value };
+        }
+
+        // Publishes the writes above to the consumer
+        self.ring.head.store(head.wrapping_add(count), Ordering::Release);
+        count
+    }
+
+    pub fn free(&self) -> usize {
+        let used = self.ring.head.load(Ordering::Relaxed).wrapping_sub(self.ring.tail.load(Ordering::Acquire));
+        self.ring.slots.len() - used
+    }
+}
+
+#[derive(Debug)]
+pub struct Consumer<T> {
+    ring: Arc<Ring<T>>,
+}
+
+impl<T: Copy> Consumer<T> {
+    /// Fills as much of `out` as there is queued up, returning how many were copied
+    pub fn pop_slice(&mut self, out: &mut [T]) -> usize {
+        let tail = self.ring.tail.load(Ordering::Relaxed);
+        let head = self.ring.head.load(Ordering::Acquire);
+
+        let count = out.len().min(head.wrapping_sub(tail));
+        for (i, value) in out[..count].iter_mut().enumerate() {
+            *value = unsafe { *self.ring.slot(tail.wrapping_add(i)) };
+        }
+
+        // Hands the slots back to the producer
+        self.ring.tail.store(tail.wrapping_add(count), Ordering::Release);
+        count
+    }
+
+    pub fn len(&self) -> usize {
+        self.ring.head.load(Ordering::Acquire).wrapping_sub(self.ring.tail.load(Ordering::Relaxed))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn fills_up_and_wraps() {
+        let (mut producer, mut consumer) = ring_buffer::<u8>(4);
+        assert_eq!(producer.push_slice(&[1, 2, 3]), 3);
+
+        let mut out = [0; 2];
+        assert_eq!(consumer.pop_slice(&mut out), 2);
+        assert_eq!(out, [1, 2]);
+
+        // Only 3 of these fit, the last two wrap round to the start
+        assert_eq!(producer.push_slice(&[4, 5, 6, 7]), 3);
+        assert_eq!(producer.free(), 0);
+
+        let mut out = [0; 8];
+        assert_eq!(consumer.pop_slice(&mut out), 4);
+        assert_eq!(out[..4], [3, 4, 5, 6]);
+        assert!(consumer.is_empty());
+    }
+
+    #[test]
+    fn survives_threads() {
+        let (mut producer, mut consumer) = ring_buffer::<u32>(64);
+
+        let writer = thread::spawn(move || {
+            let mut next = 0;
+            while next < 100_000 {
+                let chunk: Vec<u32> = (next..next + 7).collect();
+                match producer.push_slice(&chunk) {
+                    0 => thread::yield_now(),
+                    count => next += count as u32,
+                }
+            }
+        });
+
+        let mut expected = 0;
+        let mut out = [0; 5];
+        while expected < 100_000 {
+            let count = consumer.pop_slice(&mut out);
+            if count == 0 {
+                thread::yield_now();
+            }
+            for &value in &out[..count] {
+                assert_eq!(value, expected);
+                expected += 1;
+            }
+        }
+        writer.join().unwrap();
+    }
+}
@@ -0,0 +1,127 @@
+use std::io::{self, Seek, SeekFrom, Write};
+
+use super::AudioSink;
+
+const HEADER_SIZE: u32 = 44;
+const CHANNELS: u16 = 2;
+const BITS_PER_SAMPLE: u16 = 16;
+
+/// Writes 16-bit stereo PCM to a WAV file, for checking the APU's output on the host
+#[derive(Debug)]
+pub struct WavSink<W: Write + Seek> {
+    writer: W,
+    sample_rate: u32,
+    data_size: u32,
+}
+
+impl<W: Write + Seek> WavSink<W> {
+    pub fn new(mut writer: W, sample_rate: u32) -> io::Result<Self> {
+        // The sizes get filled in by finish once they are known
+        write_header(&mut writer, sample_rate, 0)?;
+        Ok(WavSink {
+            writer,
+            sample_rate,
+            data_size: 0,
+        })
+    }
+
+    /// Fixes up the header and hands back the writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.seek(SeekFrom::Start(0))?;
+        write_header(&mut self.writer, self.sample_rate, self.data_size)?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write + Seek> AudioSink for WavSink<W> {
+    type Error = io::Error;
+
+    fn write(&mut self, samples: &[i16]) -> Result<(), Self::Error> {
+        let bytes: Vec<u8> = samples.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+        self.writer.write_all(&bytes)?;
+        self.data_size += bytes.len() as u32;
+        Ok(())
+    }
+}
+
+fn write_header<W: Write>(writer: &mut W, sample_rate: u32, data_size: u32) -> io::Result<()> {
+    let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(HEADER_SIZE - 8 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    // PCM
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&CHANNELS.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    use crate::gb::apu::Apu;
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn header_matches_the_data() {
+        let mut sink = WavSink::new(Cursor::new(Vec::new()), 32_000).unwrap();
+        sink.write(&[1, -1, 2, -2]).unwrap();
+        let bytes = sink.finish().unwrap().into_inner();
+
+        assert_eq!(bytes.len(), 44 + 8);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(u32_at(&bytes, 4), 36 + 8);
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!(u32_at(&bytes, 24), 32_000);
+        assert_eq!(u32_at(&bytes, 28), 32_000 * 4);
+        assert_eq!(&bytes[36..40], b"data");
+        assert_eq!(u32_at(&bytes, 40), 8);
+        assert_eq!(&bytes[44..], [1, 0, 0xFF, 0xFF, 2, 0, 0xFE, 0xFF]);
+    }
+
+    #[test]
+    fn records_a_tone_from_the_apu() {
+        let mut apu = Apu::new(32_000);
+        // Channel 2 at full volume on both sides, 50% duty at roughly 1kHz
+        apu.write_register(0xFF16, 0b1000_0000);
+        apu.write_register(0xFF17, 0xF0);
+        apu.write_register(0xFF18, 0x83);
+        apu.write_register(0xFF19, 0x87);
+
+        let mut sink = WavSink::new(Cursor::new(Vec::new()), apu.sample_rate()).unwrap();
+        for _ in 0..100 {
+            // 1/100th of a second at a time
+            for _ in 0..41_943 / 4 {
+                apu.tick(4);
+            }
+            let samples: Vec<i16> = apu.drain_samples().collect();
+            sink.write(&samples).unwrap();
+        }
+        let bytes = sink.finish().unwrap().into_inner();
+
+        let data_size = u32_at(&bytes, 40) as usize;
+        assert_eq!(bytes.len(), 44 + data_size);
+        // A second of stereo 16-bit samples, give or take the odd one from rounding
+        assert!(data_size.abs_diff(32_000 * 4) <= 4 * 4);
+
+        let left: Vec<i16> = bytes[44..].chunks(4).map(|frame| i16::from_le_bytes([frame[0], frame[1]])).collect();
+        let crossings = left.windows(2).filter(|pair| (pair[0] < 0) != (pair[1] < 0)).count();
+        assert!((1900..2200).contains(&crossings), "{} zero crossings", crossings);
+    }
+}
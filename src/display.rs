@@ -0,0 +1,4 @@
+pub mod palette;
+pub mod presenter;
+pub mod scaling;
+pub mod transfer;
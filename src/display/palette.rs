@@ -0,0 +1,167 @@
+use embedded_graphics::pixelcolor::Rgb565;
+
+use crate::gb::cartridge::{CartridgeHeader, Licensee};
+use crate::gb::ppu::{LAYER_MASK, OBJ0_LAYER, OBJ1_LAYER, SHADE_MASK};
+
+/// Colours for the four shades of each layer, lightest first
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub bg: [Rgb565; 4],
+    pub obj0: [Rgb565; 4],
+    pub obj1: [Rgb565; 4],
+}
+
+impl Palette {
+    /// The original DMG's pea green
+    pub const CLASSIC_GREEN: Palette = Palette::from_shades(shades(0x9BBC0F, 0x8BAC0F, 0x306230, 0x0F380F));
+    /// The Pocket's greyish screen
+    pub const POCKET_GREY: Palette = Palette::from_shades(shades(0xC4CFA1, 0x8B956D, 0x4D533C, 0x1F1F1F));
+    /// Plain white to black
+    pub const HIGH_CONTRAST: Palette = Palette::from_shades(shades(0xFFFFFF, 0xAAAAAA, 0x555555, 0x000000));
+    /// What the CGB boot rom gives games it doesn't recognise
+    pub const CGB_DEFAULT: Palette = Palette {
+        bg: shades(0xFFFFFF, 0x7BFF31, 0x0063C5, 0x000000),
+        obj0: RED,
+        obj1: RED,
+    };
+
+    /// Same four colours for the background and both sprite palettes
+    pub const fn from_shades(shades: [Rgb565; 4]) -> Self {
+        Palette {
+            bg: shades,
+            obj0: shades,
+            obj1: shades,
+        }
+    }
+
+    /// Colours the way a CGB would when running this DMG cartridge
+    pub fn for_cartridge(header: &CartridgeHeader) -> Self {
+        // Only Nintendo's own games get looked up
+        if !matches!(header.licensee, Licensee::Old(0x01) | Licensee::New([b'0', b'1'])) {
+            return Palette::CGB_DEFAULT;
+        }
+
+        let fourth_letter = header.title.as_bytes().get(3).copied();
+        TITLE_PALETTES.iter()
+            .find(|(checksum, letter, _)| {
+                *checksum == header.title_checksum && (letter.is_none() || *letter == fourth_letter)
+            })
+            .map_or(Palette::CGB_DEFAULT, |&(_, _, palette)| palette)
+    }
+
+    /// Colour for a frame buffer pixel
+    pub fn colour(&self, pixel: u8) -> Rgb565 {
+        let shades = match pixel & LAYER_MASK {
+            OBJ0_LAYER => &self.obj0,
+            OBJ1_LAYER => &self.obj1,
+            _ => &self.bg,
+        };
+        shades[(pixel & SHADE_MASK) as usize]
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::HIGH_CONTRAST
+    }
+}
+
+// Four 24-bit colours down to Rgb565
+const fn shades(lightest: u32, light: u32, dark: u32, darkest: u32) -> [Rgb565; 4] {
+    const fn rgb(colour: u32) -> Rgb565 {
+        Rgb565::new((colour >> 19) as u8 & 0x1F, (colour >> 10) as u8 & 0x3F, (colour >> 3) as u8 & 0x1F)
+    }
+    [rgb(lightest), rgb(light), rgb(dark), rgb(darkest)]
+}
+
+const RED: [Rgb565; 4] = shades(0xFFFFFF, 0xFF8484, 0x943A3A, 0x000000);
+const GREEN: [Rgb565; 4] = shades(0xFFFFFF, 0x7BFF31, 0x008400, 0x000000);
+const BLUE: [Rgb565; 4] = shades(0xFFFFFF, 0x63A5FF, 0x0000FF, 0x000000);
+const ORANGE: [Rgb565; 4] = shades(0xFFFFFF, 0xFF7300, 0x944200, 0x000000);
+
+// Part of the CGB boot rom's table, keyed by title checksum and, where two titles
+// share a checksum, the fourth letter of the title. Anything missing gets the
+// default, the same as a game the boot rom doesn't know
+const TITLE_PALETTES: [(u8, Option<u8>, Palette); 6] = [
+    // POKEMON RED
+    (0x14, None, Palette { bg: RED, obj0: GREEN, obj1: RED }),
+    // POKEMON BLUE
+    (0x61, Some(b'E'), Palette { bg: BLUE, obj0: RED, obj1: BLUE }),
+    // TETRIS
+    (0xDB, None, Palette::from_shades(shades(0xFFFFFF, 0xFFFF00, 0xFF0000, 0x000000))),
+    // SUPER MARIOLAND
+    (0x46, Some(b'E'), Palette {
+        bg: shades(0xFFFFFF, 0xADAD84, 0x42737B, 0x000000),
+        obj0: ORANGE,
+        obj1: shades(0xFFFFFF, 0x5ABDFF, 0xFF0000, 0x0000FF),
+    }),
+    // MARIOLAND2
+    (0xC9, None, Palette {
+        bg: shades(0xFFFFCE, 0x63EFEF, 0x9C8431, 0x5A5A5A),
+        obj0: ORANGE,
+        obj1: BLUE,
+    }),
+    // ZELDA
+    (0x70, None, Palette {
+        bg: shades(0xFFFFFF, 0x00FF00, 0x318400, 0x004A00),
+        obj0: RED,
+        obj1: BLUE,
+    }),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gb::cartridge::HEADER_END;
+
+    fn header(title: &str, licensee: u8) -> CartridgeHeader {
+        let mut rom = vec![0; HEADER_END];
+        rom[0x0134..0x0134 + title.len()].copy_from_slice(title.as_bytes());
+        rom[0x014B] = licensee;
+        CartridgeHeader::parse(&rom).unwrap()
+    }
+
+    #[test]
+    fn layers_get_their_own_colours() {
+        let palette = Palette::CGB_DEFAULT;
+        assert_eq!(palette.colour(2), palette.bg[2]);
+        assert_eq!(palette.colour(OBJ0_LAYER | 1), palette.obj0[1]);
+        assert_eq!(palette.colour(OBJ1_LAYER | 3), palette.obj1[3]);
+    }
+
+    #[test]
+    fn colours_convert_to_rgb565() {
+        assert_eq!(Palette::HIGH_CONTRAST.bg, [
+            Rgb565::new(31, 63, 31),
+            Rgb565::new(21, 42, 21),
+            Rgb565::new(10, 21, 10),
+            Rgb565::new(0, 0, 0),
+        ]);
+    }
+
+    #[test]
+    fn nintendo_titles_are_looked_up() {
+        let tetris = header("TETRIS", 0x01);
+        assert_eq!(tetris.title_checksum, 0xDB);
+        assert_eq!(Palette::for_cartridge(&tetris).bg[1], Rgb565::new(31, 63, 0));
+
+        assert_eq!(Palette::for_cartridge(&header("POKEMON RED", 0x01)).obj0, GREEN);
+    }
+
+    #[test]
+    fn fourth_letter_settles_shared_checksums() {
+        let blue = header("POKEMON BLUE", 0x01);
+        assert_eq!(blue.title_checksum, 0x61);
+        assert_eq!(Palette::for_cartridge(&blue).bg, BLUE);
+
+        // Same checksum, different fourth letter
+        let other = header("VEGAS STAKES", 0x01);
+        assert_eq!(other.title_checksum, 0x61);
+        assert_eq!(Palette::for_cartridge(&other), Palette::CGB_DEFAULT);
+    }
+
+    #[test]
+    fn other_licensees_get_the_default() {
+        assert_eq!(Palette::for_cartridge(&header("TETRIS", 0x08)), Palette::CGB_DEFAULT);
+    }
+}
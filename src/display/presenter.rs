@@ -0,0 +1,371 @@
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::Rectangle,
+};
+
+use super::palette::Palette;
+use super::scaling::Scaling;
+use crate::gb::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// The CYD panel in the landscape orientation main.rs sets up
+pub const PANEL_WIDTH: u32 = 320;
+pub const PANEL_HEIGHT: u32 = 240;
+
+/// Gets finished frames of shaded pixels onto a display
+pub trait FramePresenter {
+    fn present<D>(&mut self, frame: &[u8], target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>;
+}
+
+// Rgb565 goes over the wire as two bytes a pixel
+const BYTES_PER_PIXEL: u32 = 2;
+
+/// What went out to the panel for the last frame
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PresentStats {
+    /// Output rows sent
+    pub lines: u32,
+    /// Pixel data sent, not counting command overhead
+    pub bytes: u32,
+}
+
+/// Draws the frame onto the panel with one of the scaling modes, only sending
+/// the rows whose frame lines changed since the last frame
+#[derive(Debug)]
+pub struct Presenter {
+    palette: Palette,
+    scaling: Scaling,
+    // Set when the scaled area shrinks and the old picture has to go
+    clear_pending: bool,
+    // What the panel is currently showing, None when it has to be redrawn in full
+    previous: Option<Vec<u8>>,
+    dirty_tracking: bool,
+    stats: PresentStats,
+}
+
+impl Presenter {
+    pub fn new(palette: Palette, scaling: Scaling) -> Self {
+        Presenter {
+            palette,
+            scaling,
+            clear_pending: false,
+            previous: None,
+            dirty_tracking: true,
+            stats: PresentStats::default(),
+        }
+    }
+
+    pub fn palette(&self) -> Palette {
+        self.palette
+    }
+
+    /// Recolours from the next frame on, which is sent in full
+    pub fn set_palette(&mut self, palette: Palette) {
+        if palette != self.palette {
+            self.palette = palette;
+            self.invalidate();
+        }
+    }
+
+    pub fn scaling(&self) -> Scaling {
+        self.scaling
+    }
+
+    /// Switches mode, the panel is cleared before the next frame goes out
+    pub fn set_scaling(&mut self, scaling: Scaling) {
+        if scaling != self.scaling {
+            self.scaling = scaling;
+            self.clear_pending = true;
+            self.invalidate();
+        }
+    }
+
+    /// Turning this off pushes every frame in full, to compare against
+    pub fn set_dirty_tracking(&mut self, enabled: bool) {
+        self.dirty_tracking = enabled;
+        self.invalidate();
+    }
+
+    /// Forces the next frame to be sent in full
+    pub fn invalidate(&mut self) {
+        self.previous = None;
+    }
+
+    pub fn stats(&self) -> PresentStats {
+        self.stats
+    }
+
+    fn changed_lines(&self, frame: &[u8]) -> [bool; SCREEN_HEIGHT] {
+        let mut changed = [true; SCREEN_HEIGHT];
+        if let Some(previous) = &self.previous {
+            let lines = frame.chunks(SCREEN_WIDTH).zip(previous.chunks(SCREEN_WIDTH));
+            for (changed, (line, previous)) in changed.iter_mut().zip(lines) {
+                *changed = line != previous;
+            }
+        }
+        changed
+    }
+}
+
+impl FramePresenter for Presenter {
+    fn present<D>(&mut self, frame: &[u8], target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        if self.clear_pending {
+            target.fill_solid(&Rectangle::new(Point::zero(), Size::new(PANEL_WIDTH, PANEL_HEIGHT)), Rgb565::BLACK)?;
+            self.clear_pending = false;
+        }
+
+        let changed = self.changed_lines(frame);
+        // Anything that goes wrong part way through leaves the panel in an unknown state
+        let mut previous = self.previous.take().unwrap_or_default();
+
+        let area = self.scaling.area();
+        let (palette, scaling) = (&self.palette, self.scaling);
+        let dirty = |row: u32| {
+            let (first, second) = scaling.source_lines(row);
+            changed[first as usize] || changed[second as usize]
+        };
+
+        // Each run of dirty rows is a single fill_contiguous, which mipidsi turns
+        // into one set_pixels burst over an address window instead of a transfer per pixel
+        let mut stats = PresentStats::default();
+        let mut row = 0;
+        while row < area.size.height {
+            if !dirty(row) {
+                row += 1;
+                continue;
+            }
+
+            let start = row;
+            while row < area.size.height && dirty(row) {
+                row += 1;
+            }
+
+            let span = Rectangle::new(
+                area.top_left + Point::new(0, start as i32),
+                Size::new(area.size.width, row - start),
+            );
+            let colours = (start..row)
+                .flat_map(move |y| (0..area.size.width).map(move |x| scaling.pixel(frame, palette, x, y)));
+            target.fill_contiguous(&span, colours)?;
+
+            stats.lines += row - start;
+            stats.bytes += (row - start) * area.size.width * BYTES_PER_PIXEL;
+        }
+
+        self.stats = stats;
+        if self.dirty_tracking {
+            previous.clear();
+            previous.extend_from_slice(frame);
+            self.previous = Some(previous);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+
+    use crate::gb::ppu::{OBJ0_LAYER, OBJ1_LAYER};
+
+    const SHADES: [Rgb565; 4] = Palette::HIGH_CONTRAST.bg;
+
+    // Panel sized framebuffer that also counts how the presenter talks to it
+    struct MemoryDisplay {
+        pixels: Vec<Rgb565>,
+        fills: usize,
+        single_pixels: usize,
+    }
+
+    impl MemoryDisplay {
+        fn new() -> Self {
+            MemoryDisplay {
+                pixels: vec![Rgb565::BLACK; (PANEL_WIDTH * PANEL_HEIGHT) as usize],
+                fills: 0,
+                single_pixels: 0,
+            }
+        }
+
+        fn pixel(&self, x: i32, y: i32) -> Rgb565 {
+            self.pixels[(y * PANEL_WIDTH as i32 + x) as usize]
+        }
+    }
+
+    impl OriginDimensions for MemoryDisplay {
+        fn size(&self) -> Size {
+            Size::new(PANEL_WIDTH, PANEL_HEIGHT)
+        }
+    }
+
+    impl DrawTarget for MemoryDisplay {
+        type Color = Rgb565;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            for Pixel(point, color) in pixels {
+                self.single_pixels += 1;
+                self.pixels[(point.y * PANEL_WIDTH as i32 + point.x) as usize] = color;
+            }
+            Ok(())
+        }
+
+        fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Self::Color>,
+        {
+            self.fills += 1;
+            for (point, color) in area.points().zip(colors) {
+                self.pixels[(point.y * PANEL_WIDTH as i32 + point.x) as usize] = color;
+            }
+            Ok(())
+        }
+    }
+
+    fn test_frame() -> Vec<u8> {
+        (0..SCREEN_WIDTH * SCREEN_HEIGHT).map(|i| (i % 4) as u8).collect()
+    }
+
+    #[test]
+    fn frame_is_pushed_in_one_burst() {
+        let mut display = MemoryDisplay::new();
+        Presenter::new(Palette::HIGH_CONTRAST, Scaling::Centred).present(&test_frame(), &mut display).unwrap();
+
+        assert_eq!(display.fills, 1);
+        assert_eq!(display.single_pixels, 0);
+    }
+
+    #[test]
+    fn frame_is_centred_and_shaded() {
+        let mut display = MemoryDisplay::new();
+        Presenter::new(Palette::HIGH_CONTRAST, Scaling::Centred).present(&test_frame(), &mut display).unwrap();
+
+        for shade in 0..4 {
+            assert_eq!(display.pixel(80 + shade, 48), SHADES[shade as usize]);
+        }
+        assert_eq!(display.pixel(80 + 159, 48 + 143), SHADES[3]);
+
+        // Border is left alone
+        assert_eq!(display.pixel(79, 48), Rgb565::BLACK);
+        assert_eq!(display.pixel(80, 47), Rgb565::BLACK);
+        assert_eq!(display.pixel(240, 48), Rgb565::BLACK);
+    }
+
+    #[test]
+    fn crop_fills_the_panel() {
+        let mut display = MemoryDisplay::new();
+        Presenter::new(Palette::HIGH_CONTRAST, Scaling::Crop2x).present(&test_frame(), &mut display).unwrap();
+
+        assert_eq!(display.fills, 1);
+        // Frame line 12 lands at the top, line 131 at the bottom
+        assert_eq!(display.pixel(0, 0), SHADES[0]);
+        assert_eq!(display.pixel(3, 1), SHADES[1]);
+        assert_eq!(display.pixel(319, 239), SHADES[3]);
+    }
+
+    #[test]
+    fn switching_mode_clears_the_old_picture() {
+        let mut display = MemoryDisplay::new();
+        let mut presenter = Presenter::new(Palette::HIGH_CONTRAST, Scaling::Crop2x);
+        presenter.present(&test_frame(), &mut display).unwrap();
+
+        presenter.set_scaling(Scaling::Fit);
+        presenter.present(&test_frame(), &mut display).unwrap();
+
+        assert_eq!(display.pixel(0, 0), Rgb565::BLACK);
+        assert_eq!(display.pixel(40, 12), SHADES[0]);
+        assert_eq!(display.pixel(279, 227), SHADES[3]);
+        assert_eq!(display.pixel(280, 227), Rgb565::BLACK);
+    }
+
+    #[test]
+    fn unchanged_frame_sends_nothing() {
+        let mut display = MemoryDisplay::new();
+        let mut presenter = Presenter::new(Palette::HIGH_CONTRAST, Scaling::Fit);
+        presenter.present(&test_frame(), &mut display).unwrap();
+        assert_eq!(presenter.stats(), PresentStats { lines: 216, bytes: 216 * 240 * 2 });
+
+        presenter.present(&test_frame(), &mut display).unwrap();
+        assert_eq!(display.fills, 1);
+        assert_eq!(presenter.stats(), PresentStats::default());
+    }
+
+    #[test]
+    fn only_changed_lines_are_sent() {
+        let mut display = MemoryDisplay::new();
+        let mut presenter = Presenter::new(Palette::HIGH_CONTRAST, Scaling::Fit);
+        let mut frame = test_frame();
+        presenter.present(&frame, &mut display).unwrap();
+
+        // Line 1 is a single row at 1.5x, line 2 is doubled up
+        frame[SCREEN_WIDTH] = 3;
+        frame[SCREEN_WIDTH * 2] = 3;
+        frame[SCREEN_WIDTH * 100] = 3;
+        presenter.present(&frame, &mut display).unwrap();
+
+        assert_eq!(display.fills, 3);
+        assert_eq!(presenter.stats(), PresentStats { lines: 5, bytes: 5 * 240 * 2 });
+        assert_eq!(display.pixel(40, 12 + 2), SHADES[3]);
+        assert_eq!(display.pixel(40, 12 + 150), SHADES[3]);
+    }
+
+    #[test]
+    fn smoothed_rows_follow_both_lines() {
+        let mut display = MemoryDisplay::new();
+        let mut presenter = Presenter::new(Palette::HIGH_CONTRAST, Scaling::SmoothFit);
+        let mut frame = test_frame();
+        presenter.present(&frame, &mut display).unwrap();
+
+        // The blended row between lines 0 and 1 has to go out as well
+        frame[SCREEN_WIDTH] = 3;
+        presenter.present(&frame, &mut display).unwrap();
+
+        assert_eq!(presenter.stats().lines, 2);
+    }
+
+    #[test]
+    fn palette_switch_redraws_everything() {
+        let mut display = MemoryDisplay::new();
+        let mut presenter = Presenter::new(Palette::HIGH_CONTRAST, Scaling::Centred);
+        presenter.present(&test_frame(), &mut display).unwrap();
+
+        presenter.set_palette(Palette::CLASSIC_GREEN);
+        presenter.present(&test_frame(), &mut display).unwrap();
+
+        assert_eq!(presenter.stats().lines, 144);
+        assert_eq!(display.pixel(80, 48), Palette::CLASSIC_GREEN.bg[0]);
+    }
+
+    #[test]
+    fn sprites_use_their_own_palette() {
+        let mut display = MemoryDisplay::new();
+        let mut frame = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT];
+        frame[0] = OBJ0_LAYER | 1;
+        frame[1] = OBJ1_LAYER | 1;
+        Presenter::new(Palette::CGB_DEFAULT, Scaling::Centred).present(&frame, &mut display).unwrap();
+
+        assert_eq!(display.pixel(80, 48), Palette::CGB_DEFAULT.obj0[1]);
+        assert_eq!(display.pixel(81, 48), Palette::CGB_DEFAULT.obj1[1]);
+        assert_eq!(display.pixel(82, 48), Palette::CGB_DEFAULT.bg[0]);
+    }
+
+    #[test]
+    fn full_pushes_without_tracking() {
+        let mut display = MemoryDisplay::new();
+        let mut presenter = Presenter::new(Palette::HIGH_CONTRAST, Scaling::Centred);
+        presenter.set_dirty_tracking(false);
+        presenter.present(&test_frame(), &mut display).unwrap();
+        presenter.present(&test_frame(), &mut display).unwrap();
+
+        assert_eq!(display.fills, 2);
+        assert_eq!(presenter.stats(), PresentStats { lines: 144, bytes: 160 * 144 * 2 });
+    }
+}
@@ -0,0 +1,196 @@
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::Rectangle,
+};
+
+use super::palette::Palette;
+use super::presenter::{PANEL_HEIGHT, PANEL_WIDTH};
+use crate::gb::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+const WIDTH: u32 = SCREEN_WIDTH as u32;
+const HEIGHT: u32 = SCREEN_HEIGHT as u32;
+
+// 2x is 288 rows tall, so 12 source lines (24 rows) go off the top and 12 off the bottom
+const CROP_LINES: u32 = (HEIGHT * 2 - PANEL_HEIGHT) / 4;
+
+/// How the 160x144 frame is fitted onto the 320x240 panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scaling {
+    /// 1:1 in the middle of the panel
+    Centred,
+    /// 2x, filling the width and losing 12 lines off the top and bottom
+    Crop2x,
+    /// 1.5x nearest neighbour, 240x216
+    Fit,
+    /// 1.5x with the in-between pixels blended from their neighbours
+    SmoothFit,
+}
+
+impl Scaling {
+    /// Where the scaled frame lands on the panel
+    pub fn area(&self) -> Rectangle {
+        let size = match self {
+            Scaling::Centred => Size::new(WIDTH, HEIGHT),
+            Scaling::Crop2x => Size::new(PANEL_WIDTH, PANEL_HEIGHT),
+            Scaling::Fit | Scaling::SmoothFit => Size::new(WIDTH * 3 / 2, HEIGHT * 3 / 2),
+        };
+
+        let origin = Point::new(
+            (PANEL_WIDTH - size.width) as i32 / 2,
+            (PANEL_HEIGHT - size.height) as i32 / 2,
+        );
+        Rectangle::new(origin, size)
+    }
+
+    /// Colour of the output pixel at x, y relative to the top left of `area`
+    pub fn pixel(&self, frame: &[u8], palette: &Palette, x: u32, y: u32) -> Rgb565 {
+        let shade = |x: u32, y: u32| palette.colour(frame[(y * WIDTH + x) as usize]);
+        let (y0, y1) = self.source_lines(y);
+
+        match self {
+            Scaling::Centred => shade(x, y0),
+            Scaling::Crop2x => shade(x / 2, y0),
+            Scaling::Fit => shade(x * 2 / 3, y0),
+            Scaling::SmoothFit => {
+                let (x0, x1) = smooth_taps(x);
+                blend([shade(x0, y0), shade(x1, y0), shade(x0, y1), shade(x1, y1)])
+            }
+        }
+    }
+
+    /// The frame lines an output row is made from, the same line twice unless it is blended
+    pub fn source_lines(&self, row: u32) -> (u32, u32) {
+        match self {
+            Scaling::Centred => (row, row),
+            Scaling::Crop2x => (row / 2 + CROP_LINES, row / 2 + CROP_LINES),
+            Scaling::Fit => (row * 2 / 3, row * 2 / 3),
+            Scaling::SmoothFit => smooth_taps(row),
+        }
+    }
+}
+
+// Every 2 source pixels become 3, the middle one made from both
+fn smooth_taps(i: u32) -> (u32, u32) {
+    let k = i / 3 * 2;
+    match i % 3 {
+        0 => (k, k),
+        1 => (k, k + 1),
+        _ => (k + 1, k + 1),
+    }
+}
+
+fn blend(colours: [Rgb565; 4]) -> Rgb565 {
+    let (r, g, b) = colours.iter().fold((0u16, 0u16, 0u16), |(r, g, b), colour| {
+        (r + colour.r() as u16, g + colour.g() as u16, b + colour.b() as u16)
+    });
+    Rgb565::new((r / 4) as u8, (g / 4) as u8, (b / 4) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADES: [Rgb565; 4] = Palette::HIGH_CONTRAST.bg;
+
+    // 1 pixel wide stripes of all four shades, with every third line black
+    fn test_frame() -> Vec<u8> {
+        (0..HEIGHT)
+            .flat_map(|y| (0..WIDTH).map(move |x| if y % 3 == 2 {3} else {(x % 4) as u8}))
+            .collect()
+    }
+
+    // Top left corner of the output, shades as digits and anything blended as '*'
+    fn render_corner(scaling: Scaling, width: u32, height: u32) -> String {
+        let frame = test_frame();
+        let mut out = String::new();
+        for y in 0..height {
+            for x in 0..width {
+                let colour = scaling.pixel(&frame, &Palette::HIGH_CONTRAST, x, y);
+                out.push(match SHADES.iter().position(|&shade| shade == colour) {
+                    Some(shade) => char::from_digit(shade as u32, 10).unwrap(),
+                    None => '*',
+                });
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    fn golden(rows: &[&str]) -> String {
+        rows.iter().map(|row| format!("{}\n", row)).collect()
+    }
+
+    #[test]
+    fn areas_fit_the_panel() {
+        assert_eq!(Scaling::Centred.area(), Rectangle::new(Point::new(80, 48), Size::new(160, 144)));
+        assert_eq!(Scaling::Crop2x.area(), Rectangle::new(Point::new(0, 0), Size::new(320, 240)));
+        assert_eq!(Scaling::Fit.area(), Rectangle::new(Point::new(40, 12), Size::new(240, 216)));
+        assert_eq!(Scaling::SmoothFit.area(), Scaling::Fit.area());
+    }
+
+    #[test]
+    fn centred_golden() {
+        assert_eq!(render_corner(Scaling::Centred, 10, 4), golden(&[
+            "0123012301",
+            "0123012301",
+            "3333333333",
+            "0123012301",
+        ]));
+    }
+
+    #[test]
+    fn crop_2x_golden() {
+        // Starts on frame line 12, which is a plain line
+        assert_eq!(render_corner(Scaling::Crop2x, 12, 6), golden(&[
+            "001122330011",
+            "001122330011",
+            "001122330011",
+            "001122330011",
+            "333333333333",
+            "333333333333",
+        ]));
+    }
+
+    #[test]
+    fn fit_golden() {
+        assert_eq!(render_corner(Scaling::Fit, 12, 6), golden(&[
+            "001223001223",
+            "001223001223",
+            "001223001223",
+            "333333333333",
+            "333333333333",
+            "001223001223",
+        ]));
+    }
+
+    #[test]
+    fn smooth_fit_golden() {
+        // Shade 1 half blended with black happens to land exactly on shade 2
+        assert_eq!(render_corner(Scaling::SmoothFit, 12, 6), golden(&[
+            "0*12*30*12*3",
+            "0*12*30*12*3",
+            "0*12*30*12*3",
+            "333333333333",
+            "**2**3**2**3",
+            "0*12*30*12*3",
+        ]));
+    }
+
+    #[test]
+    fn source_lines_cover_the_frame() {
+        assert_eq!(Scaling::Crop2x.source_lines(0), (12, 12));
+        assert_eq!(Scaling::Crop2x.source_lines(239), (131, 131));
+        assert_eq!(Scaling::Fit.source_lines(215), (143, 143));
+        assert_eq!(Scaling::SmoothFit.source_lines(4), (2, 3));
+    }
+
+    #[test]
+    fn smooth_fit_blends_neighbours() {
+        let mut frame = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT];
+        frame[1] = 3;
+
+        let colour = Scaling::SmoothFit.pixel(&frame, &Palette::HIGH_CONTRAST, 1, 0);
+        assert_eq!(colour, Rgb565::new(15, 31, 15));
+    }
+}
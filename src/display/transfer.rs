@@ -0,0 +1,382 @@
+use core::convert::Infallible;
+use core::fmt::Debug;
+use core::mem;
+use core::ops::Range;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::Rectangle,
+};
+
+use super::presenter::{PANEL_HEIGHT, PANEL_WIDTH};
+
+/// Pixels waiting to go out, as rectangles each with their own address window
+#[derive(Debug, Default)]
+pub struct Batch {
+    pub spans: Vec<(Rectangle, Range<usize>)>,
+    pub pixels: Vec<Rgb565>,
+}
+
+impl Batch {
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    fn clear(&mut self) {
+        self.spans.clear();
+        self.pixels.clear();
+    }
+}
+
+/// Sends batches to the panel in the background
+pub trait Transfer {
+    /// Hands a batch off, returning straight away
+    fn start(&mut self, batch: Batch);
+    /// Blocks until the batch passed to `start` has gone out and gives it back
+    fn finish(&mut self) -> Batch;
+}
+
+/// A DrawTarget that fills one batch while the other is being sent, so
+/// the emulator can get on with the next frame during the SPI DMA
+#[derive(Debug)]
+pub struct DoubleBuffered<T: Transfer> {
+    transfer: T,
+    back: Batch,
+    in_flight: bool,
+    // Pixels per batch, the two together have to fit in internal ram for DMA
+    capacity: usize,
+}
+
+impl<T: Transfer> DoubleBuffered<T> {
+    pub fn new(transfer: T, capacity: usize) -> Self {
+        assert!(capacity >= PANEL_WIDTH as usize, "batches have to hold at least a row");
+
+        DoubleBuffered {
+            transfer,
+            back: Batch {
+                spans: Vec::new(),
+                pixels: Vec::with_capacity(capacity),
+            },
+            in_flight: false,
+            capacity,
+        }
+    }
+
+    /// Sends everything drawn since the last flush, after waiting for the batch before it
+    pub fn flush(&mut self) {
+        if self.back.is_empty() {
+            return;
+        }
+
+        let mut front = self.wait();
+        front.clear();
+        mem::swap(&mut front, &mut self.back);
+
+        self.transfer.start(front);
+        self.in_flight = true;
+    }
+
+    // Gets the in flight batch back, or a new one the first time round
+    fn wait(&mut self) -> Batch {
+        if self.in_flight {
+            self.in_flight = false;
+            self.transfer.finish()
+        } else {
+            Batch {
+                spans: Vec::new(),
+                pixels: Vec::with_capacity(self.capacity),
+            }
+        }
+    }
+
+    /// Waits for everything to go out and hands back the transfer
+    pub fn into_inner(mut self) -> T {
+        self.flush();
+        self.wait();
+        self.transfer
+    }
+}
+
+impl<T: Transfer> OriginDimensions for DoubleBuffered<T> {
+    fn size(&self) -> Size {
+        Size::new(PANEL_WIDTH, PANEL_HEIGHT)
+    }
+}
+
+impl<T: Transfer> DrawTarget for DoubleBuffered<T> {
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, colour) in pixels {
+            self.fill_contiguous(&Rectangle::new(point, Size::new(1, 1)), core::iter::once(colour))?;
+        }
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colours: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let drawn = area.intersection(&self.bounding_box());
+        if drawn == *area {
+            self.push_area(&drawn, colours.into_iter());
+        } else {
+            // Anything off the panel still has its colour in the iterator, which has to be skipped
+            let colours = area.points()
+                .zip(colours)
+                .filter(|&(point, _)| drawn.contains(point))
+                .map(|(_, colour)| colour);
+            self.push_area(&drawn, colours);
+        }
+        Ok(())
+    }
+}
+
+impl<T: Transfer> DoubleBuffered<T> {
+    // Adds an area that's all on the panel, rows that don't fit in this batch start the next one
+    fn push_area<I>(&mut self, area: &Rectangle, mut colours: I)
+    where
+        I: Iterator<Item = Rgb565>,
+    {
+        let width = area.size.width as usize;
+        if width == 0 {
+            return;
+        }
+
+        let mut row = 0;
+        while row < area.size.height {
+            let free_rows = (self.capacity - self.back.pixels.len()) / width;
+            if free_rows == 0 {
+                self.flush();
+                continue;
+            }
+
+            let rows = (free_rows as u32).min(area.size.height - row);
+            let start = self.back.pixels.len();
+            self.back.pixels.extend(colours.by_ref().take(width * rows as usize));
+            let span = Rectangle::new(area.top_left + Point::new(0, row as i32), Size::new(area.size.width, rows));
+            self.back.spans.push((span, start..self.back.pixels.len()));
+            row += rows;
+        }
+    }
+}
+
+/// Sends batches from a worker thread that owns the display, which blocks on the
+/// SPI DMA while the emulator thread carries on
+#[derive(Debug)]
+pub struct ThreadTransfer {
+    // Taken on drop to close the channel
+    batches: Option<Sender<Batch>>,
+    finished: Receiver<Batch>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl ThreadTransfer {
+    pub fn new<D>(mut display: D) -> Self
+    where
+        D: DrawTarget<Color = Rgb565> + Send + 'static,
+        D::Error: Debug,
+    {
+        let (batches, incoming) = mpsc::channel::<Batch>();
+        let (done, finished) = mpsc::channel();
+
+        let worker = thread::Builder::new()
+            .name("display".into())
+            .stack_size(8 * 1024)
+            .spawn(move || {
+                for batch in incoming {
+                    for (area, range) in &batch.spans {
+                        if let Err(e) = display.fill_contiguous(area, batch.pixels[range.clone()].iter().copied()) {
+                            log::error!("Display transfer failed: {:?}", e);
+                        }
+                    }
+
+                    if done.send(batch).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("display thread");
+
+        ThreadTransfer {
+            batches: Some(batches),
+            finished,
+            worker: Some(worker),
+        }
+    }
+}
+
+impl Transfer for ThreadTransfer {
+    fn start(&mut self, batch: Batch) {
+        self.batches.as_ref()
+            .and_then(|batches| batches.send(batch).ok())
+            .expect("display thread stopped");
+    }
+
+    fn finish(&mut self) -> Batch {
+        self.finished.recv().expect("display thread stopped")
+    }
+}
+
+impl Drop for ThreadTransfer {
+    fn drop(&mut self) {
+        // Closing the channel lets the worker run out of batches and stop
+        self.batches = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Start(*const Rgb565, usize),
+        Finish(*const Rgb565),
+    }
+
+    // Hands batches straight back, noting which buffer went where
+    #[derive(Default)]
+    struct RecordingTransfer {
+        events: Vec<Event>,
+        in_flight: Option<Batch>,
+    }
+
+    impl Transfer for RecordingTransfer {
+        fn start(&mut self, batch: Batch) {
+            assert!(self.in_flight.is_none(), "started while a batch was in flight");
+            self.events.push(Event::Start(batch.pixels.as_ptr(), batch.pixels.len()));
+            self.in_flight = Some(batch);
+        }
+
+        fn finish(&mut self) -> Batch {
+            let batch = self.in_flight.take().expect("finished with nothing in flight");
+            self.events.push(Event::Finish(batch.pixels.as_ptr()));
+            batch
+        }
+    }
+
+    fn draw_rows(display: &mut DoubleBuffered<RecordingTransfer>, rows: u32, colour: Rgb565) {
+        let area = Rectangle::new(Point::zero(), Size::new(PANEL_WIDTH, rows));
+        display.fill_solid(&area, colour).unwrap();
+    }
+
+    #[test]
+    fn buffers_alternate() {
+        let mut display = DoubleBuffered::new(RecordingTransfer::default(), PANEL_WIDTH as usize * 4);
+        for _ in 0..3 {
+            draw_rows(&mut display, 1, Rgb565::RED);
+            display.flush();
+        }
+
+        let events = &display.transfer.events;
+        let first = match events[0] {
+            Event::Start(buffer, 320) => buffer,
+            ref event => panic!("{:?}", event),
+        };
+        let second = match events[2] {
+            Event::Start(buffer, 320) => buffer,
+            ref event => panic!("{:?}", event),
+        };
+
+        // The second frame can't start until the first is back
+        assert_eq!(events[1], Event::Finish(first));
+        assert_ne!(first, second);
+        assert_eq!(events[3], Event::Finish(second));
+        assert_eq!(events[4], Event::Start(first, 320));
+        assert_eq!(events.len(), 5);
+    }
+
+    #[test]
+    fn nothing_drawn_sends_nothing() {
+        let mut display = DoubleBuffered::new(RecordingTransfer::default(), PANEL_WIDTH as usize * 4);
+        display.flush();
+
+        assert!(display.transfer.events.is_empty());
+    }
+
+    #[test]
+    fn full_batches_go_out_early() {
+        let mut display = DoubleBuffered::new(RecordingTransfer::default(), PANEL_WIDTH as usize * 4);
+        draw_rows(&mut display, 10, Rgb565::RED);
+
+        // 4 + 4 rows sent while drawing, the last 2 waiting for a flush
+        let starts: Vec<usize> = display.transfer.events.iter()
+            .filter_map(|event| match event {
+                Event::Start(_, len) => Some(*len),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(starts, [320 * 4, 320 * 4]);
+        assert_eq!(display.back.spans, [(Rectangle::new(Point::new(0, 8), Size::new(320, 2)), 0..640)]);
+    }
+
+    #[test]
+    fn clipped_pixels_skip_their_colours() {
+        let mut display = DoubleBuffered::new(RecordingTransfer::default(), PANEL_WIDTH as usize * 4);
+        let colours = (0..8).map(|i| Rgb565::new(i, 0, 0));
+
+        // 4x2 hanging two pixels off the left edge
+        display.fill_contiguous(&Rectangle::new(Point::new(-2, 0), Size::new(4, 2)), colours.clone()).unwrap();
+        assert_eq!(display.back.spans, [(Rectangle::new(Point::zero(), Size::new(2, 2)), 0..4)]);
+        assert_eq!(display.back.pixels, [2, 3, 6, 7].map(|i| Rgb565::new(i, 0, 0)));
+
+        // And off the bottom right corner
+        display.fill_contiguous(&Rectangle::new(Point::new(318, 239), Size::new(4, 2)), colours).unwrap();
+        assert_eq!(display.back.spans[1], (Rectangle::new(Point::new(318, 239), Size::new(2, 1)), 4..6));
+        assert_eq!(display.back.pixels[4..], [0, 1].map(|i| Rgb565::new(i, 0, 0)));
+    }
+
+    #[derive(Clone)]
+    struct SharedDisplay(std::sync::Arc<std::sync::Mutex<Vec<Rgb565>>>);
+
+    impl OriginDimensions for SharedDisplay {
+        fn size(&self) -> Size {
+            Size::new(PANEL_WIDTH, PANEL_HEIGHT)
+        }
+    }
+
+    impl DrawTarget for SharedDisplay {
+        type Color = Rgb565;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            let mut panel = self.0.lock().unwrap();
+            for Pixel(point, colour) in pixels {
+                panel[(point.y * PANEL_WIDTH as i32 + point.x) as usize] = colour;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn worker_thread_draws_everything() {
+        let panel = SharedDisplay(Default::default());
+        panel.0.lock().unwrap().resize((PANEL_WIDTH * PANEL_HEIGHT) as usize, Rgb565::BLACK);
+
+        let mut display = DoubleBuffered::new(ThreadTransfer::new(panel.clone()), PANEL_WIDTH as usize * 16);
+        display.fill_solid(&Rectangle::new(Point::new(10, 20), Size::new(100, 50)), Rgb565::GREEN).unwrap();
+        display.flush();
+        display.fill_solid(&Rectangle::new(Point::new(10, 30), Size::new(10, 10)), Rgb565::BLUE).unwrap();
+        drop(display.into_inner());
+
+        let panel = panel.0.lock().unwrap();
+        assert_eq!(panel[20 * 320 + 10], Rgb565::GREEN);
+        assert_eq!(panel[69 * 320 + 109], Rgb565::GREEN);
+        assert_eq!(panel[30 * 320 + 10], Rgb565::BLUE);
+        assert_eq!(panel[70 * 320 + 10], Rgb565::BLACK);
+    }
+}
@@ -0,0 +1,367 @@
+use display_interface_spi::SPIInterfaceNoCS;
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+};
+
+use esp_idf_svc::{
+    eventloop::EspSystemEventLoop,
+    hal::{gpio, prelude::Peripherals},
+    nvs::EspDefaultNvsPartition,
+    wifi::{AuthMethod, BlockingWifi, ClientConfiguration, Configuration, EspWifi},
+};
+
+use esp_idf_hal::{
+    cpu::Core,
+    delay::{Ets, FreeRtos},
+    gpio::InputPin,
+    modem::Modem,
+    spi::{config::{Config, DriverConfig}, Dma, SpiDeviceDriver, SpiDriver}, units::MegaHertz,
+    task::thread::ThreadSpawnConfiguration,
+};
+
+use mipidsi::Builder;
+
+use std::error::Error;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use esp_idf_sys::{self as _, EspError}; // If using the `binstart` feature of `esp-idf-sys`, always keep this module imported
+
+use cyd_gameboy::audio::{dac::DacSink, AudioSink};
+use cyd_gameboy::display::{
+    palette::Palette,
+    presenter::{FramePresenter, Presenter, PANEL_WIDTH},
+    scaling::Scaling,
+    transfer::{DoubleBuffered, ThreadTransfer, Transfer},
+};
+use cyd_gameboy::gb::{cartridge::Cartridge, cpu::Cpu, ram::MemoryBus};
+use cyd_gameboy::input::{
+    calibration::{draw_crosshair, store::CalibrationStore, Calibration, MAX_TAP_ERROR, TARGETS},
+    overlay::Overlay,
+    xpt2046::Xpt2046,
+};
+use cyd_gameboy::link::tcp::TcpLink;
+use cyd_gameboy::save::{self, store::SaveStore, Autosave};
+
+static ROM: &[u8] = include_bytes!(env!("CYD_GAMEBOY_ROM"));
+
+// Rows per display batch, two of these live in internal ram at once
+const BATCH_ROWS: usize = 24;
+
+// Plenty for the 8-bit DAC, and less work than 44.1kHz
+const AUDIO_SAMPLE_RATE: u32 = 32_000;
+
+// How often the touch screen is checked for a button to wake the game from STOP
+const STOPPED_POLL_MS: u32 = 20;
+
+// Serial output without a newline is logged anyway once it gets this long
+const MAX_SERIAL_LINE: usize = 256;
+
+// The link cable goes over Wi-Fi, either `listen:<port>` or the other side's `<host>:<port>`.
+// Left unset, Wi-Fi stays off and the link port is unplugged.
+const LINK: Option<&str> = option_env!("CYD_GAMEBOY_LINK");
+const WIFI_SSID: &str = match option_env!("CYD_GAMEBOY_WIFI_SSID") {
+    Some(ssid) => ssid,
+    None => "",
+};
+const WIFI_PASSWORD: &str = match option_env!("CYD_GAMEBOY_WIFI_PASSWORD") {
+    Some(password) => password,
+    None => "",
+};
+
+pub fn main() -> Result<(), Box<dyn Error>> {
+    // It is necessary to call this function once. Otherwise some patches to the runtime
+    // implemented by esp-idf-sys might not link properly. See https://github.com/esp-rs/esp-idf-template/issues/71
+    esp_idf_sys::link_patches();
+    esp_idf_svc::log::EspLogger::initialize_default();
+
+    let peripherals = Peripherals::take().unwrap();
+    let pins = peripherals.pins;
+
+    // Reset, -1 or 4
+    let rst = gpio::PinDriver::output(pins.gpio4)?;
+    // Data Command control pin
+    let dc = gpio::PinDriver::output(pins.gpio2)?;
+
+    // Espressif built-in delay provider for small delays
+    let mut delay = Ets;
+
+    // Pin 14, Serial Clock
+    let sclk = pins.gpio14;
+    let spi = peripherals.spi2;
+    // Pin 13, MOSI, Master Out Slave In
+    let sdo = pins.gpio13;
+    // Pin 12, MISO, Master In Slave Out
+    let sdi = pins.gpio12;
+
+    let cs = pins.gpio15;
+
+    let di = SPIInterfaceNoCS::new(
+        SpiDeviceDriver::new_single(
+            spi,
+            sclk,
+            sdo,
+            Some(sdi),
+            Some(cs),
+            &DriverConfig::new().dma(Dma::Auto(4096)),
+            &Config::new().baudrate(MegaHertz(40).into()),
+        )?,
+        dc,
+    );
+
+    let mut display = Builder::ili9341_rgb565(di)
+        .with_color_order(mipidsi::ColorOrder::Rgb)
+        .with_orientation(mipidsi::options::Orientation::LandscapeInverted(false))  // Mirror on text
+        .init(&mut delay, Some(rst))
+        .map_err(|_| Box::<dyn Error>::from("display init"))?;
+
+    // Pin 21, Backlight
+    let mut bl = gpio::PinDriver::output(pins.gpio21)?;
+    // Turn on backlight, it goes off again while the game is in STOP
+    bl.set_high()?;
+
+    // fill the screen with black
+    // TO check: this is quite slow somehow?
+    display
+         .clear(Rgb565::BLACK)
+         .map_err(|_| Box::<dyn Error>::from("clear display"))?;
+
+    // The touch controller has its own bus, pins 25 clock, 32 MOSI, 39 MISO, 33 CS and 36 for the pen interrupt
+    let touch_spi = SpiDeviceDriver::new(
+        SpiDriver::new(peripherals.spi3, pins.gpio25, pins.gpio32, Some(pins.gpio39), &DriverConfig::new())?,
+        Some(pins.gpio33),
+        &Config::new().baudrate(MegaHertz(2).into()),
+    )?;
+    let touch_irq = gpio::PinDriver::input(pins.gpio36.downgrade_input())?;
+    let mut touch = Xpt2046::new(touch_spi, touch_irq, Calibration::default());
+    // Holding the screen down while it starts up asks for the calibration again
+    let recalibrate = touch.read_raw()?.is_some();
+
+    // The display and audio threads get the second core, blocking on the SPI and I2S DMA
+    // while the emulator carries on with the next frame on this one
+    ThreadSpawnConfiguration {
+        pin_to_core: Some(Core::Core1),
+        ..Default::default()
+    }.set()?;
+    let mut display = DoubleBuffered::new(ThreadTransfer::new(display), PANEL_WIDTH as usize * BATCH_ROWS);
+    let mut speaker = DacSink::new(AUDIO_SAMPLE_RATE)?;
+    ThreadSpawnConfiguration::default().set()?;
+
+    let nvs = EspDefaultNvsPartition::take()?;
+    let mut calibration_store = CalibrationStore::new(nvs.clone())?;
+    match calibration_store.load()? {
+        Some(calibration) if !recalibrate => touch.set_calibration(calibration),
+        _ => {
+            let calibration = calibrate(&mut touch, &mut display)?;
+            calibration_store.save(&calibration)?;
+            touch.set_calibration(calibration);
+        }
+    }
+
+    // Kept around for as long as the link is, dropping it turns Wi-Fi off
+    let (_wifi, links) = match LINK {
+        Some(peer) => (Some(connect_wifi(peripherals.modem, nvs)?), Some(open_link(peer))),
+        None => (None, None),
+    };
+
+    let cartridge = Cartridge::new(ROM.to_vec())?;
+    log::info!("Loaded {}", cartridge.header.title);
+
+    let palette = Palette::for_cartridge(&cartridge.header);
+    let save_key = save::key(&cartridge.header);
+    let mut cpu = Cpu::new(MemoryBus::new(cartridge));
+    cpu.bus_mut().apu.set_sample_rate(AUDIO_SAMPLE_RATE);
+
+    // Battery backed ram, picked up from the last time this game was played
+    let mut save_store = SaveStore::new()?;
+    let saved = save_store.load(&save_key)?.unwrap_or_default();
+    if !saved.is_empty() {
+        log::info!("Loaded {} bytes of save data", saved.len());
+        cpu.bus_mut().load_save_data(&saved);
+    }
+    let mut autosave = Autosave::new(cpu.bus(), &saved);
+    // Coloured the way a GBC would, set_palette swaps it at any point
+    let mut presenter = Presenter::new(palette, Scaling::Fit);
+
+    // Has to be drawn again after set_scaling, which clears the panel
+    let overlay = Overlay::for_scaling(presenter.scaling());
+    let mut held = 0;
+    overlay
+        .draw(&mut display, held)
+        .map_err(|_| Box::<dyn Error>::from("draw overlay"))?;
+
+    let mut frames = 0u32;
+    let mut bytes_sent = 0u32;
+    let mut serial_line = String::new();
+    let mut stopped = false;
+
+    loop {
+        if cpu.is_stopped() {
+            // Nothing runs until a button is pressed, so the screen goes dark and the emulator idles
+            if !stopped {
+                log::info!("Stopped, waiting for a button");
+                bl.set_low()?;
+                stopped = true;
+            }
+            poll_touch(&mut touch, &overlay, cpu.bus_mut())?;
+            cpu.step();
+            FreeRtos::delay_ms(STOPPED_POLL_MS);
+            continue;
+        } else if stopped {
+            bl.set_high()?;
+            stopped = false;
+        }
+
+        cpu.step();
+
+        if let Some(frame) = cpu.bus_mut().ppu.take_frame() {
+            presenter
+                .present(frame, &mut display)
+                .map_err(|_| Box::<dyn Error>::from("present frame"))?;
+
+            // Touch is only polled once a frame, about as often as games read the buttons
+            let pressed = poll_touch(&mut touch, &overlay, cpu.bus_mut())?;
+            if pressed != held {
+                held = pressed;
+                overlay
+                    .draw(&mut display, held)
+                    .map_err(|_| Box::<dyn Error>::from("draw overlay"))?;
+            }
+            display.flush();
+
+            if let Some(link) = links.as_ref().and_then(|links| links.try_recv().ok()) {
+                cpu.bus_mut().serial.connect(Box::new(link));
+            }
+
+            // Anything printed through the serial port, test roms report their results this way
+            serial_line.push_str(&cpu.bus_mut().serial.take_output());
+            while let Some(end) = serial_line.find('\n') {
+                log::info!("Serial: {}", serial_line[..end].trim_end());
+                serial_line.drain(..=end);
+            }
+            if serial_line.len() > MAX_SERIAL_LINE {
+                log::info!("Serial: {}", serial_line);
+                serial_line.clear();
+            }
+
+            if let Some(data) = autosave.frame(cpu.bus()) {
+                save_store.save(&save_key, &data)?;
+                log::info!("Saved {} bytes", data.len());
+            }
+
+            speaker
+                .write(cpu.bus_mut().apu.drain_samples().as_slice())
+                .map_err(|_| Box::<dyn Error>::from("play audio"))?;
+
+            frames += 1;
+            bytes_sent += presenter.stats().bytes;
+            if frames == 60 {
+                let full = presenter.scaling().area().size;
+                log::debug!(
+                    "Sent {} bytes a frame, {}% of full frames",
+                    bytes_sent / frames,
+                    bytes_sent as u64 * 100 / (full.width * full.height * 2 * frames) as u64
+                );
+                frames = 0;
+                bytes_sent = 0;
+            }
+        }
+    }
+}
+
+// Presses the buttons under wherever the screen is being touched, returning them
+fn poll_touch(touch: &mut Xpt2046, overlay: &Overlay, bus: &mut MemoryBus) -> Result<u8, EspError> {
+    let pressed = touch.read()?.map_or(0, |point| overlay.buttons_at(point));
+    bus.joypad.set_pressed(pressed, &mut bus.interrupts);
+    Ok(pressed)
+}
+
+// Has the user tap each crosshair in turn, starting over until the taps agree with each other
+fn calibrate<T: Transfer>(touch: &mut Xpt2046, display: &mut DoubleBuffered<T>) -> Result<Calibration, Box<dyn Error>> {
+    // Let go of the screen from holding it at startup first
+    while touch.read_raw()?.is_some() {
+        FreeRtos::delay_ms(10);
+    }
+
+    loop {
+        let mut taps = Vec::with_capacity(TARGETS.len());
+        for target in TARGETS {
+            display.clear(Rgb565::BLACK)?;
+            draw_crosshair(display, target, Rgb565::WHITE)?;
+            display.flush();
+            taps.push((wait_for_tap(touch)?, target));
+        }
+
+        display.clear(Rgb565::BLACK)?;
+        display.flush();
+        match Calibration::from_points(&taps) {
+            Some(calibration) if calibration.max_error(&taps) <= MAX_TAP_ERROR => {
+                log::info!("Touch calibrated, {:?}", calibration);
+                return Ok(calibration);
+            },
+            _ => log::warn!("Calibration taps didn't line up, starting again"),
+        }
+    }
+}
+
+// Raw readings averaged over a press, returning once it is let go
+fn wait_for_tap(touch: &mut Xpt2046) -> Result<(u16, u16), EspError> {
+    let (mut x, mut y, mut count) = (0u32, 0u32, 0u32);
+    while count == 0 {
+        while let Some((raw_x, raw_y)) = touch.read_raw()? {
+            x += raw_x as u32;
+            y += raw_y as u32;
+            count += 1;
+            FreeRtos::delay_ms(10);
+        }
+        FreeRtos::delay_ms(10);
+    }
+    Ok(((x / count) as u16, (y / count) as u16))
+}
+
+fn connect_wifi(modem: Modem, nvs: EspDefaultNvsPartition) -> Result<BlockingWifi<EspWifi<'static>>, Box<dyn Error>> {
+    let sys_loop = EspSystemEventLoop::take()?;
+    let mut wifi = BlockingWifi::wrap(EspWifi::new(modem, sys_loop.clone(), Some(nvs))?, sys_loop)?;
+
+    wifi.set_configuration(&Configuration::Client(ClientConfiguration {
+        ssid: WIFI_SSID.try_into().map_err(|_| "Wi-Fi SSID too long")?,
+        password: WIFI_PASSWORD.try_into().map_err(|_| "Wi-Fi password too long")?,
+        auth_method: if WIFI_PASSWORD.is_empty() {AuthMethod::None} else {AuthMethod::WPA2Personal},
+        ..Default::default()
+    }))?;
+    wifi.start()?;
+    wifi.connect()?;
+    wifi.wait_netif_up()?;
+
+    log::info!("Wi-Fi up at {}", wifi.wifi().sta_netif().get_ip_info()?.ip);
+    Ok(wifi)
+}
+
+// Sets up the link cable in the background, the game runs unplugged until it arrives
+fn open_link(peer: &'static str) -> Receiver<TcpLink> {
+    let (sender, receiver) = mpsc::channel();
+    thread::Builder::new()
+        .name("link setup".into())
+        .stack_size(8192)
+        .spawn(move || loop {
+            let link = match peer.strip_prefix("listen:") {
+                Some(port) => TcpLink::listen(format!("0.0.0.0:{}", port)),
+                None => TcpLink::connect(peer),
+            };
+            match link {
+                Ok(link) => {
+                    log::info!("Link cable connected");
+                    let _ = sender.send(link);
+                    return;
+                },
+                Err(e) => {
+                    log::warn!("Link cable to {} failed, trying again: {}", peer, e);
+                    thread::sleep(Duration::from_secs(1));
+                }
+            }
+        })
+        .expect("link setup thread");
+    receiver
+}
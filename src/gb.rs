@@ -2,3 +2,12 @@ pub mod cpu;
 pub mod registers;
 pub mod ram;
 pub mod instructions;
+pub mod interrupts;
+pub mod cartridge;
+pub mod mapper;
+pub mod clock;
+pub mod ppu;
+pub mod apu;
+pub mod timer;
+pub mod joypad;
+pub mod serial;
@@ -0,0 +1,442 @@
+pub mod square;
+pub mod wave;
+pub mod noise;
+
+use square::SquareChannel;
+use wave::WaveChannel;
+use noise::NoiseChannel;
+
+pub const NR10_ADDRESS: u16 = 0xFF10;
+pub const NR50_ADDRESS: u16 = 0xFF24;
+pub const NR51_ADDRESS: u16 = 0xFF25;
+pub const NR52_ADDRESS: u16 = 0xFF26;
+pub const WAVE_RAM_START: u16 = 0xFF30;
+pub const WAVE_RAM_END: u16 = 0xFF3F;
+
+pub const CPU_CLOCK_HZ: u32 = 4_194_304;
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+
+// Stereo pairs held until someone drains them, anything past this is dropped
+const MAX_BUFFERED_FRAMES: usize = 4096;
+
+const NR52_POWER: u8 = 0b1000_0000;
+
+// How much the output capacitor keeps each cpu cycle, it slowly pulls out any DC offset
+const CAPACITOR_CHARGE: f32 = 0.999958;
+
+/// Counts down to switching its channel off, clocked at 256Hz by the frame sequencer
+#[derive(Debug)]
+struct LengthCounter {
+    max: u16,
+    counter: u16,
+    enabled: bool,
+}
+
+impl LengthCounter {
+    fn new(max: u16) -> Self {
+        LengthCounter {
+            max,
+            counter: 0,
+            enabled: false,
+        }
+    }
+
+    fn load(&mut self, length: u16) {
+        self.counter = self.max - length;
+    }
+
+    /// True once the counter runs out
+    fn clock(&mut self) -> bool {
+        if self.enabled && self.counter > 0 {
+            self.counter -= 1;
+            self.counter == 0
+        } else {
+            false
+        }
+    }
+
+    /// Handles the length enable and trigger bits of an NRx4 write, true if the channel has to stop.
+    /// `odd_step` is set when the next frame sequencer step won't clock lengths, which gets an extra clock in.
+    fn write_control(&mut self, enable: bool, trigger: bool, odd_step: bool) -> bool {
+        let was_enabled = self.enabled;
+        self.enabled = enable;
+
+        let mut expired = false;
+        if odd_step && !was_enabled && enable && self.counter > 0 {
+            self.counter -= 1;
+            expired = self.counter == 0 && !trigger;
+        }
+
+        if trigger && self.counter == 0 {
+            self.counter = if enable && odd_step {self.max - 1} else {self.max};
+        }
+        expired
+    }
+}
+
+/// Volume that steps up or down at 64Hz
+#[derive(Debug, Default)]
+struct Envelope {
+    initial: u8,
+    increase: bool,
+    period: u8,
+    volume: u8,
+    timer: u8,
+}
+
+impl Envelope {
+    fn write(&mut self, val: u8) {
+        self.initial = val >> 4;
+        self.increase = val & 0b0000_1000 != 0;
+        self.period = val & 0b0000_0111;
+    }
+
+    // The top 5 bits of NRx2 double as the DAC power
+    fn dac_enabled(&self) -> bool {
+        self.initial != 0 || self.increase
+    }
+
+    fn trigger(&mut self) {
+        self.volume = self.initial;
+        self.timer = if self.period == 0 {8} else {self.period};
+    }
+
+    fn clock(&mut self) {
+        if self.period == 0 {
+            return;
+        }
+
+        // Written with a period before any trigger the timer is still at 0, so it reloads rather than wrapping
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer == 0 {
+            self.timer = self.period;
+            if self.increase && self.volume < 15 {
+                self.volume += 1;
+            } else if !self.increase && self.volume > 0 {
+                self.volume -= 1;
+            }
+        }
+    }
+}
+
+/// The four sound channels, mixed down to stereo samples at a configurable rate
+#[derive(Debug)]
+pub struct Apu {
+    square1: SquareChannel,
+    square2: SquareChannel,
+    wave: WaveChannel,
+    noise: NoiseChannel,
+
+    // Raw NR10-NR51 for reading back, the bus masks off the write only bits
+    registers: [u8; (NR52_ADDRESS - NR10_ADDRESS) as usize],
+    powered: bool,
+    // Next of the 8 frame sequencer steps
+    frame_step: u8,
+
+    sample_rate: u32,
+    // Counts up by the sample rate every cpu cycle, a sample is due each time it passes the cpu clock
+    sample_clock: u32,
+    charge_factor: f32,
+    capacitors: [f32; 2],
+    // Interleaved left and right
+    samples: Vec<i16>,
+}
+
+impl Apu {
+    pub fn new(sample_rate: u32) -> Self {
+        let mut apu = Apu {
+            square1: SquareChannel::with_sweep(),
+            square2: SquareChannel::new(),
+            wave: WaveChannel::new(),
+            noise: NoiseChannel::new(),
+            registers: [0; (NR52_ADDRESS - NR10_ADDRESS) as usize],
+            powered: true,
+            frame_step: 0,
+            sample_rate,
+            sample_clock: 0,
+            charge_factor: 1.0,
+            capacitors: [0.0; 2],
+            samples: Vec::with_capacity(MAX_BUFFERED_FRAMES * 2),
+        };
+        apu.set_sample_rate(sample_rate);
+
+        // Volume and panning the boot rom leaves behind
+        apu.write_register(NR50_ADDRESS, 0x77);
+        apu.write_register(NR51_ADDRESS, 0xF3);
+        apu
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.sample_clock = 0;
+        self.charge_factor = CAPACITOR_CHARGE.powf(CPU_CLOCK_HZ as f32 / sample_rate as f32);
+    }
+
+    /// Hands out the stereo samples made since the last call, interleaved left then right
+    pub fn drain_samples(&mut self) -> std::vec::Drain<'_, i16> {
+        self.samples.drain(..)
+    }
+
+    pub fn read_register(&self, address: u16) -> u8 {
+        match address {
+            NR52_ADDRESS => {
+                let power = if self.powered {NR52_POWER} else {0};
+                let channels = [self.square1.enabled, self.square2.enabled, self.wave.enabled, self.noise.enabled]
+                    .iter()
+                    .enumerate()
+                    .fold(0, |bits, (i, &enabled)| bits | (enabled as u8) << i);
+                power | channels
+            },
+            WAVE_RAM_START..=WAVE_RAM_END => self.wave.read_ram(address - WAVE_RAM_START),
+            NR10_ADDRESS..=NR51_ADDRESS => self.registers[(address - NR10_ADDRESS) as usize],
+            _ => 0xFF
+        }
+    }
+
+    pub fn write_register(&mut self, address: u16, val: u8) {
+        match address {
+            NR52_ADDRESS => self.set_power(val & NR52_POWER != 0),
+            WAVE_RAM_START..=WAVE_RAM_END => self.wave.write_ram(address - WAVE_RAM_START, val),
+            // Powered down, only the DMG's length counters can still be written
+            NR10_ADDRESS..=NR51_ADDRESS if !self.powered => match address - NR10_ADDRESS {
+                0x01 => self.square1.load_length(val),
+                0x06 => self.square2.load_length(val),
+                0x0B => self.wave.load_length(val),
+                0x10 => self.noise.load_length(val),
+                _ => {}
+            },
+            NR10_ADDRESS..=NR51_ADDRESS => {
+                let index = address - NR10_ADDRESS;
+                self.registers[index as usize] = val;
+
+                let odd_step = self.frame_step & 1 == 1;
+                match index {
+                    0x00..=0x04 => self.square1.write(index as u8, val, odd_step),
+                    0x06..=0x09 => self.square2.write((index - 0x05) as u8, val, odd_step),
+                    0x0A..=0x0E => self.wave.write((index - 0x0A) as u8, val, odd_step),
+                    0x10..=0x13 => self.noise.write((index - 0x0F) as u8, val, odd_step),
+                    // NR50 and NR51 are only read back when mixing
+                    _ => {}
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /// Called on the falling edge of DIV bit 4, 512 times a second
+    pub fn clock_frame_sequencer(&mut self) {
+        if !self.powered {
+            return;
+        }
+
+        if self.frame_step & 1 == 0 {
+            self.square1.clock_length();
+            self.square2.clock_length();
+            self.wave.clock_length();
+            self.noise.clock_length();
+        }
+        if self.frame_step == 2 || self.frame_step == 6 {
+            self.square1.clock_sweep();
+        }
+        if self.frame_step == 7 {
+            self.square1.clock_envelope();
+            self.square2.clock_envelope();
+            self.noise.clock_envelope();
+        }
+
+        self.frame_step = (self.frame_step + 1) % 8;
+    }
+
+    pub fn tick(&mut self, cycles: u8) {
+        if self.powered {
+            self.square1.step(cycles as u32);
+            self.square2.step(cycles as u32);
+            self.wave.step(cycles as u32);
+            self.noise.step(cycles as u32);
+        }
+
+        self.sample_clock += cycles as u32 * self.sample_rate;
+        while self.sample_clock >= CPU_CLOCK_HZ {
+            self.sample_clock -= CPU_CLOCK_HZ;
+            self.push_sample();
+        }
+    }
+
+    fn set_power(&mut self, on: bool) {
+        if on && !self.powered {
+            self.frame_step = 0;
+        } else if !on && self.powered {
+            // Everything but wave ram is cleared
+            self.square1 = SquareChannel::with_sweep();
+            self.square2 = SquareChannel::new();
+            self.wave.power_off();
+            self.noise = NoiseChannel::new();
+            self.registers = [0; (NR52_ADDRESS - NR10_ADDRESS) as usize];
+        }
+        self.powered = on;
+    }
+
+    fn push_sample(&mut self) {
+        // Each DAC turns 0-15 into -1.0 to 1.0, or sits at 0 while switched off
+        let dacs = [
+            dac(self.square1.dac_enabled(), self.square1.output()),
+            dac(self.square2.dac_enabled(), self.square2.output()),
+            dac(self.wave.dac_enabled(), self.wave.output()),
+            dac(self.noise.dac_enabled(), self.noise.output()),
+        ];
+
+        let nr50 = self.registers[(NR50_ADDRESS - NR10_ADDRESS) as usize];
+        let nr51 = self.registers[(NR51_ADDRESS - NR10_ADDRESS) as usize];
+
+        // NR51 has the left enables in the top nibble and the right in the bottom
+        let mut frame = [0i16; 2];
+        for (side, (panning, volume)) in [(nr51 >> 4, (nr50 >> 4) & 0b111), (nr51, nr50 & 0b111)].into_iter().enumerate() {
+            let mixed: f32 = dacs.iter()
+                .enumerate()
+                .filter(|&(channel, _)| panning & (1 << channel) != 0)
+                .map(|(_, &level)| level)
+                .sum();
+            let level = mixed / 4.0 * (volume + 1) as f32 / 8.0;
+
+            let filtered = level - self.capacitors[side];
+            self.capacitors[side] = level - filtered * self.charge_factor;
+            frame[side] = (filtered * i16::MAX as f32) as i16;
+        }
+
+        if self.samples.len() < MAX_BUFFERED_FRAMES * 2 {
+            self.samples.extend_from_slice(&frame);
+        }
+    }
+}
+
+impl Default for Apu {
+    fn default() -> Self {
+        Apu::new(DEFAULT_SAMPLE_RATE)
+    }
+}
+
+fn dac(enabled: bool, output: u8) -> f32 {
+    if enabled {
+        1.0 - output as f32 / 7.5
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NR11_ADDRESS: u16 = 0xFF11;
+    const NR12_ADDRESS: u16 = 0xFF12;
+    const NR14_ADDRESS: u16 = 0xFF14;
+
+    fn clock_envelopes(apu: &mut Apu) {
+        for _ in 0..8 {
+            apu.clock_frame_sequencer();
+        }
+    }
+
+    #[test]
+    fn envelope_period_written_before_a_trigger() {
+        let mut apu = Apu::default();
+        apu.write_register(NR12_ADDRESS, 0xF3);
+        clock_envelopes(&mut apu);
+        // The timer reloads with the period, and with nothing triggered the volume stays at 0
+        assert_eq!(apu.square1.envelope.timer, 3);
+        assert_eq!(apu.square1.envelope.volume, 0);
+
+        // Triggered, it starts at the top and steps down every 3 clocks
+        apu.write_register(NR14_ADDRESS, 0x80);
+        assert_eq!(apu.square1.envelope.volume, 15);
+        for _ in 0..2 {
+            clock_envelopes(&mut apu);
+        }
+        assert_eq!(apu.square1.envelope.volume, 15);
+        clock_envelopes(&mut apu);
+        assert_eq!(apu.square1.envelope.volume, 14);
+
+        // And again once a power cycle has cleared the channel
+        apu.write_register(NR52_ADDRESS, 0x00);
+        apu.write_register(NR52_ADDRESS, NR52_POWER);
+        apu.write_register(NR12_ADDRESS, 0xF3);
+        clock_envelopes(&mut apu);
+        assert_eq!(apu.square1.envelope.timer, 3);
+        assert_eq!(apu.square1.envelope.volume, 0);
+    }
+
+    #[test]
+    fn envelope_steps_once_per_period() {
+        let mut envelope = Envelope::default();
+        envelope.write(0b1010_0010);
+        envelope.trigger();
+        assert_eq!(envelope.volume, 10);
+
+        envelope.clock();
+        assert_eq!(envelope.volume, 10);
+        envelope.clock();
+        assert_eq!(envelope.volume, 9);
+
+        // Stops at the ends rather than wrapping
+        envelope.write(0b0000_1001);
+        envelope.volume = 15;
+        envelope.clock();
+        assert_eq!(envelope.volume, 15);
+
+        // A period of 0 leaves it alone
+        envelope.write(0b1000_0000);
+        envelope.trigger();
+        envelope.clock();
+        assert_eq!(envelope.volume, 8);
+    }
+
+    #[test]
+    fn length_counter_switches_the_channel_off() {
+        let mut apu = Apu::default();
+        apu.write_register(NR12_ADDRESS, 0xF0);
+        // One step of length left
+        apu.write_register(NR11_ADDRESS, 0x3F);
+        apu.write_register(NR14_ADDRESS, 0b1100_0000);
+        assert_eq!(apu.read_register(NR52_ADDRESS) & 1, 1);
+
+        apu.clock_frame_sequencer();
+        assert_eq!(apu.read_register(NR52_ADDRESS) & 1, 0);
+    }
+
+    #[test]
+    fn length_counter_reloads_on_trigger() {
+        let mut length = LengthCounter::new(64);
+        assert!(!length.write_control(true, true, false));
+        assert_eq!(length.counter, 64);
+
+        // Enabled on a step that won't clock lengths it gets an extra clock
+        let mut length = LengthCounter::new(64);
+        length.load(63);
+        assert!(length.write_control(true, false, true));
+        assert_eq!(length.counter, 0);
+        assert!(!length.write_control(true, true, true));
+        assert_eq!(length.counter, 63);
+    }
+
+    #[test]
+    fn only_lengths_can_be_written_while_powered_off() {
+        let mut apu = Apu::default();
+        apu.write_register(NR52_ADDRESS, 0x00);
+        apu.write_register(NR12_ADDRESS, 0xF0);
+        assert_eq!(apu.read_register(NR12_ADDRESS), 0x00);
+        assert_eq!(apu.read_register(NR52_ADDRESS), 0x00);
+
+        // One step of length, loaded while off, is still there after powering back on
+        apu.write_register(NR11_ADDRESS, 0x3F);
+        apu.write_register(NR52_ADDRESS, NR52_POWER);
+        apu.write_register(NR12_ADDRESS, 0xF0);
+        apu.write_register(NR14_ADDRESS, 0b1100_0000);
+        assert_eq!(apu.read_register(NR52_ADDRESS) & 1, 1);
+        apu.clock_frame_sequencer();
+        assert_eq!(apu.read_register(NR52_ADDRESS) & 1, 0);
+    }
+}
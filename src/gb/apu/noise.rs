@@ -0,0 +1,177 @@
+use super::{Envelope, LengthCounter};
+
+const DIVISORS: [u32; 8] = [8, 16, 32, 48, 64, 80, 96, 112];
+
+/// Channel 4, white noise from a 15 bit LFSR that can be cut down to 7 bits
+#[derive(Debug)]
+pub struct NoiseChannel {
+    pub enabled: bool,
+    length: LengthCounter,
+    envelope: Envelope,
+    clock_shift: u8,
+    short_mode: bool,
+    divisor: u8,
+    timer: u32,
+    lfsr: u16,
+}
+
+impl NoiseChannel {
+    pub fn new() -> Self {
+        NoiseChannel {
+            enabled: false,
+            length: LengthCounter::new(64),
+            envelope: Envelope::default(),
+            clock_shift: 0,
+            short_mode: false,
+            divisor: 0,
+            timer: DIVISORS[0],
+            lfsr: 0x7FFF,
+        }
+    }
+
+    /// Writes NR41-NR44, picked by `register`
+    pub fn write(&mut self, register: u8, val: u8, odd_step: bool) {
+        match register {
+            1 => self.load_length(val),
+            2 => {
+                self.envelope.write(val);
+                if !self.envelope.dac_enabled() {
+                    self.enabled = false;
+                }
+            },
+            3 => {
+                self.clock_shift = val >> 4;
+                self.short_mode = val & 0b0000_1000 != 0;
+                self.divisor = val & 0b111;
+            },
+            4 => {
+                let trigger = val & 0b1000_0000 != 0;
+                if self.length.write_control(val & 0b0100_0000 != 0, trigger, odd_step) {
+                    self.enabled = false;
+                }
+                if trigger {
+                    self.enabled = self.dac_enabled();
+                    self.timer = self.period();
+                    self.envelope.trigger();
+                    self.lfsr = 0x7FFF;
+                }
+            },
+            _ => {}
+        }
+    }
+
+    pub fn load_length(&mut self, val: u8) {
+        self.length.load((val & 0b0011_1111) as u16);
+    }
+
+    pub fn dac_enabled(&self) -> bool {
+        self.envelope.dac_enabled()
+    }
+
+    /// Current level, 0-15
+    pub fn output(&self) -> u8 {
+        if self.enabled && self.lfsr & 1 == 0 {
+            self.envelope.volume
+        } else {
+            0
+        }
+    }
+
+    pub fn step(&mut self, mut cycles: u32) {
+        while cycles >= self.timer {
+            cycles -= self.timer;
+            self.timer = self.period();
+
+            // Shifts of 14 and 15 leave the LFSR without a clock
+            if self.clock_shift < 14 {
+                let bit = (self.lfsr ^ (self.lfsr >> 1)) & 1;
+                self.lfsr = (self.lfsr >> 1) | (bit << 14);
+                if self.short_mode {
+                    self.lfsr = (self.lfsr & !(1 << 6)) | (bit << 6);
+                }
+            }
+        }
+        self.timer -= cycles;
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    fn period(&self) -> u32 {
+        DIVISORS[self.divisor as usize] << self.clock_shift
+    }
+}
+
+impl Default for NoiseChannel {
+    fn default() -> Self {
+        NoiseChannel::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Clocks the LFSR once, the shortest period is 8 cycles
+    fn shift(channel: &mut NoiseChannel) {
+        channel.step(DIVISORS[0]);
+    }
+
+    fn triggered(nr43: u8) -> NoiseChannel {
+        let mut channel = NoiseChannel::new();
+        channel.write(2, 0xF0, false);
+        channel.write(3, nr43, false);
+        channel.write(4, 0b1000_0000, false);
+        channel
+    }
+
+    #[test]
+    fn lfsr_feeds_back_the_xor_of_the_low_bits() {
+        let mut channel = triggered(0x00);
+        shift(&mut channel);
+        assert_eq!(channel.lfsr, 0x3FFF);
+
+        for _ in 0..13 {
+            shift(&mut channel);
+        }
+        assert_eq!(channel.lfsr, 0x0001);
+        shift(&mut channel);
+        assert_eq!(channel.lfsr, 0x4000);
+    }
+
+    #[test]
+    fn lfsr_repeats_every_32767_in_long_mode() {
+        let mut channel = triggered(0x00);
+        for _ in 0..32767 {
+            shift(&mut channel);
+        }
+        assert_eq!(channel.lfsr, 0x7FFF);
+    }
+
+    #[test]
+    fn lfsr_repeats_every_127_in_short_mode() {
+        let mut channel = triggered(0b0000_1000);
+        shift(&mut channel);
+        let start = channel.lfsr & 0x7F;
+        for _ in 0..126 {
+            shift(&mut channel);
+            assert_ne!(channel.lfsr & 0x7F, start);
+        }
+        shift(&mut channel);
+        assert_eq!(channel.lfsr & 0x7F, start);
+    }
+
+    #[test]
+    fn top_clock_shifts_stop_the_lfsr() {
+        let mut channel = triggered(0xE0);
+        channel.step(channel.period() * 4);
+        assert_eq!(channel.lfsr, 0x7FFF);
+    }
+}
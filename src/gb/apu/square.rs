@@ -0,0 +1,258 @@
+use super::{Envelope, LengthCounter};
+
+// One bit per step of the 8 step waveform, 12.5%, 25%, 50% and 75%
+const DUTY_PATTERNS: [u8; 4] = [0b0000_0001, 0b1000_0001, 0b1000_0111, 0b0111_1110];
+
+const MAX_FREQUENCY: u16 = 2047;
+
+/// Channel 1's frequency sweep, clocked at 128Hz
+#[derive(Debug, Default)]
+struct Sweep {
+    period: u8,
+    negate: bool,
+    shift: u8,
+    timer: u8,
+    enabled: bool,
+    shadow: u16,
+    // Switching negate off after it has been used in a calculation kills the channel
+    negate_used: bool,
+}
+
+impl Sweep {
+    fn reload_timer(&mut self) {
+        self.timer = if self.period == 0 {8} else {self.period};
+    }
+
+    fn calculate(&mut self) -> u16 {
+        let delta = self.shadow >> self.shift;
+        if self.negate {
+            self.negate_used = true;
+            self.shadow - delta
+        } else {
+            self.shadow + delta
+        }
+    }
+}
+
+/// Channels 1 and 2, only channel 1 has the sweep
+#[derive(Debug)]
+pub struct SquareChannel {
+    pub enabled: bool,
+    sweep: Option<Sweep>,
+    duty: u8,
+    duty_step: u8,
+    length: LengthCounter,
+    pub(super) envelope: Envelope,
+    frequency: u16,
+    timer: u32,
+}
+
+impl SquareChannel {
+    pub fn new() -> Self {
+        SquareChannel {
+            enabled: false,
+            sweep: None,
+            duty: 0,
+            duty_step: 0,
+            length: LengthCounter::new(64),
+            envelope: Envelope::default(),
+            frequency: 0,
+            timer: 8192,
+        }
+    }
+
+    pub fn with_sweep() -> Self {
+        SquareChannel {
+            sweep: Some(Sweep::default()),
+            ..SquareChannel::new()
+        }
+    }
+
+    /// Writes NRx0-NRx4, picked by `register`
+    pub fn write(&mut self, register: u8, val: u8, odd_step: bool) {
+        match register {
+            0 => if let Some(sweep) = &mut self.sweep {
+                sweep.period = (val >> 4) & 0b111;
+                sweep.negate = val & 0b0000_1000 != 0;
+                sweep.shift = val & 0b111;
+                if sweep.negate_used && !sweep.negate {
+                    self.enabled = false;
+                }
+            },
+            1 => {
+                self.duty = val >> 6;
+                self.load_length(val);
+            },
+            2 => {
+                self.envelope.write(val);
+                if !self.envelope.dac_enabled() {
+                    self.enabled = false;
+                }
+            },
+            3 => self.frequency = (self.frequency & 0x0700) | val as u16,
+            4 => {
+                self.frequency = (self.frequency & 0x00FF) | ((val as u16 & 0b111) << 8);
+
+                let trigger = val & 0b1000_0000 != 0;
+                if self.length.write_control(val & 0b0100_0000 != 0, trigger, odd_step) {
+                    self.enabled = false;
+                }
+                if trigger {
+                    self.trigger();
+                }
+            },
+            _ => {}
+        }
+    }
+
+    pub fn load_length(&mut self, val: u8) {
+        self.length.load((val & 0b0011_1111) as u16);
+    }
+
+    pub fn dac_enabled(&self) -> bool {
+        self.envelope.dac_enabled()
+    }
+
+    /// Current level, 0-15
+    pub fn output(&self) -> u8 {
+        if self.enabled && (DUTY_PATTERNS[self.duty as usize] >> self.duty_step) & 1 != 0 {
+            self.envelope.volume
+        } else {
+            0
+        }
+    }
+
+    pub fn step(&mut self, mut cycles: u32) {
+        while cycles >= self.timer {
+            cycles -= self.timer;
+            self.timer = self.period();
+            self.duty_step = (self.duty_step + 1) % 8;
+        }
+        self.timer -= cycles;
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_sweep(&mut self) {
+        let Some(sweep) = &mut self.sweep else {
+            return;
+        };
+
+        sweep.timer = sweep.timer.saturating_sub(1);
+        if sweep.timer > 0 {
+            return;
+        }
+        sweep.reload_timer();
+
+        if sweep.enabled && sweep.period != 0 {
+            let frequency = sweep.calculate();
+            if frequency > MAX_FREQUENCY {
+                self.enabled = false;
+            } else if sweep.shift != 0 {
+                sweep.shadow = frequency;
+                self.frequency = frequency;
+                // The new frequency gets checked for overflow straight away as well
+                if sweep.calculate() > MAX_FREQUENCY {
+                    self.enabled = false;
+                }
+            }
+        }
+    }
+
+    fn period(&self) -> u32 {
+        (2048 - self.frequency as u32) * 4
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.dac_enabled();
+        self.timer = self.period();
+        self.envelope.trigger();
+
+        if let Some(sweep) = &mut self.sweep {
+            sweep.shadow = self.frequency;
+            sweep.reload_timer();
+            sweep.enabled = sweep.period != 0 || sweep.shift != 0;
+            sweep.negate_used = false;
+            if sweep.shift != 0 && sweep.calculate() > MAX_FREQUENCY {
+                self.enabled = false;
+            }
+        }
+    }
+}
+
+impl Default for SquareChannel {
+    fn default() -> Self {
+        SquareChannel::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Channel 1 with its DAC on, triggered at `frequency`
+    fn triggered(nr10: u8, frequency: u16) -> SquareChannel {
+        let mut channel = SquareChannel::with_sweep();
+        channel.write(0, nr10, false);
+        channel.write(2, 0xF0, false);
+        channel.write(3, frequency as u8, false);
+        channel.write(4, 0b1000_0000 | (frequency >> 8) as u8, false);
+        channel
+    }
+
+    #[test]
+    fn sweep_overflow_on_trigger() {
+        // 1400 + 1400 / 2 is past 2047
+        assert!(!triggered(0b0000_0001, 1400).enabled);
+        assert!(triggered(0b0000_0001, 1000).enabled);
+        // Without a shift nothing gets checked
+        assert!(triggered(0b0001_0000, 2047).enabled);
+    }
+
+    #[test]
+    fn sweep_overflow_checks_the_next_frequency_too() {
+        let mut channel = triggered(0b0001_0001, 1000);
+        channel.clock_sweep();
+        // 1500 was written back, but 2250 would come next
+        assert_eq!(channel.frequency, 1500);
+        assert!(!channel.enabled);
+    }
+
+    #[test]
+    fn sweep_down_never_overflows() {
+        let mut channel = triggered(0b0001_1001, 2000);
+        for _ in 0..8 {
+            channel.clock_sweep();
+        }
+        assert!(channel.enabled);
+        assert!(channel.frequency < 2000);
+
+        // Switching negate off once it has been used kills the channel
+        channel.write(0, 0b0001_0001, false);
+        assert!(!channel.enabled);
+    }
+
+    #[test]
+    fn duty_cycle_steps_with_the_frequency() {
+        let mut channel = SquareChannel::new();
+        channel.write(1, 0b1000_0000, false);
+        channel.write(2, 0xF0, false);
+        channel.write(4, 0b1000_0111, false);
+
+        // 50% duty, high for steps 7, 0, 1 and 2
+        let mut levels = Vec::new();
+        for _ in 0..8 {
+            channel.step(channel.period());
+            levels.push(channel.output());
+        }
+        assert_eq!(levels, [15, 15, 0, 0, 0, 0, 15, 15]);
+    }
+}
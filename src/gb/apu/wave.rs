@@ -0,0 +1,128 @@
+use super::LengthCounter;
+
+const WAVE_RAM_SIZE: usize = 16;
+
+// NR32 output level as a right shift of the 4-bit sample, the first one mutes it
+const VOLUME_SHIFTS: [u8; 4] = [4, 0, 1, 2];
+
+/// Channel 3, plays back 32 4-bit samples from wave ram
+#[derive(Debug)]
+pub struct WaveChannel {
+    pub enabled: bool,
+    dac_enabled: bool,
+    length: LengthCounter,
+    volume: u8,
+    frequency: u16,
+    timer: u32,
+    position: u8,
+    // Last sample read out of wave ram, what is actually playing
+    sample: u8,
+    ram: [u8; WAVE_RAM_SIZE],
+}
+
+impl WaveChannel {
+    pub fn new() -> Self {
+        WaveChannel {
+            enabled: false,
+            dac_enabled: false,
+            length: LengthCounter::new(256),
+            volume: 0,
+            frequency: 0,
+            timer: 4096,
+            position: 0,
+            sample: 0,
+            ram: [0; WAVE_RAM_SIZE],
+        }
+    }
+
+    /// Writes NR30-NR34, picked by `register`
+    pub fn write(&mut self, register: u8, val: u8, odd_step: bool) {
+        match register {
+            0 => {
+                self.dac_enabled = val & 0b1000_0000 != 0;
+                if !self.dac_enabled {
+                    self.enabled = false;
+                }
+            },
+            1 => self.load_length(val),
+            2 => self.volume = (val >> 5) & 0b11,
+            3 => self.frequency = (self.frequency & 0x0700) | val as u16,
+            4 => {
+                self.frequency = (self.frequency & 0x00FF) | ((val as u16 & 0b111) << 8);
+
+                let trigger = val & 0b1000_0000 != 0;
+                if self.length.write_control(val & 0b0100_0000 != 0, trigger, odd_step) {
+                    self.enabled = false;
+                }
+                if trigger {
+                    self.enabled = self.dac_enabled;
+                    self.timer = self.period();
+                    self.position = 0;
+                }
+            },
+            _ => {}
+        }
+    }
+
+    pub fn load_length(&mut self, val: u8) {
+        self.length.load(val as u16);
+    }
+
+    pub fn read_ram(&self, offset: u16) -> u8 {
+        self.ram[offset as usize]
+    }
+
+    pub fn write_ram(&mut self, offset: u16, val: u8) {
+        self.ram[offset as usize] = val;
+    }
+
+    pub fn dac_enabled(&self) -> bool {
+        self.dac_enabled
+    }
+
+    /// Current level, 0-15
+    pub fn output(&self) -> u8 {
+        if self.enabled {
+            self.sample >> VOLUME_SHIFTS[self.volume as usize]
+        } else {
+            0
+        }
+    }
+
+    pub fn step(&mut self, mut cycles: u32) {
+        while cycles >= self.timer {
+            cycles -= self.timer;
+            self.timer = self.period();
+            self.position = (self.position + 1) % 32;
+
+            // High nibble first
+            let byte = self.ram[self.position as usize / 2];
+            self.sample = if self.position & 1 == 0 {byte >> 4} else {byte & 0x0F};
+        }
+        self.timer -= cycles;
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    /// Resets everything apart from wave ram
+    pub fn power_off(&mut self) {
+        *self = WaveChannel {
+            ram: self.ram,
+            ..WaveChannel::new()
+        };
+    }
+
+    fn period(&self) -> u32 {
+        (2048 - self.frequency as u32) * 2
+    }
+}
+
+impl Default for WaveChannel {
+    fn default() -> Self {
+        WaveChannel::new()
+    }
+}
@@ -0,0 +1,428 @@
+use std::fmt;
+
+use super::mapper::ROM_BANK_SIZE;
+
+const TITLE_START: usize = 0x0134;
+const TITLE_END: usize = 0x0143;
+const NEW_LICENSEE_CODE: usize = 0x0144;
+const SGB_FLAG: usize = 0x0146;
+const CARTRIDGE_TYPE: usize = 0x0147;
+const ROM_SIZE: usize = 0x0148;
+const RAM_SIZE: usize = 0x0149;
+const OLD_LICENSEE_CODE: usize = 0x014B;
+const VERSION: usize = 0x014C;
+const HEADER_CHECKSUM: usize = 0x014D;
+const GLOBAL_CHECKSUM: usize = 0x014E;
+pub const HEADER_END: usize = 0x0150;
+
+#[derive(Debug, PartialEq)]
+pub enum CartridgeError {
+    Truncated { expected: usize, actual: usize },
+    UnknownRomSize(u8),
+    UnknownRamSize(u8),
+    HeaderChecksum { expected: u8, actual: u8 },
+    GlobalChecksum { expected: u16, actual: u16 },
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CartridgeError::Truncated { expected, actual } =>
+                write!(f, "rom is truncated, expected {} bytes but got {}", expected, actual),
+            CartridgeError::UnknownRomSize(code) => write!(f, "unknown rom size code 0x{:02x}", code),
+            CartridgeError::UnknownRamSize(code) => write!(f, "unknown ram size code 0x{:02x}", code),
+            CartridgeError::HeaderChecksum { expected, actual } =>
+                write!(f, "header checksum mismatch, expected 0x{:02x} but got 0x{:02x}", expected, actual),
+            CartridgeError::GlobalChecksum { expected, actual } =>
+                write!(f, "global checksum mismatch, expected 0x{:04x} but got 0x{:04x}", expected, actual),
+        }
+    }
+}
+
+impl std::error::Error for CartridgeError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CgbSupport {
+    None,
+    Enhanced,
+    Only
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Licensee {
+    Old(u8),
+    // 0x33 in the old slot means the two ascii bytes at 0x0144 are used instead
+    New([u8; 2])
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MapperType {
+    RomOnly,
+    Mbc1,
+    Mbc2,
+    Mmm01,
+    Mbc3,
+    Mbc5,
+    Mbc6,
+    Mbc7,
+    PocketCamera,
+    Tama5,
+    HuC3,
+    HuC1,
+    Unknown(u8)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CartridgeType {
+    pub mapper: MapperType,
+    pub ram: bool,
+    pub battery: bool,
+    pub timer: bool,
+    pub rumble: bool,
+}
+
+impl std::convert::From<u8> for CartridgeType {
+    fn from(byte: u8) -> Self {
+        let (mapper, ram, battery, timer, rumble) = match byte {
+            0x00 => (MapperType::RomOnly,      false, false, false, false),
+            0x01 => (MapperType::Mbc1,         false, false, false, false),
+            0x02 => (MapperType::Mbc1,         true,  false, false, false),
+            0x03 => (MapperType::Mbc1,         true,  true,  false, false),
+            0x05 => (MapperType::Mbc2,         false, false, false, false),
+            0x06 => (MapperType::Mbc2,         false, true,  false, false),
+            0x08 => (MapperType::RomOnly,      true,  false, false, false),
+            0x09 => (MapperType::RomOnly,      true,  true,  false, false),
+            0x0B => (MapperType::Mmm01,        false, false, false, false),
+            0x0C => (MapperType::Mmm01,        true,  false, false, false),
+            0x0D => (MapperType::Mmm01,        true,  true,  false, false),
+            0x0F => (MapperType::Mbc3,         false, true,  true,  false),
+            0x10 => (MapperType::Mbc3,         true,  true,  true,  false),
+            0x11 => (MapperType::Mbc3,         false, false, false, false),
+            0x12 => (MapperType::Mbc3,         true,  false, false, false),
+            0x13 => (MapperType::Mbc3,         true,  true,  false, false),
+            0x19 => (MapperType::Mbc5,         false, false, false, false),
+            0x1A => (MapperType::Mbc5,         true,  false, false, false),
+            0x1B => (MapperType::Mbc5,         true,  true,  false, false),
+            0x1C => (MapperType::Mbc5,         false, false, false, true),
+            0x1D => (MapperType::Mbc5,         true,  false, false, true),
+            0x1E => (MapperType::Mbc5,         true,  true,  false, true),
+            0x20 => (MapperType::Mbc6,         false, false, false, false),
+            0x22 => (MapperType::Mbc7,         true,  true,  false, true),
+            0xFC => (MapperType::PocketCamera, false, false, false, false),
+            0xFD => (MapperType::Tama5,        false, false, false, false),
+            0xFE => (MapperType::HuC3,         false, false, false, false),
+            0xFF => (MapperType::HuC1,         true,  true,  false, false),
+            _    => (MapperType::Unknown(byte), false, false, false, false),
+        };
+
+        CartridgeType {
+            mapper,
+            ram,
+            battery,
+            timer,
+            rumble
+        }
+    }
+}
+
+/// The 0x0100-0x014F cartridge header
+#[derive(Debug, Clone, PartialEq)]
+pub struct CartridgeHeader {
+    pub title: String,
+    // Sum of all 16 title bytes, the CGB uses it to pick colours for DMG games
+    pub title_checksum: u8,
+    pub cgb: CgbSupport,
+    pub sgb: bool,
+    pub cartridge_type: CartridgeType,
+    pub rom_size: usize,
+    pub ram_size: usize,
+    pub licensee: Licensee,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+impl CartridgeHeader {
+    pub fn parse(rom: &[u8]) -> Result<Self, CartridgeError> {
+        if rom.len() < HEADER_END {
+            return Err(CartridgeError::Truncated { expected: HEADER_END, actual: rom.len() });
+        }
+
+        let cgb = match rom[TITLE_END] {
+            0xC0 => CgbSupport::Only,
+            0x80 => CgbSupport::Enhanced,
+            _ => CgbSupport::None
+        };
+
+        // The last title byte doubles as the cgb flag on newer carts
+        let title_end = if cgb == CgbSupport::None {TITLE_END} else {TITLE_END - 1};
+        let title: String = rom[TITLE_START..=title_end].iter()
+            .take_while(|&&byte| byte != 0)
+            .map(|&byte| byte as char)
+            .collect();
+
+        let rom_size = match rom[ROM_SIZE] {
+            code @ 0x00..=0x08 => (ROM_BANK_SIZE * 2) << code,
+            code => return Err(CartridgeError::UnknownRomSize(code))
+        };
+
+        let ram_size = match rom[RAM_SIZE] {
+            0x00 => 0,
+            0x01 => 0x800,
+            0x02 => 0x2000,
+            0x03 => 0x8000,
+            0x04 => 0x20000,
+            0x05 => 0x10000,
+            code => return Err(CartridgeError::UnknownRamSize(code))
+        };
+
+        let licensee = match rom[OLD_LICENSEE_CODE] {
+            0x33 => Licensee::New([rom[NEW_LICENSEE_CODE], rom[NEW_LICENSEE_CODE + 1]]),
+            code => Licensee::Old(code)
+        };
+
+        Ok(CartridgeHeader {
+            title: title.trim_end().to_string(),
+            title_checksum: rom[TITLE_START..=TITLE_END].iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)),
+            cgb,
+            sgb: rom[SGB_FLAG] == 0x03,
+            cartridge_type: rom[CARTRIDGE_TYPE].into(),
+            rom_size,
+            ram_size,
+            licensee,
+            version: rom[VERSION],
+            header_checksum: rom[HEADER_CHECKSUM],
+            global_checksum: (rom[GLOBAL_CHECKSUM] as u16) << 8 | rom[GLOBAL_CHECKSUM + 1] as u16,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Cartridge {
+    pub header: CartridgeHeader,
+    rom: Vec<u8>,
+}
+
+impl Cartridge {
+    pub fn new(rom: Vec<u8>) -> Result<Self, CartridgeError> {
+        let header = CartridgeHeader::parse(&rom)?;
+
+        if rom.len() < header.rom_size {
+            return Err(CartridgeError::Truncated { expected: header.rom_size, actual: rom.len() });
+        }
+
+        let header_checksum = header_checksum(&rom);
+        if header_checksum != header.header_checksum {
+            return Err(CartridgeError::HeaderChecksum { expected: header.header_checksum, actual: header_checksum });
+        }
+
+        let global_checksum = global_checksum(&rom);
+        if global_checksum != header.global_checksum {
+            return Err(CartridgeError::GlobalChecksum { expected: header.global_checksum, actual: global_checksum });
+        }
+
+        Ok(Cartridge { header, rom })
+    }
+
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    pub fn into_parts(self) -> (CartridgeHeader, Vec<u8>) {
+        (self.header, self.rom)
+    }
+}
+
+fn header_checksum(rom: &[u8]) -> u8 {
+    rom[TITLE_START..HEADER_CHECKSUM].iter()
+        .fold(0u8, |sum, &byte| sum.wrapping_sub(byte).wrapping_sub(1))
+}
+
+// Sum of every byte in the rom apart from the checksum itself
+fn global_checksum(rom: &[u8]) -> u16 {
+    rom.iter()
+        .enumerate()
+        .filter(|&(i, _)| i != GLOBAL_CHECKSUM && i != GLOBAL_CHECKSUM + 1)
+        .fold(0u16, |sum, (_, &byte)| sum.wrapping_add(byte as u16))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A blank rom with the given header codes, checksummed so it loads
+    pub(crate) fn build_rom(cartridge_type: u8, rom_size: u8, ram_size: u8) -> Vec<u8> {
+        let mut rom = vec![0; (ROM_BANK_SIZE * 2) << rom_size];
+        rom[TITLE_START..TITLE_START + 4].copy_from_slice(b"TEST");
+        rom[CARTRIDGE_TYPE] = cartridge_type;
+        rom[ROM_SIZE] = rom_size;
+        rom[RAM_SIZE] = ram_size;
+        fix_checksums(&mut rom);
+        rom
+    }
+
+    /// Redoes both checksums after the rom has been changed
+    pub(crate) fn fix_checksums(rom: &mut [u8]) {
+        rom[HEADER_CHECKSUM] = header_checksum(rom);
+        let global = global_checksum(rom);
+        rom[GLOBAL_CHECKSUM..GLOBAL_CHECKSUM + 2].copy_from_slice(&global.to_be_bytes());
+    }
+
+    fn with_title(title: &[u8], cgb_flag: u8) -> Vec<u8> {
+        let mut rom = build_rom(0x00, 0x00, 0x00);
+        rom[TITLE_START..=TITLE_END].fill(0);
+        rom[TITLE_START..TITLE_START + title.len()].copy_from_slice(title);
+        rom[TITLE_END] |= cgb_flag;
+        fix_checksums(&mut rom);
+        rom
+    }
+
+    #[test]
+    fn parses_the_header() {
+        let mut rom = build_rom(0x13, 0x02, 0x03);
+        rom[SGB_FLAG] = 0x03;
+        rom[OLD_LICENSEE_CODE] = 0x01;
+        rom[VERSION] = 0x02;
+        fix_checksums(&mut rom);
+
+        let header = Cartridge::new(rom.clone()).unwrap().header;
+        assert_eq!(header.title, "TEST");
+        assert_eq!(header.cgb, CgbSupport::None);
+        assert!(header.sgb);
+        assert_eq!(header.cartridge_type, CartridgeType {
+            mapper: MapperType::Mbc3,
+            ram: true,
+            battery: true,
+            timer: false,
+            rumble: false,
+        });
+        assert_eq!(header.rom_size, 128 * 1024);
+        assert_eq!(header.ram_size, 32 * 1024);
+        assert_eq!(header.licensee, Licensee::Old(0x01));
+        assert_eq!(header.version, 0x02);
+        assert_eq!(header.header_checksum, rom[HEADER_CHECKSUM]);
+        assert_eq!(header.global_checksum, u16::from_be_bytes([rom[GLOBAL_CHECKSUM], rom[GLOBAL_CHECKSUM + 1]]));
+    }
+
+    #[test]
+    fn cgb_flag_takes_the_last_title_byte() {
+        let full = CartridgeHeader::parse(&with_title(b"SIXTEEN LETTERS!", 0x00)).unwrap();
+        assert_eq!(full.title, "SIXTEEN LETTERS!");
+        assert_eq!(full.cgb, CgbSupport::None);
+
+        let enhanced = CartridgeHeader::parse(&with_title(b"FIFTEEN LETTERS", 0x80)).unwrap();
+        assert_eq!(enhanced.title, "FIFTEEN LETTERS");
+        assert_eq!(enhanced.cgb, CgbSupport::Enhanced);
+
+        let only = CartridgeHeader::parse(&with_title(b"GBC", 0xC0)).unwrap();
+        assert_eq!(only.title, "GBC");
+        assert_eq!(only.cgb, CgbSupport::Only);
+
+        // Padding stops the title
+        assert_eq!(CartridgeHeader::parse(&with_title(b"A B  ", 0x00)).unwrap().title, "A B");
+    }
+
+    #[test]
+    fn new_licensee_code() {
+        let mut rom = build_rom(0x00, 0x00, 0x00);
+        rom[OLD_LICENSEE_CODE] = 0x33;
+        rom[NEW_LICENSEE_CODE..NEW_LICENSEE_CODE + 2].copy_from_slice(b"01");
+        assert_eq!(CartridgeHeader::parse(&rom).unwrap().licensee, Licensee::New(*b"01"));
+    }
+
+    #[test]
+    fn cartridge_types() {
+        let mbc3 = CartridgeType::from(0x10);
+        assert_eq!(mbc3.mapper, MapperType::Mbc3);
+        assert!(mbc3.ram && mbc3.battery && mbc3.timer && !mbc3.rumble);
+
+        let mbc5 = CartridgeType::from(0x1C);
+        assert_eq!(mbc5.mapper, MapperType::Mbc5);
+        assert!(!mbc5.ram && !mbc5.battery && mbc5.rumble);
+
+        assert_eq!(CartridgeType::from(0x42).mapper, MapperType::Unknown(0x42));
+    }
+
+    #[test]
+    fn size_codes() {
+        for (code, size) in [(0x00, 0x8000), (0x05, 0x100000), (0x08, 0x800000)] {
+            let mut rom = build_rom(0x00, 0x00, 0x00);
+            rom[ROM_SIZE] = code;
+            assert_eq!(CartridgeHeader::parse(&rom).unwrap().rom_size, size);
+        }
+        for (code, size) in [(0x00, 0), (0x01, 0x800), (0x02, 0x2000), (0x03, 0x8000), (0x04, 0x20000), (0x05, 0x10000)] {
+            assert_eq!(CartridgeHeader::parse(&build_rom(0x00, 0x00, code)).unwrap().ram_size, size);
+        }
+
+        let mut rom = build_rom(0x00, 0x00, 0x00);
+        rom[ROM_SIZE] = 0x09;
+        assert_eq!(CartridgeHeader::parse(&rom), Err(CartridgeError::UnknownRomSize(0x09)));
+        rom[ROM_SIZE] = 0x00;
+        rom[RAM_SIZE] = 0x06;
+        assert_eq!(CartridgeHeader::parse(&rom), Err(CartridgeError::UnknownRamSize(0x06)));
+    }
+
+    #[test]
+    fn truncated_roms() {
+        assert_eq!(
+            CartridgeHeader::parse(&[0; 0x0100]),
+            Err(CartridgeError::Truncated { expected: HEADER_END, actual: 0x0100 })
+        );
+
+        // Says 64K but only has 32K
+        let mut rom = build_rom(0x01, 0x00, 0x00);
+        rom[ROM_SIZE] = 0x01;
+        fix_checksums(&mut rom);
+        assert_eq!(Cartridge::new(rom).unwrap_err(), CartridgeError::Truncated { expected: 0x10000, actual: 0x8000 });
+    }
+
+    #[test]
+    fn checksums() {
+        // 25 zero bytes take 25 off
+        assert_eq!(header_checksum(&[0; HEADER_END]), 0xE7);
+
+        // The global checksum skips its own two bytes
+        let mut rom = vec![0x01; 0x0200];
+        rom[GLOBAL_CHECKSUM] = 0xFF;
+        rom[GLOBAL_CHECKSUM + 1] = 0xFF;
+        assert_eq!(global_checksum(&rom), 0x01FE);
+    }
+
+    #[test]
+    fn checksum_mismatches() {
+        let rom = build_rom(0x00, 0x00, 0x00);
+
+        let mut bad_header = rom.clone();
+        bad_header[HEADER_CHECKSUM] ^= 0xFF;
+        assert_eq!(
+            Cartridge::new(bad_header).unwrap_err(),
+            CartridgeError::HeaderChecksum { expected: rom[HEADER_CHECKSUM] ^ 0xFF, actual: rom[HEADER_CHECKSUM] }
+        );
+
+        let mut bad_global = rom.clone();
+        bad_global[0x4000] = 0x01;
+        let expected = u16::from_be_bytes([rom[GLOBAL_CHECKSUM], rom[GLOBAL_CHECKSUM + 1]]);
+        assert_eq!(
+            Cartridge::new(bad_global).unwrap_err(),
+            CartridgeError::GlobalChecksum { expected, actual: expected.wrapping_add(1) }
+        );
+    }
+
+    #[test]
+    fn errors_read_well() {
+        assert_eq!(
+            CartridgeError::Truncated { expected: 0x8000, actual: 16 }.to_string(),
+            "rom is truncated, expected 32768 bytes but got 16"
+        );
+        assert_eq!(CartridgeError::UnknownRomSize(0x09).to_string(), "unknown rom size code 0x09");
+        assert_eq!(CartridgeError::UnknownRamSize(0x06).to_string(), "unknown ram size code 0x06");
+        assert_eq!(
+            CartridgeError::HeaderChecksum { expected: 0x12, actual: 0x34 }.to_string(),
+            "header checksum mismatch, expected 0x12 but got 0x34"
+        );
+        assert_eq!(
+            CartridgeError::GlobalChecksum { expected: 0x1234, actual: 0xABCD }.to_string(),
+            "global checksum mismatch, expected 0x1234 but got 0xabcd"
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Wall clock time source for cartridge RTCs
+pub trait Clock: fmt::Debug {
+    /// Seconds since the unix epoch
+    fn now(&self) -> u64;
+}
+
+/// On the ESP32 this only tracks real time once it has been set, e.g. over SNTP. The firmware
+/// never sets it, so it starts from 0 on every boot and an RTC saved before power off doesn't
+/// move on for the time the board was switched off.
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// Time that only moves when the test says so
+    #[derive(Debug, Clone, Default)]
+    pub(crate) struct FakeClock(Rc<Cell<u64>>);
+
+    impl FakeClock {
+        pub(crate) fn advance(&self, seconds: u64) {
+            self.0.set(self.0.get() + seconds);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> u64 {
+            self.0.get()
+        }
+    }
+}
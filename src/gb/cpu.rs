@@ -1,44 +1,184 @@
 use super::ram::{MemoryBus};
 use super::registers::{Registers};
 use super::instructions::*;
+use super::interrupts::{Interrupt, DISPATCH_CYCLES};
+use super::timer::DIV_ADDRESS;
 
 #[derive(Debug)]
-struct Cpu {
+pub struct Cpu {
    registers: Registers,
    pc: u16,
    sp: u16,
    bus: MemoryBus,
+   ime: bool,
+   // EI only takes effect after the instruction that follows it
+   ime_scheduled: bool,
+   halted: bool,
+   stopped: bool,
+   // set when HALT is hit with IME off and an interrupt already pending
+   halt_bug: bool,
+   // set by an opcode that doesn't exist, the cpu hangs for good like the real one does
+   locked: bool,
+   // T-cycles of the current step already clocked into the bus by memory accesses
+   clocked: u8,
 }
 
 impl Cpu {
-    fn step(&mut self) {
-        let mut instruction_byte = self.bus.read_byte(self.pc);
+    /// Starts where the boot rom would have left off, at 0x0100
+    pub fn new(bus: MemoryBus) -> Self {
+        Cpu {
+            registers: Registers::new(),
+            pc: 0x0100,
+            sp: 0xFFFE,
+            bus,
+            ime: false,
+            ime_scheduled: false,
+            halted: false,
+            stopped: false,
+            halt_bug: false,
+            locked: false,
+            clocked: 0,
+        }
+    }
+
+    /// Runs a single instruction, clocking the rest of the system along with it,
+    /// and returns the number of T-cycles it took
+    pub fn step(&mut self) -> u8 {
+        let cycles = self.run_instruction();
+        // Whatever wasn't spent on memory accesses goes at the end
+        debug_assert!(self.clocked <= cycles, "{} cycles of memory access in a {} cycle step", self.clocked, cycles);
+        self.bus.tick(cycles.saturating_sub(self.clocked));
+        self.clocked = 0;
+        cycles
+    }
+
+    pub fn bus(&self) -> &MemoryBus {
+        &self.bus
+    }
+
+    pub fn bus_mut(&mut self) -> &mut MemoryBus {
+        &mut self.bus
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    fn run_instruction(&mut self) -> u8 {
+        if self.locked {
+            return 4;
+        }
+
+        if self.stopped {
+            // only a button press brings the cpu back out of STOP
+            if self.bus.interrupts.flag & Interrupt::Joypad.bit() == 0 {
+                return 4;
+            }
+            self.stopped = false;
+        }
+
+        if self.halted {
+            // any enabled interrupt wakes the cpu, even with IME off
+            if self.bus.interrupts.pending() == 0 {
+                return 4;
+            }
+            self.halted = false;
+        }
+
+        if let Some(cycles) = self.handle_interrupts() {
+            return cycles;
+        }
+
+        let enable_ime = self.ime_scheduled;
+
+        let mut instruction_byte = self.read(self.pc);
+        if self.halt_bug {
+            // pc fails to increment after the opcode fetch, so the byte after HALT is read twice.
+            // For 0xCB that second read is the prefixed opcode, making it CB CB
+            self.halt_bug = false;
+            self.pc = self.pc.wrapping_sub(1);
+        }
+
         let prefixed = instruction_byte == 0xCB;
         if prefixed {
-            instruction_byte = self.bus.read_byte(self.pc + 1);
+            instruction_byte = self.read(self.pc.wrapping_add(1));
         }
 
-        let next_pc = if let Some((instruction, _cc)) = Instruction::from_byte(instruction_byte, prefixed) {
-            self.execute(instruction)
+        let (next_pc, cycles) = if let Some((instruction, cc)) = Instruction::from_byte(instruction_byte, prefixed) {
+            // flags have to be checked before the instruction runs
+            let cycles = cc + self.branch_cycles(&instruction);
+            (self.execute(instruction), cycles)
         } else {
             let desc = format!("0x{}{:x}", if prefixed {"cb"} else {""}, instruction_byte);
-            panic!("Unknown instruction found for: {}", desc);  
+            log::error!("Unknown instruction found for: {} at 0x{:04X}, locking up", desc, self.pc);
+            self.locked = true;
+            return 4;
         };
 
         self.pc = next_pc;
+
+        // a DI straight after EI cancels it
+        if enable_ime && self.ime_scheduled {
+            self.ime = true;
+            self.ime_scheduled = false;
+        }
+
+        cycles
+    }
+
+    /// Jumps to the highest priority pending interrupt, returning the cycles spent
+    fn handle_interrupts(&mut self) -> Option<u8> {
+        if !self.ime {
+            return None;
+        }
+
+        let interrupt = self.bus.interrupts.next()?;
+        self.bus.interrupts.acknowledge(interrupt);
+        self.ime = false;
+
+        self.push(self.pc);
+        self.pc = interrupt.vector();
+
+        Some(DISPATCH_CYCLES)
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// The frontend can use this to blank the LCD while the game is in STOP
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// True once an unknown opcode has hung the cpu, only a reset gets it going again
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Extra T-cycles spent when a conditional branch is taken
+    fn branch_cycles(&self, instruction: &Instruction) -> u8 {
+        match instruction {
+            Instruction::JP(test) | Instruction::JR(test)
+                if *test != JumpTest::Always && self.test_jump(test) => 4,
+            Instruction::CALL(test) | Instruction::RET(test)
+                if *test != JumpTest::Always && self.test_jump(test) => 12,
+            _ => 0
+        }
     }
 
     fn execute(&mut self, instruction: Instruction) -> u16 {
         match instruction {
            Instruction::NOP => {
-               self.pc.wrapping_add(4)
+               self.pc.wrapping_add(1)
            },
            Instruction::ADD(ref target) | Instruction::ADC(ref target) => {
                
-               let carry = match instruction {
-                   Instruction::ADC(_) => true,
-                   _ => false
-               };
+               let carry = matches!(instruction, Instruction::ADC(_));
 
                 self.registers.a = match target {
                    ArithmeticTarget::A => self.add(self.registers.a, carry),
@@ -48,8 +188,14 @@ impl Cpu {
                    ArithmeticTarget::E => self.add(self.registers.e, carry),
                    ArithmeticTarget::H => self.add(self.registers.h, carry),
                    ArithmeticTarget::L => self.add(self.registers.l, carry),
-                   ArithmeticTarget::HL => self.add(self.bus.read_byte(self.registers.get_hl()), carry),
-                   ArithmeticTarget::N8 => self.add(self.read_next_byte(), carry)
+                   ArithmeticTarget::HL => {
+                       let val = self.read(self.registers.get_hl());
+                       self.add(val, carry)
+                   },
+                   ArithmeticTarget::N8 => {
+                       let val = self.read_next_byte();
+                       self.add(val, carry)
+                   },
                 };
 
                 match target {
@@ -60,26 +206,24 @@ impl Cpu {
            },
            Instruction::SUB(ref target) | Instruction::SBC(ref target) => {
                
-               let carry = match &instruction {
-                   &Instruction::SBC(_) => true,
-                   _ => false
-               };
+               let carry = matches!(instruction, Instruction::SBC(_));
 
                 self.registers.a = match target {
-                   ArithmeticTarget::A => {
-                       let old_carry = if carry {self.registers.f.carry} else {false};
-                       let val = self.sub(self.registers.a, carry);
-                       self.registers.f.carry = old_carry;
-                       val
-                   },
+                   ArithmeticTarget::A => self.sub(self.registers.a, carry),
                    ArithmeticTarget::B => self.sub(self.registers.b, carry),
                    ArithmeticTarget::C => self.sub(self.registers.c, carry),
                    ArithmeticTarget::D => self.sub(self.registers.d, carry),
                    ArithmeticTarget::E => self.sub(self.registers.e, carry),
                    ArithmeticTarget::H => self.sub(self.registers.h, carry),
                    ArithmeticTarget::L => self.sub(self.registers.l, carry),
-                   ArithmeticTarget::HL => self.sub(self.bus.read_byte(self.registers.get_hl()), carry),
-                   ArithmeticTarget::N8 => self.sub(self.read_next_byte(), carry)
+                   ArithmeticTarget::HL => {
+                       let val = self.read(self.registers.get_hl());
+                       self.sub(val, carry)
+                   },
+                   ArithmeticTarget::N8 => {
+                       let val = self.read_next_byte();
+                       self.sub(val, carry)
+                   },
                 };
 
                 match target {
@@ -97,8 +241,14 @@ impl Cpu {
                    ArithmeticTarget::E => self.and(self.registers.e),
                    ArithmeticTarget::H => self.and(self.registers.h),
                    ArithmeticTarget::L => self.and(self.registers.l),
-                   ArithmeticTarget::HL => self.and(self.bus.read_byte(self.registers.get_hl())),
-                   ArithmeticTarget::N8 => self.and(self.read_next_byte())
+                   ArithmeticTarget::HL => {
+                       let val = self.read(self.registers.get_hl());
+                       self.and(val)
+                   },
+                   ArithmeticTarget::N8 => {
+                       let val = self.read_next_byte();
+                       self.and(val)
+                   },
                 };
 
                 match target {
@@ -108,10 +258,7 @@ impl Cpu {
                 
            },
            Instruction::OR(ref target) | Instruction::XOR(ref target) => {
-               let not = match &instruction {
-                   &Instruction::XOR(_) => true,
-                   _ => false
-               };
+               let not = matches!(instruction, Instruction::XOR(_));
 
                 self.registers.a = match target {
                    ArithmeticTarget::A => self.or(self.registers.a, not),
@@ -121,8 +268,14 @@ impl Cpu {
                    ArithmeticTarget::E => self.or(self.registers.e, not),
                    ArithmeticTarget::H => self.or(self.registers.h, not),
                    ArithmeticTarget::L => self.or(self.registers.l, not),
-                   ArithmeticTarget::HL => self.or(self.bus.read_byte(self.registers.get_hl()), not),
-                   ArithmeticTarget::N8 => self.or(self.read_next_byte(), not)
+                   ArithmeticTarget::HL => {
+                       let val = self.read(self.registers.get_hl());
+                       self.or(val, not)
+                   },
+                   ArithmeticTarget::N8 => {
+                       let val = self.read_next_byte();
+                       self.or(val, not)
+                   },
                 };
 
                 match target {
@@ -132,16 +285,23 @@ impl Cpu {
                 
            },
            Instruction::CP(target) => {
-                self.registers.a = match target {
-                   ArithmeticTarget::A => self.sub(self.registers.a, false), //may need to set flags 1100
+                // Same flags as SUB, but the result is thrown away
+                match target {
+                   ArithmeticTarget::A => self.sub(self.registers.a, false),
                    ArithmeticTarget::B => self.sub(self.registers.b, false),
                    ArithmeticTarget::C => self.sub(self.registers.c, false),
                    ArithmeticTarget::D => self.sub(self.registers.d, false),
                    ArithmeticTarget::E => self.sub(self.registers.e, false),
                    ArithmeticTarget::H => self.sub(self.registers.h, false),
                    ArithmeticTarget::L => self.sub(self.registers.l, false),
-                   ArithmeticTarget::HL => self.sub(self.bus.read_byte(self.registers.get_hl()), false),
-                   ArithmeticTarget::N8 => self.sub(self.read_next_byte(), false)
+                   ArithmeticTarget::HL => {
+                       let val = self.read(self.registers.get_hl());
+                       self.sub(val, false)
+                   },
+                   ArithmeticTarget::N8 => {
+                       let val = self.read_next_byte();
+                       self.sub(val, false)
+                   },
                 };
 
                 match target {
@@ -150,6 +310,43 @@ impl Cpu {
                 }
                 
            },
+           Instruction::INC(ref target) | Instruction::DEC(ref target) => {
+               let inc = matches!(instruction, Instruction::INC(_));
+
+               match target {
+                   IncDecTarget::A => self.registers.a = self.inc_dec(self.registers.a, inc),
+                   IncDecTarget::B => self.registers.b = self.inc_dec(self.registers.b, inc),
+                   IncDecTarget::C => self.registers.c = self.inc_dec(self.registers.c, inc),
+                   IncDecTarget::D => self.registers.d = self.inc_dec(self.registers.d, inc),
+                   IncDecTarget::E => self.registers.e = self.inc_dec(self.registers.e, inc),
+                   IncDecTarget::H => self.registers.h = self.inc_dec(self.registers.h, inc),
+                   IncDecTarget::L => self.registers.l = self.inc_dec(self.registers.l, inc),
+                   IncDecTarget::HL => {
+                       let address = self.registers.get_hl();
+                       let val = self.read(address);
+                       let val = self.inc_dec(val, inc);
+                       self.write(address, val);
+                   }
+               };
+
+               self.pc.wrapping_add(1)
+           },
+           Instruction::INC16(ref target) | Instruction::DEC16(ref target) => {
+               let step: u16 = match &instruction {
+                   &Instruction::INC16(_) => 1,
+                   _ => 0xFFFF // wrapping_add of -1
+               };
+
+               // 16-bit INC/DEC leave the flags untouched
+               match target {
+                   WordTarget::BC => self.registers.set_bc(self.registers.get_bc().wrapping_add(step)),
+                   WordTarget::DE => self.registers.set_de(self.registers.get_de().wrapping_add(step)),
+                   WordTarget::HL => self.registers.set_hl(self.registers.get_hl().wrapping_add(step)),
+                   WordTarget::SP => self.sp = self.sp.wrapping_add(step),
+               };
+
+               self.pc.wrapping_add(1)
+           },
            Instruction::ADDHL(target) => {
                 let new_value = match target {
                    WordTarget::BC => self.addhl(self.registers.get_bc()),
@@ -186,15 +383,16 @@ impl Cpu {
                 }  
            },*/
            Instruction::JP(test) => {
-               let jump_cond = match test {
-                   JumpTest::NotZero => !self.registers.f.zero,
-                   JumpTest::NotCarry => !self.registers.f.carry,
-                   JumpTest::Zero => self.registers.f.zero,
-                   JumpTest::Carry => self.registers.f.carry,
-                   JumpTest::Always => true
-               };
+               let jump_cond = self.test_jump(&test);
                self.jump(jump_cond)
            },
+           Instruction::JPHL => {
+               self.registers.get_hl()
+           },
+           Instruction::JR(test) => {
+               let jump_cond = self.test_jump(&test);
+               self.jump_relative(jump_cond)
+           },
            Instruction::LD(load_type) => {
                match load_type {
                     LoadType::Byte(target, source) => {
@@ -207,12 +405,18 @@ impl Cpu {
                             LoadByteSource::H => self.registers.h,
                             LoadByteSource::L => self.registers.l,
                             LoadByteSource::D8 => self.read_next_byte(),
-                            LoadByteSource::HL | LoadByteSource::HLI | LoadByteSource::HLD => self.bus.read_byte(self.registers.get_hl()),
-                            LoadByteSource::BC => self.bus.read_byte(self.registers.get_bc()),
-                            LoadByteSource::DE => self.bus.read_byte(self.registers.get_de()),
-                            LoadByteSource::D16 => self.bus.read_byte(self.read_next_word()),
-                            LoadByteSource::ADRC => self.bus.read_byte(0xFF00 | self.registers.c as u16),
-                            LoadByteSource::A8 => self.bus.read_byte(0xFF00 | self.read_next_byte() as u16),
+                            LoadByteSource::HL | LoadByteSource::HLI | LoadByteSource::HLD => self.read(self.registers.get_hl()),
+                            LoadByteSource::BC => self.read(self.registers.get_bc()),
+                            LoadByteSource::DE => self.read(self.registers.get_de()),
+                            LoadByteSource::D16 => {
+                                let address = self.read_next_word();
+                                self.read(address)
+                            },
+                            LoadByteSource::ADRC => self.read(0xFF00 | self.registers.c as u16),
+                            LoadByteSource::A8 => {
+                                let address = 0xFF00 | self.read_next_byte() as u16;
+                                self.read(address)
+                            },
 
                         };
 
@@ -224,12 +428,18 @@ impl Cpu {
                              LoadByteTarget::E => self.registers.e = source_value,
                              LoadByteTarget::H => self.registers.h = source_value,
                              LoadByteTarget::L => self.registers.l = source_value,
-                             LoadByteTarget::HL | LoadByteTarget::HLI | LoadByteTarget::HLD => self.bus.write_byte(self.registers.get_hl(), source_value),
-                             LoadByteTarget::BC => self.bus.write_byte(self.registers.get_bc(), source_value),
-                             LoadByteTarget::DE => self.bus.write_byte(self.registers.get_de(), source_value),
-                             LoadByteTarget::D16 => self.bus.write_byte(self.read_next_word(), source_value),
-                             LoadByteTarget::ADRC => self.bus.write_byte(0xFF00 | self.registers.c as u16, source_value),
-                             LoadByteTarget::A8 => self.bus.write_byte(0xFF00 | self.read_next_byte() as u16, source_value),
+                             LoadByteTarget::HL | LoadByteTarget::HLI | LoadByteTarget::HLD => self.write(self.registers.get_hl(), source_value),
+                             LoadByteTarget::BC => self.write(self.registers.get_bc(), source_value),
+                             LoadByteTarget::DE => self.write(self.registers.get_de(), source_value),
+                             LoadByteTarget::D16 => {
+                                 let address = self.read_next_word();
+                                 self.write(address, source_value)
+                             },
+                             LoadByteTarget::ADRC => self.write(0xFF00 | self.registers.c as u16, source_value),
+                             LoadByteTarget::A8 => {
+                                 let address = 0xFF00 | self.read_next_byte() as u16;
+                                 self.write(address, source_value)
+                             },
                         };
 
                         if source == LoadByteSource::HLI || target == LoadByteTarget::HLI {
@@ -259,19 +469,18 @@ impl Cpu {
                             LoadWordSource::D16 => self.read_next_word(),
                             LoadWordSource::SP => self.sp,
                             LoadWordSource::HL => self.registers.get_hl(),
-                            LoadWordSource::SP8 => {
-                                //Add the signed value e8 to SP and copy the result in HL.
-                                let new_val = self.add_sign_to_sp();
-                                self.sp = new_val;
-                                new_val
-                            },  
+                            //Add the signed value e8 to SP and copy the result in HL.
+                            LoadWordSource::SP8 => self.add_sign_to_sp(),
                          };
 
                          match target {
                              LoadWordTarget::BC => self.registers.set_bc(source_value),
                              LoadWordTarget::DE => self.registers.set_de(source_value),
                              LoadWordTarget::HL => self.registers.set_hl(source_value),
-                             LoadWordTarget::A16 => self.bus.write_word(self.read_next_word(), source_value),
+                             LoadWordTarget::A16 => {
+                                 let address = self.read_next_word();
+                                 self.write_word(address, source_value)
+                             },
                              LoadWordTarget::SP => self.sp = source_value,
                          };
 
@@ -287,8 +496,10 @@ impl Cpu {
            },
            Instruction::PUSH(target) => {
                let value = match target {
-                   StackTarget::BC => { self.registers.get_bc() },
-                   _ => todo!()
+                   StackTarget::AF => self.registers.get_af(),
+                   StackTarget::BC => self.registers.get_bc(),
+                   StackTarget::DE => self.registers.get_de(),
+                   StackTarget::HL => self.registers.get_hl(),
                };
                self.push(value);
                self.pc.wrapping_add(1)
@@ -296,26 +507,146 @@ impl Cpu {
            Instruction::POP(target) => {
                let result = self.pop();
                match target {
+                   StackTarget::AF => self.registers.set_af(result),
                    StackTarget::BC => self.registers.set_bc(result),
-                   _ => todo!()
+                   StackTarget::DE => self.registers.set_de(result),
+                   StackTarget::HL => self.registers.set_hl(result),
                }
                self.pc.wrapping_add(1)
            },
            Instruction::CALL(test) => {
-               let jump_cond = match test {
-                   JumpTest::NotZero => !self.registers.f.zero,
-                   _ => { todo!() }
-               };
+               let jump_cond = self.test_jump(&test);
                self.call(jump_cond)
            }
            Instruction::RET(test) => {
-               let jump_cond = match test {
-                   JumpTest::NotZero => !self.registers.f.zero,
-                   _ => todo!()
-               };
+               let jump_cond = self.test_jump(&test);
                self.return_(jump_cond)
            }
-           //_ => {}
+           Instruction::RETI => {
+               self.ime = true;
+               self.return_(true)
+           }
+           Instruction::RST(vector) => {
+               self.push(self.pc.wrapping_add(1));
+               vector as u16
+           }
+           Instruction::RLCA => {
+               let a = self.registers.a;
+               self.registers.a = a.rotate_left(1);
+               self.set_rotate_a_flags(a & 0b1000_0000 != 0);
+               self.pc.wrapping_add(1)
+           }
+           Instruction::RRCA => {
+               let a = self.registers.a;
+               self.registers.a = a.rotate_right(1);
+               self.set_rotate_a_flags(a & 0b0000_0001 != 0);
+               self.pc.wrapping_add(1)
+           }
+           Instruction::RLA => {
+               let a = self.registers.a;
+               self.registers.a = (a << 1) | (if self.registers.f.carry {1} else {0});
+               self.set_rotate_a_flags(a & 0b1000_0000 != 0);
+               self.pc.wrapping_add(1)
+           }
+           Instruction::RRA => {
+               let a = self.registers.a;
+               self.registers.a = (a >> 1) | (if self.registers.f.carry {0b1000_0000} else {0});
+               self.set_rotate_a_flags(a & 0b0000_0001 != 0);
+               self.pc.wrapping_add(1)
+           }
+           Instruction::DAA => {
+               self.registers.a = self.daa();
+               self.pc.wrapping_add(1)
+           }
+           Instruction::CPL => {
+               self.registers.a = !self.registers.a;
+               self.registers.f.subtract = true;
+               self.registers.f.half_carry = true;
+               self.pc.wrapping_add(1)
+           }
+           Instruction::SCF => {
+               self.registers.f.subtract = false;
+               self.registers.f.half_carry = false;
+               self.registers.f.carry = true;
+               self.pc.wrapping_add(1)
+           }
+           Instruction::CCF => {
+               self.registers.f.subtract = false;
+               self.registers.f.half_carry = false;
+               self.registers.f.carry = !self.registers.f.carry;
+               self.pc.wrapping_add(1)
+           }
+           Instruction::DI => {
+               self.ime = false;
+               self.ime_scheduled = false;
+               self.pc.wrapping_add(1)
+           }
+           Instruction::EI => {
+               self.ime_scheduled = true;
+               self.pc.wrapping_add(1)
+           }
+           Instruction::HALT => {
+               if !self.ime && self.bus.interrupts.pending() != 0 {
+                   self.halt_bug = true;
+               } else {
+                   self.halted = true;
+               }
+               self.pc.wrapping_add(1)
+           }
+           Instruction::STOP => {
+               // STOP is followed by a padding byte, and resets DIV on the way in
+               self.bus.write_byte(DIV_ADDRESS, 0);
+               self.stopped = true;
+               self.pc.wrapping_add(2)
+           }
+
+           // CB prefixed, pc skips over both the 0xCB and the opcode
+           Instruction::RLC(ref target) | Instruction::RRC(ref target) |
+           Instruction::RL(ref target) | Instruction::RR(ref target) |
+           Instruction::SLA(ref target) | Instruction::SRA(ref target) |
+           Instruction::SWAP(ref target) | Instruction::SRL(ref target) => {
+               let val = self.read_prefix_target(target);
+               let old_carry = if self.registers.f.carry {1} else {0};
+
+               let (new_value, carry) = match instruction {
+                   Instruction::RLC(_) => (val.rotate_left(1), val & 0b1000_0000 != 0),
+                   Instruction::RRC(_) => (val.rotate_right(1), val & 0b0000_0001 != 0),
+                   Instruction::RL(_) => ((val << 1) | old_carry, val & 0b1000_0000 != 0),
+                   Instruction::RR(_) => ((val >> 1) | (old_carry << 7), val & 0b0000_0001 != 0),
+                   Instruction::SLA(_) => (val << 1, val & 0b1000_0000 != 0),
+                   Instruction::SRA(_) => ((val >> 1) | (val & 0b1000_0000), val & 0b0000_0001 != 0),
+                   Instruction::SWAP(_) => (val.rotate_left(4), false),
+                   _ => (val >> 1, val & 0b0000_0001 != 0), // SRL
+               };
+
+               self.registers.f.zero = new_value == 0;
+               self.registers.f.subtract = false;
+               self.registers.f.half_carry = false;
+               self.registers.f.carry = carry;
+
+               self.write_prefix_target(target, new_value);
+               self.pc.wrapping_add(2)
+           }
+           Instruction::BIT(bit, target) => {
+               let val = self.read_prefix_target(&target);
+
+               // carry is left alone
+               self.registers.f.zero = val & (1 << bit) == 0;
+               self.registers.f.subtract = false;
+               self.registers.f.half_carry = true;
+
+               self.pc.wrapping_add(2)
+           }
+           Instruction::RES(bit, target) => {
+               let val = self.read_prefix_target(&target);
+               self.write_prefix_target(&target, val & !(1 << bit));
+               self.pc.wrapping_add(2)
+           }
+           Instruction::SET(bit, target) => {
+               let val = self.read_prefix_target(&target);
+               self.write_prefix_target(&target, val | (1 << bit));
+               self.pc.wrapping_add(2)
+           }
         }
     }
 
@@ -328,8 +659,10 @@ impl Cpu {
     fn call(&mut self, should_jump: bool) -> u16 {
         let next_pc = self.pc.wrapping_add(3);
         if should_jump {
+            // The address is read before the return address goes on the stack
+            let address = self.read_next_word();
             self.push(next_pc);
-            self.read_next_word()
+            address
         } else {
             next_pc
         }
@@ -344,10 +677,10 @@ impl Cpu {
     }
 
     fn pop(&mut self) -> u16 {
-        let lsb = self.bus.read_byte(self.sp) as u16;
+        let lsb = self.read(self.sp) as u16;
         self.sp = self.sp.wrapping_add(1);
 
-        let msb = self.bus.read_byte(self.sp) as u16;
+        let msb = self.read(self.sp) as u16;
         self.sp = self.sp.wrapping_add(1);
 
         (msb << 8) | lsb
@@ -355,58 +688,125 @@ impl Cpu {
 
     fn push(&mut self, value: u16) {
         self.sp = self.sp.wrapping_sub(1);
-        self.bus.write_byte(self.sp, ((value & 0xFF00) >> 8 as u8).try_into().unwrap());
+        self.write(self.sp, (value >> 8) as u8);
 
         self.sp = self.sp.wrapping_sub(1);
-        self.bus.write_byte(self.sp, (value & 0xFF) as u8);
+        self.write(self.sp, (value & 0xFF) as u8);
+    }
+
+    fn test_jump(&self, test: &JumpTest) -> bool {
+        match test {
+            JumpTest::NotZero => !self.registers.f.zero,
+            JumpTest::NotCarry => !self.registers.f.carry,
+            JumpTest::Zero => self.registers.f.zero,
+            JumpTest::Carry => self.registers.f.carry,
+            JumpTest::Always => true
+        }
     }
 
-    fn jump(&self, should_jump: bool) -> u16 {
+    fn jump(&mut self, should_jump: bool) -> u16 {
         if should_jump {
-            let least_significant_byte = self.bus.read_byte(self.pc + 1) as u16;
-            let most_significant_byte = self.bus.read_byte(self.pc + 2) as u16;
-            
-            (most_significant_byte << 8) | least_significant_byte
+            self.read_next_word()
         } else {
             self.pc.wrapping_add(3)
         }
     }
 
+    fn jump_relative(&mut self, should_jump: bool) -> u16 {
+        let next_pc = self.pc.wrapping_add(2);
+        if should_jump {
+            let offset = self.read_next_byte() as i8;
+            next_pc.wrapping_add(offset as u16)
+        } else {
+            next_pc
+        }
+    }
+
     fn add(&mut self, val: u8, carry: bool) -> u8 {
-         let (new_value, did_overflow) = self.registers.a.overflowing_add(val + (if carry {1} else {0}));
+        let carry_in = if carry && self.registers.f.carry {1} else {0};
+        let (partial, overflow_a) = self.registers.a.overflowing_add(val);
+        let (new_value, overflow_b) = partial.overflowing_add(carry_in);
 
         self.registers.f.zero = new_value == 0;
         self.registers.f.subtract = false;
-        self.registers.f.carry = did_overflow;
-        self.registers.f.half_carry = (self.registers.a & 0b1111) + (val & 0b1111) > 0b1111;
+        self.registers.f.carry = overflow_a || overflow_b;
+        self.registers.f.half_carry = (self.registers.a & 0b1111) + (val & 0b1111) + carry_in > 0b1111;
 
         new_value
    }
 
     fn sub(&mut self, val: u8, carry: bool) -> u8 {
-         let (new_value, did_overflow) = self.registers.a.overflowing_sub(val + (if carry {1} else {0}));
+        let carry_in = if carry && self.registers.f.carry {1} else {0};
+        let (partial, overflow_a) = self.registers.a.overflowing_sub(val);
+        let (new_value, overflow_b) = partial.overflowing_sub(carry_in);
 
         self.registers.f.zero = new_value == 0;
         self.registers.f.subtract = true;
-        self.registers.f.carry = did_overflow;
-        self.registers.f.half_carry = (self.registers.a & 0b1111) + (val & 0b1111) > 0b1111;
+        self.registers.f.carry = overflow_a || overflow_b;
+        self.registers.f.half_carry = (self.registers.a & 0b1111) < (val & 0b1111) + carry_in;
 
         new_value
    }
 
+    fn inc_dec(&mut self, val: u8, inc: bool) -> u8 {
+        let new_value = if inc {val.wrapping_add(1)} else {val.wrapping_sub(1)};
+
+        // carry is left alone
+        self.registers.f.zero = new_value == 0;
+        self.registers.f.subtract = !inc;
+        self.registers.f.half_carry = if inc {val & 0b1111 == 0b1111} else {val & 0b1111 == 0};
+
+        new_value
+    }
+
     fn addhl(&mut self, val: u16) -> u16 {
         let (new_value, did_overflow) = self.registers.get_hl().overflowing_add(val);
 
         self.registers.f.subtract = false;
         self.registers.f.carry = did_overflow;
-        self.registers.f.half_carry = (self.registers.get_hl() & 0b11111111) + (val & 0b11111111) > 0b11111111;
+        self.registers.f.half_carry = (self.registers.get_hl() & 0x0FFF) + (val & 0x0FFF) > 0x0FFF;
 
         new_value
     }
 
+    fn set_rotate_a_flags(&mut self, carry: bool) {
+        self.registers.f.zero = false;
+        self.registers.f.subtract = false;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = carry;
+    }
+
+    fn daa(&mut self) -> u8 {
+        let mut a = self.registers.a;
+        let mut carry = self.registers.f.carry;
+
+        if !self.registers.f.subtract {
+            if carry || a > 0x99 {
+                a = a.wrapping_add(0x60);
+                carry = true;
+            }
+            if self.registers.f.half_carry || (a & 0x0F) > 0x09 {
+                a = a.wrapping_add(0x06);
+            }
+        } else {
+            if carry {
+                a = a.wrapping_sub(0x60);
+            }
+            if self.registers.f.half_carry {
+                a = a.wrapping_sub(0x06);
+            }
+        }
+
+        self.registers.f.zero = a == 0;
+        self.registers.f.half_carry = false;
+        self.registers.f.carry = carry;
+
+        a
+    }
+
     fn and(&mut self, val: u8) -> u8 {
         let val = self.registers.a & val;
-        self.registers.f.zero = if val == 0 {true} else {false};
+        self.registers.f.zero = val == 0;
         self.registers.f.half_carry = true;
         self.registers.f.subtract = false;
         self.registers.f.carry = false;
@@ -416,7 +816,7 @@ impl Cpu {
 
     fn or(&mut self, val: u8, not: bool) -> u8 {
         let val = if not {self.registers.a ^ val} else {self.registers.a | val};
-        self.registers.f.zero = if val == 0 {true} else {false};
+        self.registers.f.zero = val == 0;
         self.registers.f.half_carry = false;
         self.registers.f.subtract = false;
         self.registers.f.carry = false;
@@ -424,34 +824,529 @@ impl Cpu {
         val
     }
 
-    fn read_next_byte(&self) -> u8 {
-        self.bus.read_byte(self.pc+1)//wrap???
+    fn read_prefix_target(&mut self, target: &PrefixTarget) -> u8 {
+        match target {
+            PrefixTarget::A => self.registers.a,
+            PrefixTarget::B => self.registers.b,
+            PrefixTarget::C => self.registers.c,
+            PrefixTarget::D => self.registers.d,
+            PrefixTarget::E => self.registers.e,
+            PrefixTarget::H => self.registers.h,
+            PrefixTarget::L => self.registers.l,
+            PrefixTarget::HL => self.read(self.registers.get_hl()),
+        }
     }
 
-    fn read_next_word(&self) -> u16 {
-       let lsb = self.bus.read_byte(self.pc + 1) as u16;
-       let msb = self.bus.read_byte(self.pc + 2) as u16;
-            
-       (msb << 8) | lsb
+    fn write_prefix_target(&mut self, target: &PrefixTarget, val: u8) {
+        match target {
+            PrefixTarget::A => self.registers.a = val,
+            PrefixTarget::B => self.registers.b = val,
+            PrefixTarget::C => self.registers.c = val,
+            PrefixTarget::D => self.registers.d = val,
+            PrefixTarget::E => self.registers.e = val,
+            PrefixTarget::H => self.registers.h = val,
+            PrefixTarget::L => self.registers.l = val,
+            PrefixTarget::HL => self.write(self.registers.get_hl(), val),
+        }
     }
 
-    fn add_sign_to_sp(&mut self) -> u16 {
-        let val = self.read_next_byte();
-        let mut did_overflow: bool;    
-        let mut new_value: u16;
-        let uval = (val & 0b0111_1111) as u16;
+    fn read_next_byte(&mut self) -> u8 {
+        self.read(self.pc.wrapping_add(1))
+    }
 
-        if val & 0b1000_0000 == 0 { //neg
-            (new_value, did_overflow) = self.sp.overflowing_add(uval);
-        } else {//pos
-            (new_value, did_overflow) = self.sp.overflowing_sub(uval);
-        }
+    fn read_next_word(&mut self) -> u16 {
+        let lsb = self.read(self.pc.wrapping_add(1)) as u16;
+        let msb = self.read(self.pc.wrapping_add(2)) as u16;
+        (msb << 8) | lsb
+    }
+
+    // Each memory access takes an M-cycle, and the rest of the system is clocked along as they
+    // happen so that the timer, the PPU and the rest see reads and writes at the right point
+    fn read(&mut self, address: u16) -> u8 {
+        let val = self.bus.read_byte(address);
+        self.clock_access();
+        val
+    }
+
+    fn write(&mut self, address: u16, val: u8) {
+        self.bus.write_byte(address, val);
+        self.clock_access();
+    }
+
+    fn write_word(&mut self, address: u16, val: u16) {
+        self.write(address, (val & 0xFF) as u8);
+        self.write(address.wrapping_add(1), (val >> 8) as u8);
+    }
+
+    fn clock_access(&mut self) {
+        self.bus.tick(4);
+        self.clocked += 4;
+    }
+
+    fn add_sign_to_sp(&mut self) -> u16 {
+        // e8 is sign extended, but the flags come from the unsigned low byte add
+        let val = self.read_next_byte() as i8 as u16;
 
         self.registers.f.zero = false;
         self.registers.f.subtract = false;
-        self.registers.f.carry = did_overflow;
-        self.registers.f.half_carry = (self.sp & 0b1111) + ((val as u16) & 0b1111) > 0b1111;
+        self.registers.f.carry = (self.sp & 0xFF) + (val & 0xFF) > 0xFF;
+        self.registers.f.half_carry = (self.sp & 0b1111) + (val & 0b1111) > 0b1111;
         
-        new_value
+        self.sp.wrapping_add(val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gb::cartridge::{tests::build_rom, Cartridge};
+    use crate::gb::joypad::{Button, JOYP_ADDRESS};
+
+    const CODE: u16 = 0xC000;
+    const HALT: u8 = 0x76;
+    const STOP: u8 = 0x10;
+    const INC_A: u8 = 0x3C;
+
+    // Runs `code` from work ram, with IME off and only the timer interrupt enabled
+    fn running(code: &[u8]) -> Cpu {
+        let mut cpu = Cpu::new(MemoryBus::new(Cartridge::new(build_rom(0x00, 0x00, 0x00)).unwrap()));
+        for (address, &byte) in (CODE..).zip(code) {
+            cpu.bus.write_byte(address, byte);
+        }
+        cpu.pc = CODE;
+        cpu.bus.interrupts.enable = Interrupt::Timer.bit();
+        cpu
+    }
+
+    #[test]
+    fn memory_accesses_fit_in_every_instruction() {
+        // Taken and not taken branches both, the debug assert in step does the checking
+        for flags in [0x00, 0xF0] {
+            for prefixed in [false, true] {
+                for opcode in 0..=0xFF {
+                    if Instruction::from_byte(opcode, prefixed).is_none() || (!prefixed && matches!(opcode, 0xCB | HALT | STOP)) {
+                        continue;
+                    }
+
+                    let code = if prefixed {[0xCB, opcode, 0x00]} else {[opcode, 0x00, 0xC1]};
+                    let mut cpu = running(&code);
+                    cpu.registers.f = flags.into();
+                    cpu.registers.set_hl(0xC100);
+                    cpu.sp = 0xD000;
+                    cpu.step();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn memory_accesses_happen_partway_through() {
+        // LDH (DIV), A writes on its third M-cycle, so only that one is counted after the reset
+        let mut cpu = running(&[0xE0, 0x04]);
+        assert_eq!(cpu.step(), 12);
+        assert_eq!(cpu.bus.timer.counter(), 4);
+    }
+
+    #[test]
+    fn halt_waits_for_an_interrupt() {
+        let mut cpu = running(&[HALT, INC_A]);
+        cpu.step();
+        for _ in 0..10 {
+            assert!(cpu.is_halted());
+            assert_eq!(cpu.step(), 4);
+        }
+        assert_eq!(cpu.pc, CODE + 1);
+
+        // Even with IME off it wakes, carrying on without servicing the interrupt
+        cpu.bus.interrupts.request(Interrupt::Timer);
+        cpu.step();
+        assert!(!cpu.is_halted());
+        assert_eq!(cpu.pc, CODE + 2);
+        assert_eq!(cpu.registers.a, 0x02);
+    }
+
+    #[test]
+    fn halt_with_ime_on_services_the_interrupt() {
+        let mut cpu = running(&[HALT, INC_A]);
+        cpu.ime = true;
+        cpu.step();
+
+        cpu.bus.interrupts.request(Interrupt::Timer);
+        assert_eq!(cpu.step(), DISPATCH_CYCLES);
+        assert_eq!(cpu.pc, Interrupt::Timer.vector());
+        assert_eq!(cpu.bus.read_word(cpu.sp), CODE + 1);
+        assert_eq!(cpu.bus.interrupts.flag & Interrupt::Timer.bit(), 0);
+    }
+
+    #[test]
+    fn halt_bug_runs_the_next_byte_twice() {
+        let mut cpu = running(&[HALT, INC_A, 0x00]);
+        cpu.bus.interrupts.request(Interrupt::Timer);
+
+        cpu.step();
+        assert!(!cpu.is_halted());
+        cpu.step();
+        assert_eq!(cpu.pc, CODE + 1);
+        cpu.step();
+        assert_eq!(cpu.pc, CODE + 2);
+        assert_eq!(cpu.registers.a, 0x03);
+    }
+
+    #[test]
+    fn halt_bug_reads_a_prefix_as_its_own_operand() {
+        let mut cpu = running(&[HALT, 0xCB, INC_A]);
+        cpu.bus.interrupts.request(Interrupt::Timer);
+        cpu.registers.e = 0x00;
+
+        cpu.step();
+        // CB CB is SET 1, E
+        assert_eq!(cpu.step(), 8);
+        assert_eq!(cpu.registers.e, 0b0000_0010);
+        assert_eq!(cpu.pc, CODE + 2);
+        cpu.step();
+        assert_eq!(cpu.registers.a, 0x02);
+        assert_eq!(cpu.pc, CODE + 3);
+    }
+
+    #[test]
+    fn stop_waits_for_a_button() {
+        let mut cpu = running(&[STOP, 0x00, INC_A]);
+        cpu.bus.write_byte(JOYP_ADDRESS, 0b0010_0000);
+        cpu.bus.tick(255);
+        cpu.bus.tick(255);
+        assert_ne!(cpu.bus.read_byte(DIV_ADDRESS), 0);
+
+        cpu.step();
+        assert!(cpu.is_stopped());
+        assert_eq!(cpu.pc, CODE + 2);
+        assert_eq!(cpu.bus.read_byte(DIV_ADDRESS), 0);
+
+        // Other interrupts don't count
+        cpu.bus.interrupts.request(Interrupt::Timer);
+        for _ in 0..10 {
+            assert_eq!(cpu.step(), 4);
+            assert!(cpu.is_stopped());
+        }
+
+        let bus = &mut cpu.bus;
+        bus.joypad.press(Button::Right, &mut bus.interrupts);
+        cpu.step();
+        assert!(!cpu.is_stopped());
+        assert_eq!(cpu.registers.a, 0x02);
+    }
+
+    #[test]
+    fn unknown_opcodes_lock_up_the_cpu() {
+        let mut cpu = running(&[0xD3, INC_A]);
+        assert_eq!(cpu.step(), 4);
+        assert!(cpu.is_locked());
+
+        // Not even an interrupt gets it going again
+        cpu.ime = true;
+        cpu.bus.interrupts.request(Interrupt::Timer);
+        for _ in 0..10 {
+            assert_eq!(cpu.step(), 4);
+        }
+        assert_eq!(cpu.pc, CODE);
+        assert_eq!(cpu.registers.a, 0x01);
+        assert_ne!(cpu.bus.interrupts.flag & Interrupt::Timer.bit(), 0);
+    }
+
+    fn flags(cpu: &Cpu) -> u8 {
+        cpu.registers.f.into()
+    }
+
+    #[test]
+    fn daa_after_an_add() {
+        for (a, val, result, f) in [(0x45, 0x38, 0x83, 0x00), (0x99, 0x01, 0x00, 0x90), (0x09, 0x08, 0x17, 0x00), (0x90, 0x90, 0x80, 0x10)] {
+            // ADD A, n then DAA
+            let mut cpu = running(&[0xC6, val, 0x27]);
+            cpu.registers.a = a;
+            cpu.step();
+            cpu.step();
+            assert_eq!((cpu.registers.a, flags(&cpu)), (result, f), "{:02X} + {:02X}", a, val);
+        }
+    }
+
+    #[test]
+    fn daa_after_a_sub() {
+        for (a, val, result, f) in [(0x42, 0x15, 0x27, 0x40), (0x10, 0x20, 0x90, 0x50), (0x15, 0x15, 0x00, 0xC0)] {
+            // SUB n then DAA
+            let mut cpu = running(&[0xD6, val, 0x27]);
+            cpu.registers.a = a;
+            cpu.step();
+            cpu.step();
+            assert_eq!((cpu.registers.a, flags(&cpu)), (result, f), "{:02X} - {:02X}", a, val);
+        }
+    }
+
+    #[test]
+    fn sp_offset_flags_come_from_the_low_byte() {
+        // ADD SP, e8 and LD HL, SP+e8 with the same operands
+        for (sp, offset, result, f) in [(0x00FF, 0x01, 0x0100, 0x30), (0x1000, 0xFF, 0x0FFF, 0x00), (0x0FFF, 0xFF, 0x0FFE, 0x30), (0xFFF8, 0x08, 0x0000, 0x30)] {
+            let mut cpu = running(&[0xE8, offset]);
+            cpu.sp = sp;
+            cpu.registers.f = 0xC0.into();
+            assert_eq!(cpu.step(), 16);
+            assert_eq!((cpu.sp, flags(&cpu)), (result, f), "ADD SP, {:04X} + {:02X}", sp, offset);
+
+            let mut cpu = running(&[0xF8, offset]);
+            cpu.sp = sp;
+            cpu.registers.f = 0xC0.into();
+            assert_eq!(cpu.step(), 12);
+            assert_eq!((cpu.registers.get_hl(), flags(&cpu)), (result, f), "LD HL, {:04X} + {:02X}", sp, offset);
+            assert_eq!(cpu.sp, sp);
+        }
+    }
+
+    #[test]
+    fn inc_and_dec_leave_carry_alone() {
+        for carry in [0x00, 0x10] {
+            // INC A, DEC B
+            let mut cpu = running(&[0x3C, 0x05]);
+            cpu.registers.a = 0xFF;
+            cpu.registers.b = 0x00;
+            cpu.registers.f = carry.into();
+            cpu.step();
+            assert_eq!((cpu.registers.a, flags(&cpu)), (0x00, 0xA0 | carry));
+            cpu.step();
+            assert_eq!((cpu.registers.b, flags(&cpu)), (0xFF, 0x60 | carry));
+        }
+    }
+
+    #[test]
+    fn pop_af_drops_the_low_nibble_of_f() {
+        let mut cpu = running(&[0xF1]);
+        cpu.sp = 0xD000;
+        cpu.bus.write_byte(0xD000, 0xFF);
+        cpu.bus.write_byte(0xD001, 0x12);
+        cpu.step();
+        assert_eq!(cpu.registers.get_af(), 0x12F0);
+        assert_eq!(cpu.sp, 0xD002);
+    }
+
+    #[test]
+    fn rlca_always_clears_zero_but_rlc_a_sets_it() {
+        // RLCA, RLC A
+        let mut cpu = running(&[0x07, 0xCB, 0x07]);
+        cpu.registers.a = 0x00;
+        cpu.registers.f = 0xF0.into();
+        cpu.step();
+        assert_eq!((cpu.registers.a, flags(&cpu)), (0x00, 0x00));
+        cpu.step();
+        assert_eq!((cpu.registers.a, flags(&cpu)), (0x00, 0x80));
+
+        let mut cpu = running(&[0x07]);
+        cpu.registers.a = 0x80;
+        cpu.step();
+        assert_eq!((cpu.registers.a, flags(&cpu)), (0x01, 0x10));
+    }
+
+    #[test]
+    fn jp_hl_jumps_to_hl() {
+        let mut cpu = running(&[0xE9]);
+        cpu.registers.set_hl(0x1234);
+        assert_eq!(cpu.step(), 4);
+        assert_eq!(cpu.pc, 0x1234);
+    }
+
+    #[test]
+    fn rst_calls_its_vector() {
+        for vector in (0x00..=0x38).step_by(8) {
+            let mut cpu = running(&[0xC7 | vector as u8]);
+            cpu.sp = 0xD000;
+            assert_eq!(cpu.step(), 16);
+            assert_eq!(cpu.pc, vector);
+            assert_eq!(cpu.sp, 0xCFFE);
+            assert_eq!(cpu.bus.read_word(cpu.sp), CODE + 1);
+        }
+    }
+
+    #[test]
+    fn bit_res_and_set_work_on_hl() {
+        // BIT 2,(HL), BIT 3,(HL), RES 2,(HL), SET 7,(HL)
+        let mut cpu = running(&[0xCB, 0x56, 0xCB, 0x5E, 0xCB, 0x96, 0xCB, 0xFE]);
+        cpu.registers.set_hl(0xC100);
+        cpu.bus.write_byte(0xC100, 0b0000_0100);
+        cpu.registers.f = 0x50.into();
+
+        cpu.step();
+        assert_eq!(flags(&cpu), 0x30);
+        cpu.step();
+        assert_eq!(flags(&cpu), 0xB0);
+        cpu.step();
+        assert_eq!(cpu.bus.read_byte(0xC100), 0x00);
+        cpu.step();
+        assert_eq!(cpu.bus.read_byte(0xC100), 0x80);
+        // RES and SET leave the flags be
+        assert_eq!(flags(&cpu), 0xB0);
+    }
+
+    #[test]
+    fn swap_clears_everything_but_zero() {
+        // SWAP A, SWAP (HL)
+        let mut cpu = running(&[0xCB, 0x37, 0xCB, 0x36]);
+        cpu.registers.a = 0xF1;
+        cpu.registers.f = 0xF0.into();
+        cpu.registers.set_hl(0xC100);
+        cpu.bus.write_byte(0xC100, 0x00);
+
+        cpu.step();
+        assert_eq!((cpu.registers.a, flags(&cpu)), (0x1F, 0x00));
+        cpu.step();
+        assert_eq!((cpu.bus.read_byte(0xC100), flags(&cpu)), (0x00, 0x80));
+    }
+
+    #[test]
+    fn sra_keeps_the_sign_bit() {
+        // SRA A, SRL A
+        let mut cpu = running(&[0xCB, 0x2F]);
+        cpu.registers.a = 0x81;
+        cpu.step();
+        assert_eq!((cpu.registers.a, flags(&cpu)), (0xC0, 0x10));
+
+        let mut cpu = running(&[0xCB, 0x3F]);
+        cpu.registers.a = 0x81;
+        cpu.step();
+        assert_eq!((cpu.registers.a, flags(&cpu)), (0x40, 0x10));
+    }
+
+    #[test]
+    fn rl_and_rr_go_through_carry() {
+        // RL A twice
+        let mut cpu = running(&[0xCB, 0x17, 0xCB, 0x17]);
+        cpu.registers.a = 0x80;
+        cpu.registers.f = 0x00.into();
+        cpu.step();
+        assert_eq!((cpu.registers.a, flags(&cpu)), (0x00, 0x90));
+        cpu.step();
+        assert_eq!((cpu.registers.a, flags(&cpu)), (0x01, 0x00));
+
+        // RR A twice
+        let mut cpu = running(&[0xCB, 0x1F, 0xCB, 0x1F]);
+        cpu.registers.a = 0x01;
+        cpu.registers.f = 0x00.into();
+        cpu.step();
+        assert_eq!((cpu.registers.a, flags(&cpu)), (0x00, 0x90));
+        cpu.step();
+        assert_eq!((cpu.registers.a, flags(&cpu)), (0x80, 0x00));
+    }
+
+    #[test]
+    fn only_the_prefixed_rotates_set_zero() {
+        // RLA against RL A, RRA against RR A
+        for (code, a) in [([0x17, 0xCB, 0x17], 0x80), ([0x1F, 0xCB, 0x1F], 0x01)] {
+            let mut cpu = running(&code);
+            cpu.registers.a = a;
+            cpu.registers.f = 0x00.into();
+            cpu.step();
+            assert_eq!((cpu.registers.a, flags(&cpu)), (0x00, 0x10));
+
+            cpu.pc = CODE + 1;
+            cpu.registers.a = a;
+            cpu.registers.f = 0x00.into();
+            cpu.step();
+            assert_eq!((cpu.registers.a, flags(&cpu)), (0x00, 0x90));
+        }
+    }
+
+    #[test]
+    fn step_returns_the_cycles_taken() {
+        const Z: u8 = 0x80;
+        // Code, flags, cycles. NZ conditions so Z set is the branch not taken
+        let cases: [(&[u8], u8, u8); 20] = [
+            (&[0x20, 0x02], 0, 12),
+            (&[0x20, 0x02], Z, 8),
+            (&[0x18, 0x02], Z, 12),
+            (&[0xC2, 0x00, 0xC1], 0, 16),
+            (&[0xC2, 0x00, 0xC1], Z, 12),
+            (&[0xC3, 0x00, 0xC1], Z, 16),
+            (&[0xC4, 0x00, 0xC1], 0, 24),
+            (&[0xC4, 0x00, 0xC1], Z, 12),
+            (&[0xCD, 0x00, 0xC1], Z, 24),
+            (&[0xC0], 0, 20),
+            (&[0xC0], Z, 8),
+            (&[0xC9], 0, 16),
+            (&[0xD9], 0, 16),
+            (&[0xFF], 0, 16),
+            (&[0x00], 0, 4),
+            (&[0x34], 0, 12),
+            (&[0xCB, 0x06], 0, 16),
+            (&[0xCB, 0xC6], 0, 16),
+            (&[0xCB, 0x46], 0, 12),
+            (&[0xCB, 0x00], 0, 8),
+        ];
+
+        for (code, f, cycles) in cases {
+            let mut cpu = running(code);
+            cpu.registers.f = f.into();
+            cpu.registers.set_hl(0xC100);
+            cpu.sp = 0xD000;
+            assert_eq!(cpu.step(), cycles, "{:02X?} with F={:02X}", code, f);
+        }
+    }
+
+    #[test]
+    fn dispatch_pushes_pc_and_takes_20_cycles() {
+        let mut cpu = running(&[INC_A]);
+        cpu.ime = true;
+        cpu.sp = 0xD000;
+        cpu.bus.interrupts.enable = Interrupt::VBlank.bit() | Interrupt::Timer.bit();
+        cpu.bus.interrupts.request(Interrupt::Timer);
+        cpu.bus.interrupts.request(Interrupt::VBlank);
+
+        assert_eq!(cpu.step(), DISPATCH_CYCLES);
+        assert_eq!(cpu.pc, Interrupt::VBlank.vector());
+        assert_eq!(cpu.sp, 0xCFFE);
+        assert_eq!(cpu.bus.read_word(cpu.sp), CODE);
+        assert!(!cpu.ime);
+        assert_eq!(cpu.bus.interrupts.flag, Interrupt::Timer.bit());
+        assert_eq!(cpu.registers.a, 0x01);
+    }
+
+    #[test]
+    fn ei_waits_for_the_next_instruction() {
+        // EI, INC A, INC A
+        let mut cpu = running(&[0xFB, INC_A, INC_A]);
+        cpu.sp = 0xD000;
+        cpu.bus.interrupts.request(Interrupt::Timer);
+
+        cpu.step();
+        assert!(!cpu.ime);
+        assert_eq!(cpu.step(), 4);
+        assert_eq!(cpu.registers.a, 0x02);
+        assert_eq!(cpu.step(), DISPATCH_CYCLES);
+        assert_eq!(cpu.bus.read_word(cpu.sp), CODE + 2);
+    }
+
+    #[test]
+    fn di_straight_after_ei_cancels_it() {
+        // EI, DI, INC A
+        let mut cpu = running(&[0xFB, 0xF3, INC_A]);
+        cpu.bus.interrupts.request(Interrupt::Timer);
+
+        for _ in 0..3 {
+            cpu.step();
+        }
+        assert!(!cpu.ime);
+        assert_eq!(cpu.pc, CODE + 3);
+        assert_eq!(cpu.registers.a, 0x02);
+    }
+
+    #[test]
+    fn reti_enables_interrupts_at_once() {
+        // RETI back to an INC A that never gets to run
+        let mut cpu = running(&[0xD9, INC_A]);
+        cpu.sp = 0xCFFE;
+        cpu.bus.write_byte(0xCFFE, 0x01);
+        cpu.bus.write_byte(0xCFFF, 0xC0);
+        cpu.bus.interrupts.request(Interrupt::Timer);
+
+        cpu.step();
+        assert!(cpu.ime);
+        assert_eq!(cpu.pc, CODE + 1);
+        assert_eq!(cpu.step(), DISPATCH_CYCLES);
+        assert_eq!(cpu.pc, Interrupt::Timer.vector());
+        assert_eq!(cpu.registers.a, 0x01);
     }
 }
@@ -37,9 +37,19 @@ pub enum WordTarget {
     BC, DE, HL, SP
 }
 
+#[derive(PartialEq)]
+pub enum IncDecTarget {
+    A, B, C, D, E, H, L, HL
+}
+
+#[derive(PartialEq)]
+pub enum PrefixTarget {
+    A, B, C, D, E, H, L, HL
+}
+
 #[derive(PartialEq)]
 pub enum StackTarget {
-    BC
+    AF, BC, DE, HL
 }
 
 #[derive(PartialEq)]
@@ -56,20 +66,55 @@ pub enum Instruction {
     SBC(ArithmeticTarget),
     ADDHL(WordTarget),
     ADDSP,
+    INC(IncDecTarget),
+    DEC(IncDecTarget),
+    INC16(WordTarget),
+    DEC16(WordTarget),
     JP(JumpTest),
+    JPHL,
+    JR(JumpTest),
     LD(LoadType),
     PUSH(StackTarget),
     POP(StackTarget),
     CALL(JumpTest),
     RET(JumpTest),
+    RETI,
+    RST(u8),
     NOP,
     AND(ArithmeticTarget),
     XOR(ArithmeticTarget),
     OR(ArithmeticTarget),
-    CP(ArithmeticTarget)
+    CP(ArithmeticTarget),
+    RLCA,
+    RRCA,
+    RLA,
+    RRA,
+    DAA,
+    CPL,
+    SCF,
+    CCF,
+    DI,
+    EI,
+    HALT,
+    STOP,
+
+    // CB prefixed
+    RLC(PrefixTarget),
+    RRC(PrefixTarget),
+    RL(PrefixTarget),
+    RR(PrefixTarget),
+    SLA(PrefixTarget),
+    SRA(PrefixTarget),
+    SWAP(PrefixTarget),
+    SRL(PrefixTarget),
+    BIT(u8, PrefixTarget),
+    RES(u8, PrefixTarget),
+    SET(u8, PrefixTarget)
 }
 
 impl Instruction {
+  /// Decodes an opcode into its instruction and T-cycle cost. Conditional
+  /// branches report the not-taken cost, see `Cpu::branch_cycles`.
   pub fn from_byte(byte: u8, prefixed: bool) -> Option<(Instruction, u8)> {
     if prefixed {
       Instruction::from_byte_prefixed(byte)
@@ -80,200 +125,541 @@ impl Instruction {
 
   fn from_byte_prefixed(byte: u8) -> Option<(Instruction, u8)> {
     match byte {
-      //0x00 => Some(Instruction::RLC(PrefixTarget::B)),
-      _ => None
+      0x00 => Some((Instruction::RLC(PrefixTarget::B), 8)),
+      0x01 => Some((Instruction::RLC(PrefixTarget::C), 8)),
+      0x02 => Some((Instruction::RLC(PrefixTarget::D), 8)),
+      0x03 => Some((Instruction::RLC(PrefixTarget::E), 8)),
+      0x04 => Some((Instruction::RLC(PrefixTarget::H), 8)),
+      0x05 => Some((Instruction::RLC(PrefixTarget::L), 8)),
+      0x06 => Some((Instruction::RLC(PrefixTarget::HL), 16)),
+      0x07 => Some((Instruction::RLC(PrefixTarget::A), 8)),
+      0x08 => Some((Instruction::RRC(PrefixTarget::B), 8)),
+      0x09 => Some((Instruction::RRC(PrefixTarget::C), 8)),
+      0x0A => Some((Instruction::RRC(PrefixTarget::D), 8)),
+      0x0B => Some((Instruction::RRC(PrefixTarget::E), 8)),
+      0x0C => Some((Instruction::RRC(PrefixTarget::H), 8)),
+      0x0D => Some((Instruction::RRC(PrefixTarget::L), 8)),
+      0x0E => Some((Instruction::RRC(PrefixTarget::HL), 16)),
+      0x0F => Some((Instruction::RRC(PrefixTarget::A), 8)),
+
+      0x10 => Some((Instruction::RL(PrefixTarget::B), 8)),
+      0x11 => Some((Instruction::RL(PrefixTarget::C), 8)),
+      0x12 => Some((Instruction::RL(PrefixTarget::D), 8)),
+      0x13 => Some((Instruction::RL(PrefixTarget::E), 8)),
+      0x14 => Some((Instruction::RL(PrefixTarget::H), 8)),
+      0x15 => Some((Instruction::RL(PrefixTarget::L), 8)),
+      0x16 => Some((Instruction::RL(PrefixTarget::HL), 16)),
+      0x17 => Some((Instruction::RL(PrefixTarget::A), 8)),
+      0x18 => Some((Instruction::RR(PrefixTarget::B), 8)),
+      0x19 => Some((Instruction::RR(PrefixTarget::C), 8)),
+      0x1A => Some((Instruction::RR(PrefixTarget::D), 8)),
+      0x1B => Some((Instruction::RR(PrefixTarget::E), 8)),
+      0x1C => Some((Instruction::RR(PrefixTarget::H), 8)),
+      0x1D => Some((Instruction::RR(PrefixTarget::L), 8)),
+      0x1E => Some((Instruction::RR(PrefixTarget::HL), 16)),
+      0x1F => Some((Instruction::RR(PrefixTarget::A), 8)),
+
+      0x20 => Some((Instruction::SLA(PrefixTarget::B), 8)),
+      0x21 => Some((Instruction::SLA(PrefixTarget::C), 8)),
+      0x22 => Some((Instruction::SLA(PrefixTarget::D), 8)),
+      0x23 => Some((Instruction::SLA(PrefixTarget::E), 8)),
+      0x24 => Some((Instruction::SLA(PrefixTarget::H), 8)),
+      0x25 => Some((Instruction::SLA(PrefixTarget::L), 8)),
+      0x26 => Some((Instruction::SLA(PrefixTarget::HL), 16)),
+      0x27 => Some((Instruction::SLA(PrefixTarget::A), 8)),
+      0x28 => Some((Instruction::SRA(PrefixTarget::B), 8)),
+      0x29 => Some((Instruction::SRA(PrefixTarget::C), 8)),
+      0x2A => Some((Instruction::SRA(PrefixTarget::D), 8)),
+      0x2B => Some((Instruction::SRA(PrefixTarget::E), 8)),
+      0x2C => Some((Instruction::SRA(PrefixTarget::H), 8)),
+      0x2D => Some((Instruction::SRA(PrefixTarget::L), 8)),
+      0x2E => Some((Instruction::SRA(PrefixTarget::HL), 16)),
+      0x2F => Some((Instruction::SRA(PrefixTarget::A), 8)),
+
+      0x30 => Some((Instruction::SWAP(PrefixTarget::B), 8)),
+      0x31 => Some((Instruction::SWAP(PrefixTarget::C), 8)),
+      0x32 => Some((Instruction::SWAP(PrefixTarget::D), 8)),
+      0x33 => Some((Instruction::SWAP(PrefixTarget::E), 8)),
+      0x34 => Some((Instruction::SWAP(PrefixTarget::H), 8)),
+      0x35 => Some((Instruction::SWAP(PrefixTarget::L), 8)),
+      0x36 => Some((Instruction::SWAP(PrefixTarget::HL), 16)),
+      0x37 => Some((Instruction::SWAP(PrefixTarget::A), 8)),
+      0x38 => Some((Instruction::SRL(PrefixTarget::B), 8)),
+      0x39 => Some((Instruction::SRL(PrefixTarget::C), 8)),
+      0x3A => Some((Instruction::SRL(PrefixTarget::D), 8)),
+      0x3B => Some((Instruction::SRL(PrefixTarget::E), 8)),
+      0x3C => Some((Instruction::SRL(PrefixTarget::H), 8)),
+      0x3D => Some((Instruction::SRL(PrefixTarget::L), 8)),
+      0x3E => Some((Instruction::SRL(PrefixTarget::HL), 16)),
+      0x3F => Some((Instruction::SRL(PrefixTarget::A), 8)),
+
+      0x40 => Some((Instruction::BIT(0, PrefixTarget::B), 8)),
+      0x41 => Some((Instruction::BIT(0, PrefixTarget::C), 8)),
+      0x42 => Some((Instruction::BIT(0, PrefixTarget::D), 8)),
+      0x43 => Some((Instruction::BIT(0, PrefixTarget::E), 8)),
+      0x44 => Some((Instruction::BIT(0, PrefixTarget::H), 8)),
+      0x45 => Some((Instruction::BIT(0, PrefixTarget::L), 8)),
+      0x46 => Some((Instruction::BIT(0, PrefixTarget::HL), 12)),
+      0x47 => Some((Instruction::BIT(0, PrefixTarget::A), 8)),
+      0x48 => Some((Instruction::BIT(1, PrefixTarget::B), 8)),
+      0x49 => Some((Instruction::BIT(1, PrefixTarget::C), 8)),
+      0x4A => Some((Instruction::BIT(1, PrefixTarget::D), 8)),
+      0x4B => Some((Instruction::BIT(1, PrefixTarget::E), 8)),
+      0x4C => Some((Instruction::BIT(1, PrefixTarget::H), 8)),
+      0x4D => Some((Instruction::BIT(1, PrefixTarget::L), 8)),
+      0x4E => Some((Instruction::BIT(1, PrefixTarget::HL), 12)),
+      0x4F => Some((Instruction::BIT(1, PrefixTarget::A), 8)),
+
+      0x50 => Some((Instruction::BIT(2, PrefixTarget::B), 8)),
+      0x51 => Some((Instruction::BIT(2, PrefixTarget::C), 8)),
+      0x52 => Some((Instruction::BIT(2, PrefixTarget::D), 8)),
+      0x53 => Some((Instruction::BIT(2, PrefixTarget::E), 8)),
+      0x54 => Some((Instruction::BIT(2, PrefixTarget::H), 8)),
+      0x55 => Some((Instruction::BIT(2, PrefixTarget::L), 8)),
+      0x56 => Some((Instruction::BIT(2, PrefixTarget::HL), 12)),
+      0x57 => Some((Instruction::BIT(2, PrefixTarget::A), 8)),
+      0x58 => Some((Instruction::BIT(3, PrefixTarget::B), 8)),
+      0x59 => Some((Instruction::BIT(3, PrefixTarget::C), 8)),
+      0x5A => Some((Instruction::BIT(3, PrefixTarget::D), 8)),
+      0x5B => Some((Instruction::BIT(3, PrefixTarget::E), 8)),
+      0x5C => Some((Instruction::BIT(3, PrefixTarget::H), 8)),
+      0x5D => Some((Instruction::BIT(3, PrefixTarget::L), 8)),
+      0x5E => Some((Instruction::BIT(3, PrefixTarget::HL), 12)),
+      0x5F => Some((Instruction::BIT(3, PrefixTarget::A), 8)),
+
+      0x60 => Some((Instruction::BIT(4, PrefixTarget::B), 8)),
+      0x61 => Some((Instruction::BIT(4, PrefixTarget::C), 8)),
+      0x62 => Some((Instruction::BIT(4, PrefixTarget::D), 8)),
+      0x63 => Some((Instruction::BIT(4, PrefixTarget::E), 8)),
+      0x64 => Some((Instruction::BIT(4, PrefixTarget::H), 8)),
+      0x65 => Some((Instruction::BIT(4, PrefixTarget::L), 8)),
+      0x66 => Some((Instruction::BIT(4, PrefixTarget::HL), 12)),
+      0x67 => Some((Instruction::BIT(4, PrefixTarget::A), 8)),
+      0x68 => Some((Instruction::BIT(5, PrefixTarget::B), 8)),
+      0x69 => Some((Instruction::BIT(5, PrefixTarget::C), 8)),
+      0x6A => Some((Instruction::BIT(5, PrefixTarget::D), 8)),
+      0x6B => Some((Instruction::BIT(5, PrefixTarget::E), 8)),
+      0x6C => Some((Instruction::BIT(5, PrefixTarget::H), 8)),
+      0x6D => Some((Instruction::BIT(5, PrefixTarget::L), 8)),
+      0x6E => Some((Instruction::BIT(5, PrefixTarget::HL), 12)),
+      0x6F => Some((Instruction::BIT(5, PrefixTarget::A), 8)),
+
+      0x70 => Some((Instruction::BIT(6, PrefixTarget::B), 8)),
+      0x71 => Some((Instruction::BIT(6, PrefixTarget::C), 8)),
+      0x72 => Some((Instruction::BIT(6, PrefixTarget::D), 8)),
+      0x73 => Some((Instruction::BIT(6, PrefixTarget::E), 8)),
+      0x74 => Some((Instruction::BIT(6, PrefixTarget::H), 8)),
+      0x75 => Some((Instruction::BIT(6, PrefixTarget::L), 8)),
+      0x76 => Some((Instruction::BIT(6, PrefixTarget::HL), 12)),
+      0x77 => Some((Instruction::BIT(6, PrefixTarget::A), 8)),
+      0x78 => Some((Instruction::BIT(7, PrefixTarget::B), 8)),
+      0x79 => Some((Instruction::BIT(7, PrefixTarget::C), 8)),
+      0x7A => Some((Instruction::BIT(7, PrefixTarget::D), 8)),
+      0x7B => Some((Instruction::BIT(7, PrefixTarget::E), 8)),
+      0x7C => Some((Instruction::BIT(7, PrefixTarget::H), 8)),
+      0x7D => Some((Instruction::BIT(7, PrefixTarget::L), 8)),
+      0x7E => Some((Instruction::BIT(7, PrefixTarget::HL), 12)),
+      0x7F => Some((Instruction::BIT(7, PrefixTarget::A), 8)),
+
+      0x80 => Some((Instruction::RES(0, PrefixTarget::B), 8)),
+      0x81 => Some((Instruction::RES(0, PrefixTarget::C), 8)),
+      0x82 => Some((Instruction::RES(0, PrefixTarget::D), 8)),
+      0x83 => Some((Instruction::RES(0, PrefixTarget::E), 8)),
+      0x84 => Some((Instruction::RES(0, PrefixTarget::H), 8)),
+      0x85 => Some((Instruction::RES(0, PrefixTarget::L), 8)),
+      0x86 => Some((Instruction::RES(0, PrefixTarget::HL), 16)),
+      0x87 => Some((Instruction::RES(0, PrefixTarget::A), 8)),
+      0x88 => Some((Instruction::RES(1, PrefixTarget::B), 8)),
+      0x89 => Some((Instruction::RES(1, PrefixTarget::C), 8)),
+      0x8A => Some((Instruction::RES(1, PrefixTarget::D), 8)),
+      0x8B => Some((Instruction::RES(1, PrefixTarget::E), 8)),
+      0x8C => Some((Instruction::RES(1, PrefixTarget::H), 8)),
+      0x8D => Some((Instruction::RES(1, PrefixTarget::L), 8)),
+      0x8E => Some((Instruction::RES(1, PrefixTarget::HL), 16)),
+      0x8F => Some((Instruction::RES(1, PrefixTarget::A), 8)),
+
+      0x90 => Some((Instruction::RES(2, PrefixTarget::B), 8)),
+      0x91 => Some((Instruction::RES(2, PrefixTarget::C), 8)),
+      0x92 => Some((Instruction::RES(2, PrefixTarget::D), 8)),
+      0x93 => Some((Instruction::RES(2, PrefixTarget::E), 8)),
+      0x94 => Some((Instruction::RES(2, PrefixTarget::H), 8)),
+      0x95 => Some((Instruction::RES(2, PrefixTarget::L), 8)),
+      0x96 => Some((Instruction::RES(2, PrefixTarget::HL), 16)),
+      0x97 => Some((Instruction::RES(2, PrefixTarget::A), 8)),
+      0x98 => Some((Instruction::RES(3, PrefixTarget::B), 8)),
+      0x99 => Some((Instruction::RES(3, PrefixTarget::C), 8)),
+      0x9A => Some((Instruction::RES(3, PrefixTarget::D), 8)),
+      0x9B => Some((Instruction::RES(3, PrefixTarget::E), 8)),
+      0x9C => Some((Instruction::RES(3, PrefixTarget::H), 8)),
+      0x9D => Some((Instruction::RES(3, PrefixTarget::L), 8)),
+      0x9E => Some((Instruction::RES(3, PrefixTarget::HL), 16)),
+      0x9F => Some((Instruction::RES(3, PrefixTarget::A), 8)),
+
+      0xA0 => Some((Instruction::RES(4, PrefixTarget::B), 8)),
+      0xA1 => Some((Instruction::RES(4, PrefixTarget::C), 8)),
+      0xA2 => Some((Instruction::RES(4, PrefixTarget::D), 8)),
+      0xA3 => Some((Instruction::RES(4, PrefixTarget::E), 8)),
+      0xA4 => Some((Instruction::RES(4, PrefixTarget::H), 8)),
+      0xA5 => Some((Instruction::RES(4, PrefixTarget::L), 8)),
+      0xA6 => Some((Instruction::RES(4, PrefixTarget::HL), 16)),
+      0xA7 => Some((Instruction::RES(4, PrefixTarget::A), 8)),
+      0xA8 => Some((Instruction::RES(5, PrefixTarget::B), 8)),
+      0xA9 => Some((Instruction::RES(5, PrefixTarget::C), 8)),
+      0xAA => Some((Instruction::RES(5, PrefixTarget::D), 8)),
+      0xAB => Some((Instruction::RES(5, PrefixTarget::E), 8)),
+      0xAC => Some((Instruction::RES(5, PrefixTarget::H), 8)),
+      0xAD => Some((Instruction::RES(5, PrefixTarget::L), 8)),
+      0xAE => Some((Instruction::RES(5, PrefixTarget::HL), 16)),
+      0xAF => Some((Instruction::RES(5, PrefixTarget::A), 8)),
+
+      0xB0 => Some((Instruction::RES(6, PrefixTarget::B), 8)),
+      0xB1 => Some((Instruction::RES(6, PrefixTarget::C), 8)),
+      0xB2 => Some((Instruction::RES(6, PrefixTarget::D), 8)),
+      0xB3 => Some((Instruction::RES(6, PrefixTarget::E), 8)),
+      0xB4 => Some((Instruction::RES(6, PrefixTarget::H), 8)),
+      0xB5 => Some((Instruction::RES(6, PrefixTarget::L), 8)),
+      0xB6 => Some((Instruction::RES(6, PrefixTarget::HL), 16)),
+      0xB7 => Some((Instruction::RES(6, PrefixTarget::A), 8)),
+      0xB8 => Some((Instruction::RES(7, PrefixTarget::B), 8)),
+      0xB9 => Some((Instruction::RES(7, PrefixTarget::C), 8)),
+      0xBA => Some((Instruction::RES(7, PrefixTarget::D), 8)),
+      0xBB => Some((Instruction::RES(7, PrefixTarget::E), 8)),
+      0xBC => Some((Instruction::RES(7, PrefixTarget::H), 8)),
+      0xBD => Some((Instruction::RES(7, PrefixTarget::L), 8)),
+      0xBE => Some((Instruction::RES(7, PrefixTarget::HL), 16)),
+      0xBF => Some((Instruction::RES(7, PrefixTarget::A), 8)),
+
+      0xC0 => Some((Instruction::SET(0, PrefixTarget::B), 8)),
+      0xC1 => Some((Instruction::SET(0, PrefixTarget::C), 8)),
+      0xC2 => Some((Instruction::SET(0, PrefixTarget::D), 8)),
+      0xC3 => Some((Instruction::SET(0, PrefixTarget::E), 8)),
+      0xC4 => Some((Instruction::SET(0, PrefixTarget::H), 8)),
+      0xC5 => Some((Instruction::SET(0, PrefixTarget::L), 8)),
+      0xC6 => Some((Instruction::SET(0, PrefixTarget::HL), 16)),
+      0xC7 => Some((Instruction::SET(0, PrefixTarget::A), 8)),
+      0xC8 => Some((Instruction::SET(1, PrefixTarget::B), 8)),
+      0xC9 => Some((Instruction::SET(1, PrefixTarget::C), 8)),
+      0xCA => Some((Instruction::SET(1, PrefixTarget::D), 8)),
+      0xCB => Some((Instruction::SET(1, PrefixTarget::E), 8)),
+      0xCC => Some((Instruction::SET(1, PrefixTarget::H), 8)),
+      0xCD => Some((Instruction::SET(1, PrefixTarget::L), 8)),
+      0xCE => Some((Instruction::SET(1, PrefixTarget::HL), 16)),
+      0xCF => Some((Instruction::SET(1, PrefixTarget::A), 8)),
+
+      0xD0 => Some((Instruction::SET(2, PrefixTarget::B), 8)),
+      0xD1 => Some((Instruction::SET(2, PrefixTarget::C), 8)),
+      0xD2 => Some((Instruction::SET(2, PrefixTarget::D), 8)),
+      0xD3 => Some((Instruction::SET(2, PrefixTarget::E), 8)),
+      0xD4 => Some((Instruction::SET(2, PrefixTarget::H), 8)),
+      0xD5 => Some((Instruction::SET(2, PrefixTarget::L), 8)),
+      0xD6 => Some((Instruction::SET(2, PrefixTarget::HL), 16)),
+      0xD7 => Some((Instruction::SET(2, PrefixTarget::A), 8)),
+      0xD8 => Some((Instruction::SET(3, PrefixTarget::B), 8)),
+      0xD9 => Some((Instruction::SET(3, PrefixTarget::C), 8)),
+      0xDA => Some((Instruction::SET(3, PrefixTarget::D), 8)),
+      0xDB => Some((Instruction::SET(3, PrefixTarget::E), 8)),
+      0xDC => Some((Instruction::SET(3, PrefixTarget::H), 8)),
+      0xDD => Some((Instruction::SET(3, PrefixTarget::L), 8)),
+      0xDE => Some((Instruction::SET(3, PrefixTarget::HL), 16)),
+      0xDF => Some((Instruction::SET(3, PrefixTarget::A), 8)),
+
+      0xE0 => Some((Instruction::SET(4, PrefixTarget::B), 8)),
+      0xE1 => Some((Instruction::SET(4, PrefixTarget::C), 8)),
+      0xE2 => Some((Instruction::SET(4, PrefixTarget::D), 8)),
+      0xE3 => Some((Instruction::SET(4, PrefixTarget::E), 8)),
+      0xE4 => Some((Instruction::SET(4, PrefixTarget::H), 8)),
+      0xE5 => Some((Instruction::SET(4, PrefixTarget::L), 8)),
+      0xE6 => Some((Instruction::SET(4, PrefixTarget::HL), 16)),
+      0xE7 => Some((Instruction::SET(4, PrefixTarget::A), 8)),
+      0xE8 => Some((Instruction::SET(5, PrefixTarget::B), 8)),
+      0xE9 => Some((Instruction::SET(5, PrefixTarget::C), 8)),
+      0xEA => Some((Instruction::SET(5, PrefixTarget::D), 8)),
+      0xEB => Some((Instruction::SET(5, PrefixTarget::E), 8)),
+      0xEC => Some((Instruction::SET(5, PrefixTarget::H), 8)),
+      0xED => Some((Instruction::SET(5, PrefixTarget::L), 8)),
+      0xEE => Some((Instruction::SET(5, PrefixTarget::HL), 16)),
+      0xEF => Some((Instruction::SET(5, PrefixTarget::A), 8)),
+
+      0xF0 => Some((Instruction::SET(6, PrefixTarget::B), 8)),
+      0xF1 => Some((Instruction::SET(6, PrefixTarget::C), 8)),
+      0xF2 => Some((Instruction::SET(6, PrefixTarget::D), 8)),
+      0xF3 => Some((Instruction::SET(6, PrefixTarget::E), 8)),
+      0xF4 => Some((Instruction::SET(6, PrefixTarget::H), 8)),
+      0xF5 => Some((Instruction::SET(6, PrefixTarget::L), 8)),
+      0xF6 => Some((Instruction::SET(6, PrefixTarget::HL), 16)),
+      0xF7 => Some((Instruction::SET(6, PrefixTarget::A), 8)),
+      0xF8 => Some((Instruction::SET(7, PrefixTarget::B), 8)),
+      0xF9 => Some((Instruction::SET(7, PrefixTarget::C), 8)),
+      0xFA => Some((Instruction::SET(7, PrefixTarget::D), 8)),
+      0xFB => Some((Instruction::SET(7, PrefixTarget::E), 8)),
+      0xFC => Some((Instruction::SET(7, PrefixTarget::H), 8)),
+      0xFD => Some((Instruction::SET(7, PrefixTarget::L), 8)),
+      0xFE => Some((Instruction::SET(7, PrefixTarget::HL), 16)),
+      0xFF => Some((Instruction::SET(7, PrefixTarget::A), 8)),
     }
   }
 
   fn from_byte_not_prefixed(byte: u8) -> Option<(Instruction, u8)> {
     match byte {
-      0x00 => Some((Instruction::NOP, 1)),
-      0x01 => Some((Instruction::LD(LoadType::Word(LoadWordTarget::BC, LoadWordSource::D16)), 3)),
-      0x02 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::BC, LoadByteSource::A)), 2)),
-      0x06 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::D8)), 2)),
-      0x08 => Some((Instruction::LD(LoadType::Word(LoadWordTarget::A16, LoadWordSource::SP)), 5)),
-      0x09 => Some((Instruction::ADDHL(WordTarget::BC), 2)),
-      0x0A => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::BC)), 2)),
-      0x0E => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::D8)), 2)),
-
-      0x11 => Some((Instruction::LD(LoadType::Word(LoadWordTarget::DE, LoadWordSource::D16)), 3)),
-      0x12 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::DE, LoadByteSource::A)), 2)),
-      0x16 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::D8)), 2)),
-      0x19 => Some((Instruction::ADDHL(WordTarget::DE), 2)),
-      0x1A => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::DE)), 2)),
-      0x1E => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::D8)), 2)),
-
-      0x21 => Some((Instruction::LD(LoadType::Word(LoadWordTarget::HL, LoadWordSource::D16)), 3)),
-      0x22 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HLI, LoadByteSource::A)), 2)),
-      0x26 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::D8)), 2)),
-      0x29 => Some((Instruction::ADDHL(WordTarget::HL), 2)),
-      0x2A => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::HLI)), 2)),
-      0x2E => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::D8)), 2)),
-
-      0x31 => Some((Instruction::LD(LoadType::Word(LoadWordTarget::SP, LoadWordSource::D16)), 3)),
-      0x32 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HLI, LoadByteSource::A)), 2)),
-      0x36 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HL, LoadByteSource::D8)), 3)), 
-      0x39 => Some((Instruction::ADDHL(WordTarget::SP), 2)),
-      0x3A => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::HLD)), 2)),
-      0x3E => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::D8)), 2)),
-
-      0x40 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::B)), 1)),
-      0x41 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::C)), 1)),
-      0x42 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::D)), 1)),
-      0x43 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::E)), 1)),
-      0x44 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::H)), 1)),
-      0x45 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::L)), 1)),
-      0x46 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::HL)), 2)),
-      0x47 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::A)), 1)),
-      0x48 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::B)), 1)),
-      0x49 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::C)), 1)),
-      0x4A => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::D)), 1)),
-      0x4B => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::E)), 1)),
-      0x4C => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::H)), 1)),
-      0x4D => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::L)), 1)),
-      0x4E => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::HL)), 1)),
-      0x4F => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::A)), 1)),
-
-      0x50 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::B)), 1)),
-      0x51 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::C)), 1)),
-      0x52 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::D)), 1)),
-      0x53 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::E)), 1)),
-      0x54 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::H)), 1)),
-      0x55 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::L)), 1)),
-      0x56 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::HL)), 2)),
-      0x57 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::A)), 1)),
-      0x58 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::B)), 1)),
-      0x59 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::C)), 1)),
-      0x5A => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::D)), 1)),
-      0x5B => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::E)), 1)),
-      0x5C => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::H)), 1)),
-      0x5D => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::L)), 1)),
-      0x5E => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::HL)), 1)),
-      0x5F => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::A)), 1)),
-
-      0x60 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::B)), 1)),
-      0x61 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::C)), 1)),
-      0x62 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::D)), 1)),
-      0x63 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::E)), 1)),
-      0x64 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::H)), 1)),
-      0x65 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::L)), 1)),
-      0x66 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::HL)), 2)),
-      0x67 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::A)), 1)),
-      0x68 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::B)), 1)),
-      0x69 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::C)), 1)),
-      0x6A => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::D)), 1)),
-      0x6B => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::E)), 1)),
-      0x6C => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::H)), 1)),
-      0x6D => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::L)), 1)),
-      0x6E => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::HL)), 1)),
-      0x6F => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::A)), 1)),
-
-      0x70 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HL, LoadByteSource::B)), 1)),
-      0x71 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HL, LoadByteSource::C)), 1)),
-      0x72 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HL, LoadByteSource::D)), 1)),
-      0x73 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HL, LoadByteSource::E)), 1)),
-      0x74 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HL, LoadByteSource::H)), 1)),
-      0x75 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HL, LoadByteSource::L)), 1)),
-      //0x76 => Some((Instruction::HALT, 1)),
-      0x77 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HL, LoadByteSource::A)), 1)),
-      0x78 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::B)), 1)),
-      0x79 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::C)), 1)),
-      0x7A => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::D)), 1)),
-      0x7B => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::E)), 1)),
-      0x7C => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::H)), 1)),
-      0x7D => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::L)), 1)),
-      0x7E => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::HL)), 1)),
-      0x7F => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::A)), 1)),
-
-      0x80 => Some((Instruction::ADD(ArithmeticTarget::B), 1)),
-      0x81 => Some((Instruction::ADD(ArithmeticTarget::C), 1)),
-      0x82 => Some((Instruction::ADD(ArithmeticTarget::D), 1)),
-      0x83 => Some((Instruction::ADD(ArithmeticTarget::E), 1)),
-      0x84 => Some((Instruction::ADD(ArithmeticTarget::H), 1)),
-      0x85 => Some((Instruction::ADD(ArithmeticTarget::L), 1)),
-      0x86 => Some((Instruction::ADD(ArithmeticTarget::HL), 2)),
-      0x87 => Some((Instruction::ADD(ArithmeticTarget::A), 1)),
-      0x88 => Some((Instruction::ADC(ArithmeticTarget::B), 1)),
-      0x89 => Some((Instruction::ADC(ArithmeticTarget::C), 1)),
-      0x8A => Some((Instruction::ADC(ArithmeticTarget::D), 1)),
-      0x8B => Some((Instruction::ADC(ArithmeticTarget::E), 1)),
-      0x8C => Some((Instruction::ADC(ArithmeticTarget::H), 1)),
-      0x8D => Some((Instruction::ADC(ArithmeticTarget::L), 1)),
-      0x8E => Some((Instruction::ADC(ArithmeticTarget::HL), 2)),
-      0x8F => Some((Instruction::ADC(ArithmeticTarget::A), 1)),
-
-      0x90 => Some((Instruction::SUB(ArithmeticTarget::B), 1)),
-      0x91 => Some((Instruction::SUB(ArithmeticTarget::C), 1)),
-      0x92 => Some((Instruction::SUB(ArithmeticTarget::D), 1)),
-      0x93 => Some((Instruction::SUB(ArithmeticTarget::E), 1)),
-      0x94 => Some((Instruction::SUB(ArithmeticTarget::H), 1)),
-      0x95 => Some((Instruction::SUB(ArithmeticTarget::L), 1)),
-      0x96 => Some((Instruction::SUB(ArithmeticTarget::HL), 2)),
-      0x97 => Some((Instruction::SUB(ArithmeticTarget::A), 1)),
-      0x98 => Some((Instruction::SBC(ArithmeticTarget::B), 1)),
-      0x99 => Some((Instruction::SBC(ArithmeticTarget::C), 1)),
-      0x9A => Some((Instruction::SBC(ArithmeticTarget::D), 1)),
-      0x9B => Some((Instruction::SBC(ArithmeticTarget::E), 1)),
-      0x9C => Some((Instruction::SBC(ArithmeticTarget::H), 1)),
-      0x9D => Some((Instruction::SBC(ArithmeticTarget::L), 1)),
-      0x9E => Some((Instruction::SBC(ArithmeticTarget::HL), 2)),
-      0xDE => Some((Instruction::SBC(ArithmeticTarget::N8), 2)),
-      0x9F => Some((Instruction::SBC(ArithmeticTarget::A), 1)),
-
-      0xA0 => Some((Instruction::SUB(ArithmeticTarget::B), 1)),
-      0xA1 => Some((Instruction::SUB(ArithmeticTarget::C), 1)),
-      0xA2 => Some((Instruction::SUB(ArithmeticTarget::D), 1)),
-      0xA3 => Some((Instruction::SUB(ArithmeticTarget::E), 1)),
-      0xA4 => Some((Instruction::SUB(ArithmeticTarget::H), 1)),
-      0xA5 => Some((Instruction::SUB(ArithmeticTarget::L), 1)),
-      0xA6 => Some((Instruction::SUB(ArithmeticTarget::HL), 2)),
-      0xA7 => Some((Instruction::SUB(ArithmeticTarget::A), 1)),
-      0xA8 => Some((Instruction::XOR(ArithmeticTarget::B), 1)),
-      0xA9 => Some((Instruction::XOR(ArithmeticTarget::C), 1)),
-      0xAA => Some((Instruction::XOR(ArithmeticTarget::D), 1)),
-      0xAB => Some((Instruction::XOR(ArithmeticTarget::E), 1)),
-      0xAC => Some((Instruction::XOR(ArithmeticTarget::H), 1)),
-      0xAD => Some((Instruction::XOR(ArithmeticTarget::L), 1)),
-      0xAE => Some((Instruction::XOR(ArithmeticTarget::HL), 2)),
-      0xAF => Some((Instruction::XOR(ArithmeticTarget::A), 1)),
-
-      0xB0 => Some((Instruction::OR(ArithmeticTarget::B), 1)),
-      0xB1 => Some((Instruction::OR(ArithmeticTarget::C), 1)),
-      0xB2 => Some((Instruction::OR(ArithmeticTarget::D), 1)),
-      0xB3 => Some((Instruction::OR(ArithmeticTarget::E), 1)),
-      0xB4 => Some((Instruction::OR(ArithmeticTarget::H), 1)),
-      0xB5 => Some((Instruction::OR(ArithmeticTarget::L), 1)),
-      0xB6 => Some((Instruction::OR(ArithmeticTarget::HL), 2)),
-      0xB7 => Some((Instruction::OR(ArithmeticTarget::A), 1)),
-      0xB8 => Some((Instruction::CP(ArithmeticTarget::B), 1)),
-      0xB9 => Some((Instruction::CP(ArithmeticTarget::C), 1)),
-      0xBA => Some((Instruction::CP(ArithmeticTarget::D), 1)),
-      0xBB => Some((Instruction::CP(ArithmeticTarget::E), 1)),
-      0xBC => Some((Instruction::CP(ArithmeticTarget::H), 1)),
-      0xBD => Some((Instruction::CP(ArithmeticTarget::L), 1)),
-      0xBE => Some((Instruction::CP(ArithmeticTarget::HL), 2)),
-      0xBF => Some((Instruction::CP(ArithmeticTarget::A), 1)),
-
-      0xC6 => Some((Instruction::ADD(ArithmeticTarget::N8), 2)),
-      0xCE => Some((Instruction::ADC(ArithmeticTarget::N8), 2)),
-
-      0xD6 => Some((Instruction::SUB(ArithmeticTarget::N8), 2)),
-      0xDE => Some((Instruction::SBC(ArithmeticTarget::N8), 2)),
-
-      0xE0 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A8, LoadByteSource::A)), 2)),
-      0xE2 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::ADRC, LoadByteSource::A)), 2)),
-      0xE6 => Some((Instruction::AND(ArithmeticTarget::N8), 2)),      
-      0xE8 => Some((Instruction::ADDSP, 4)),
-      0xEA => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D16, LoadByteSource::A)), 4)),
-      0xEE => Some((Instruction::XOR(ArithmeticTarget::N8), 2)),
-
-      0xF0 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::A8)), 2)),
-      0xF2 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::ADRC)), 2)),
-      0xF6 => Some((Instruction::OR(ArithmeticTarget::N8), 2)),
-      0xF8 => Some((Instruction::LD(LoadType::Word(LoadWordTarget::HL, LoadWordSource::SP8)), 4)),
-      0xF9 => Some((Instruction::LD(LoadType::Word(LoadWordTarget::SP, LoadWordSource::HL)), 2)),
-      0xFA => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::D16)), 4)),
-      0xFE => Some((Instruction::CP(ArithmeticTarget::N8), 2)),
+      0x00 => Some((Instruction::NOP, 4)),
+      0x01 => Some((Instruction::LD(LoadType::Word(LoadWordTarget::BC, LoadWordSource::D16)), 12)),
+      0x02 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::BC, LoadByteSource::A)), 8)),
+      0x03 => Some((Instruction::INC16(WordTarget::BC), 8)),
+      0x04 => Some((Instruction::INC(IncDecTarget::B), 4)),
+      0x05 => Some((Instruction::DEC(IncDecTarget::B), 4)),
+      0x06 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::D8)), 8)),
+      0x07 => Some((Instruction::RLCA, 4)),
+      0x08 => Some((Instruction::LD(LoadType::Word(LoadWordTarget::A16, LoadWordSource::SP)), 20)),
+      0x09 => Some((Instruction::ADDHL(WordTarget::BC), 8)),
+      0x0A => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::BC)), 8)),
+      0x0B => Some((Instruction::DEC16(WordTarget::BC), 8)),
+      0x0C => Some((Instruction::INC(IncDecTarget::C), 4)),
+      0x0D => Some((Instruction::DEC(IncDecTarget::C), 4)),
+      0x0E => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::D8)), 8)),
+      0x0F => Some((Instruction::RRCA, 4)),
+
+      0x10 => Some((Instruction::STOP, 4)),
+      0x11 => Some((Instruction::LD(LoadType::Word(LoadWordTarget::DE, LoadWordSource::D16)), 12)),
+      0x12 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::DE, LoadByteSource::A)), 8)),
+      0x13 => Some((Instruction::INC16(WordTarget::DE), 8)),
+      0x14 => Some((Instruction::INC(IncDecTarget::D), 4)),
+      0x15 => Some((Instruction::DEC(IncDecTarget::D), 4)),
+      0x16 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::D8)), 8)),
+      0x17 => Some((Instruction::RLA, 4)),
+      0x18 => Some((Instruction::JR(JumpTest::Always), 12)),
+      0x19 => Some((Instruction::ADDHL(WordTarget::DE), 8)),
+      0x1A => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::DE)), 8)),
+      0x1B => Some((Instruction::DEC16(WordTarget::DE), 8)),
+      0x1C => Some((Instruction::INC(IncDecTarget::E), 4)),
+      0x1D => Some((Instruction::DEC(IncDecTarget::E), 4)),
+      0x1E => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::D8)), 8)),
+      0x1F => Some((Instruction::RRA, 4)),
+
+      0x20 => Some((Instruction::JR(JumpTest::NotZero), 8)),
+      0x21 => Some((Instruction::LD(LoadType::Word(LoadWordTarget::HL, LoadWordSource::D16)), 12)),
+      0x22 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HLI, LoadByteSource::A)), 8)),
+      0x23 => Some((Instruction::INC16(WordTarget::HL), 8)),
+      0x24 => Some((Instruction::INC(IncDecTarget::H), 4)),
+      0x25 => Some((Instruction::DEC(IncDecTarget::H), 4)),
+      0x26 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::D8)), 8)),
+      0x27 => Some((Instruction::DAA, 4)),
+      0x28 => Some((Instruction::JR(JumpTest::Zero), 8)),
+      0x29 => Some((Instruction::ADDHL(WordTarget::HL), 8)),
+      0x2A => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::HLI)), 8)),
+      0x2B => Some((Instruction::DEC16(WordTarget::HL), 8)),
+      0x2C => Some((Instruction::INC(IncDecTarget::L), 4)),
+      0x2D => Some((Instruction::DEC(IncDecTarget::L), 4)),
+      0x2E => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::D8)), 8)),
+      0x2F => Some((Instruction::CPL, 4)),
+
+      0x30 => Some((Instruction::JR(JumpTest::NotCarry), 8)),
+      0x31 => Some((Instruction::LD(LoadType::Word(LoadWordTarget::SP, LoadWordSource::D16)), 12)),
+      0x32 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HLD, LoadByteSource::A)), 8)),
+      0x33 => Some((Instruction::INC16(WordTarget::SP), 8)),
+      0x34 => Some((Instruction::INC(IncDecTarget::HL), 12)),
+      0x35 => Some((Instruction::DEC(IncDecTarget::HL), 12)),
+      0x36 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HL, LoadByteSource::D8)), 12)),
+      0x37 => Some((Instruction::SCF, 4)),
+      0x38 => Some((Instruction::JR(JumpTest::Carry), 8)),
+      0x39 => Some((Instruction::ADDHL(WordTarget::SP), 8)),
+      0x3A => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::HLD)), 8)),
+      0x3B => Some((Instruction::DEC16(WordTarget::SP), 8)),
+      0x3C => Some((Instruction::INC(IncDecTarget::A), 4)),
+      0x3D => Some((Instruction::DEC(IncDecTarget::A), 4)),
+      0x3E => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::D8)), 8)),
+      0x3F => Some((Instruction::CCF, 4)),
+
+      0x40 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::B)), 4)),
+      0x41 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::C)), 4)),
+      0x42 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::D)), 4)),
+      0x43 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::E)), 4)),
+      0x44 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::H)), 4)),
+      0x45 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::L)), 4)),
+      0x46 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::HL)), 8)),
+      0x47 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::B, LoadByteSource::A)), 4)),
+      0x48 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::B)), 4)),
+      0x49 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::C)), 4)),
+      0x4A => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::D)), 4)),
+      0x4B => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::E)), 4)),
+      0x4C => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::H)), 4)),
+      0x4D => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::L)), 4)),
+      0x4E => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::HL)), 8)),
+      0x4F => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::C, LoadByteSource::A)), 4)),
+
+      0x50 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::B)), 4)),
+      0x51 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::C)), 4)),
+      0x52 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::D)), 4)),
+      0x53 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::E)), 4)),
+      0x54 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::H)), 4)),
+      0x55 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::L)), 4)),
+      0x56 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::HL)), 8)),
+      0x57 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D, LoadByteSource::A)), 4)),
+      0x58 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::B)), 4)),
+      0x59 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::C)), 4)),
+      0x5A => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::D)), 4)),
+      0x5B => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::E)), 4)),
+      0x5C => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::H)), 4)),
+      0x5D => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::L)), 4)),
+      0x5E => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::HL)), 8)),
+      0x5F => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::E, LoadByteSource::A)), 4)),
+
+      0x60 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::B)), 4)),
+      0x61 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::C)), 4)),
+      0x62 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::D)), 4)),
+      0x63 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::E)), 4)),
+      0x64 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::H)), 4)),
+      0x65 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::L)), 4)),
+      0x66 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::HL)), 8)),
+      0x67 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::H, LoadByteSource::A)), 4)),
+      0x68 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::B)), 4)),
+      0x69 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::C)), 4)),
+      0x6A => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::D)), 4)),
+      0x6B => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::E)), 4)),
+      0x6C => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::H)), 4)),
+      0x6D => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::L)), 4)),
+      0x6E => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::HL)), 8)),
+      0x6F => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::L, LoadByteSource::A)), 4)),
+
+      0x70 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HL, LoadByteSource::B)), 8)),
+      0x71 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HL, LoadByteSource::C)), 8)),
+      0x72 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HL, LoadByteSource::D)), 8)),
+      0x73 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HL, LoadByteSource::E)), 8)),
+      0x74 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HL, LoadByteSource::H)), 8)),
+      0x75 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HL, LoadByteSource::L)), 8)),
+      0x76 => Some((Instruction::HALT, 4)),
+      0x77 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::HL, LoadByteSource::A)), 8)),
+      0x78 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::B)), 4)),
+      0x79 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::C)), 4)),
+      0x7A => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::D)), 4)),
+      0x7B => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::E)), 4)),
+      0x7C => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::H)), 4)),
+      0x7D => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::L)), 4)),
+      0x7E => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::HL)), 8)),
+      0x7F => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::A)), 4)),
+
+      0x80 => Some((Instruction::ADD(ArithmeticTarget::B), 4)),
+      0x81 => Some((Instruction::ADD(ArithmeticTarget::C), 4)),
+      0x82 => Some((Instruction::ADD(ArithmeticTarget::D), 4)),
+      0x83 => Some((Instruction::ADD(ArithmeticTarget::E), 4)),
+      0x84 => Some((Instruction::ADD(ArithmeticTarget::H), 4)),
+      0x85 => Some((Instruction::ADD(ArithmeticTarget::L), 4)),
+      0x86 => Some((Instruction::ADD(ArithmeticTarget::HL), 8)),
+      0x87 => Some((Instruction::ADD(ArithmeticTarget::A), 4)),
+      0x88 => Some((Instruction::ADC(ArithmeticTarget::B), 4)),
+      0x89 => Some((Instruction::ADC(ArithmeticTarget::C), 4)),
+      0x8A => Some((Instruction::ADC(ArithmeticTarget::D), 4)),
+      0x8B => Some((Instruction::ADC(ArithmeticTarget::E), 4)),
+      0x8C => Some((Instruction::ADC(ArithmeticTarget::H), 4)),
+      0x8D => Some((Instruction::ADC(ArithmeticTarget::L), 4)),
+      0x8E => Some((Instruction::ADC(ArithmeticTarget::HL), 8)),
+      0x8F => Some((Instruction::ADC(ArithmeticTarget::A), 4)),
+
+      0x90 => Some((Instruction::SUB(ArithmeticTarget::B), 4)),
+      0x91 => Some((Instruction::SUB(ArithmeticTarget::C), 4)),
+      0x92 => Some((Instruction::SUB(ArithmeticTarget::D), 4)),
+      0x93 => Some((Instruction::SUB(ArithmeticTarget::E), 4)),
+      0x94 => Some((Instruction::SUB(ArithmeticTarget::H), 4)),
+      0x95 => Some((Instruction::SUB(ArithmeticTarget::L), 4)),
+      0x96 => Some((Instruction::SUB(ArithmeticTarget::HL), 8)),
+      0x97 => Some((Instruction::SUB(ArithmeticTarget::A), 4)),
+      0x98 => Some((Instruction::SBC(ArithmeticTarget::B), 4)),
+      0x99 => Some((Instruction::SBC(ArithmeticTarget::C), 4)),
+      0x9A => Some((Instruction::SBC(ArithmeticTarget::D), 4)),
+      0x9B => Some((Instruction::SBC(ArithmeticTarget::E), 4)),
+      0x9C => Some((Instruction::SBC(ArithmeticTarget::H), 4)),
+      0x9D => Some((Instruction::SBC(ArithmeticTarget::L), 4)),
+      0x9E => Some((Instruction::SBC(ArithmeticTarget::HL), 8)),
+      0x9F => Some((Instruction::SBC(ArithmeticTarget::A), 4)),
+
+      0xA0 => Some((Instruction::AND(ArithmeticTarget::B), 4)),
+      0xA1 => Some((Instruction::AND(ArithmeticTarget::C), 4)),
+      0xA2 => Some((Instruction::AND(ArithmeticTarget::D), 4)),
+      0xA3 => Some((Instruction::AND(ArithmeticTarget::E), 4)),
+      0xA4 => Some((Instruction::AND(ArithmeticTarget::H), 4)),
+      0xA5 => Some((Instruction::AND(ArithmeticTarget::L), 4)),
+      0xA6 => Some((Instruction::AND(ArithmeticTarget::HL), 8)),
+      0xA7 => Some((Instruction::AND(ArithmeticTarget::A), 4)),
+      0xA8 => Some((Instruction::XOR(ArithmeticTarget::B), 4)),
+      0xA9 => Some((Instruction::XOR(ArithmeticTarget::C), 4)),
+      0xAA => Some((Instruction::XOR(ArithmeticTarget::D), 4)),
+      0xAB => Some((Instruction::XOR(ArithmeticTarget::E), 4)),
+      0xAC => Some((Instruction::XOR(ArithmeticTarget::H), 4)),
+      0xAD => Some((Instruction::XOR(ArithmeticTarget::L), 4)),
+      0xAE => Some((Instruction::XOR(ArithmeticTarget::HL), 8)),
+      0xAF => Some((Instruction::XOR(ArithmeticTarget::A), 4)),
+
+      0xB0 => Some((Instruction::OR(ArithmeticTarget::B), 4)),
+      0xB1 => Some((Instruction::OR(ArithmeticTarget::C), 4)),
+      0xB2 => Some((Instruction::OR(ArithmeticTarget::D), 4)),
+      0xB3 => Some((Instruction::OR(ArithmeticTarget::E), 4)),
+      0xB4 => Some((Instruction::OR(ArithmeticTarget::H), 4)),
+      0xB5 => Some((Instruction::OR(ArithmeticTarget::L), 4)),
+      0xB6 => Some((Instruction::OR(ArithmeticTarget::HL), 8)),
+      0xB7 => Some((Instruction::OR(ArithmeticTarget::A), 4)),
+      0xB8 => Some((Instruction::CP(ArithmeticTarget::B), 4)),
+      0xB9 => Some((Instruction::CP(ArithmeticTarget::C), 4)),
+      0xBA => Some((Instruction::CP(ArithmeticTarget::D), 4)),
+      0xBB => Some((Instruction::CP(ArithmeticTarget::E), 4)),
+      0xBC => Some((Instruction::CP(ArithmeticTarget::H), 4)),
+      0xBD => Some((Instruction::CP(ArithmeticTarget::L), 4)),
+      0xBE => Some((Instruction::CP(ArithmeticTarget::HL), 8)),
+      0xBF => Some((Instruction::CP(ArithmeticTarget::A), 4)),
+
+      0xC0 => Some((Instruction::RET(JumpTest::NotZero), 8)),
+      0xC1 => Some((Instruction::POP(StackTarget::BC), 12)),
+      0xC2 => Some((Instruction::JP(JumpTest::NotZero), 12)),
+      0xC3 => Some((Instruction::JP(JumpTest::Always), 16)),
+      0xC4 => Some((Instruction::CALL(JumpTest::NotZero), 12)),
+      0xC5 => Some((Instruction::PUSH(StackTarget::BC), 16)),
+      0xC6 => Some((Instruction::ADD(ArithmeticTarget::N8), 8)),
+      0xC7 => Some((Instruction::RST(0x00), 16)),
+      0xC8 => Some((Instruction::RET(JumpTest::Zero), 8)),
+      0xC9 => Some((Instruction::RET(JumpTest::Always), 16)),
+      0xCA => Some((Instruction::JP(JumpTest::Zero), 12)),
+      0xCC => Some((Instruction::CALL(JumpTest::Zero), 12)),
+      0xCD => Some((Instruction::CALL(JumpTest::Always), 24)),
+      0xCE => Some((Instruction::ADC(ArithmeticTarget::N8), 8)),
+      0xCF => Some((Instruction::RST(0x08), 16)),
+
+      0xD0 => Some((Instruction::RET(JumpTest::NotCarry), 8)),
+      0xD1 => Some((Instruction::POP(StackTarget::DE), 12)),
+      0xD2 => Some((Instruction::JP(JumpTest::NotCarry), 12)),
+      0xD4 => Some((Instruction::CALL(JumpTest::NotCarry), 12)),
+      0xD5 => Some((Instruction::PUSH(StackTarget::DE), 16)),
+      0xD6 => Some((Instruction::SUB(ArithmeticTarget::N8), 8)),
+      0xD7 => Some((Instruction::RST(0x10), 16)),
+      0xD8 => Some((Instruction::RET(JumpTest::Carry), 8)),
+      0xD9 => Some((Instruction::RETI, 16)),
+      0xDA => Some((Instruction::JP(JumpTest::Carry), 12)),
+      0xDC => Some((Instruction::CALL(JumpTest::Carry), 12)),
+      0xDE => Some((Instruction::SBC(ArithmeticTarget::N8), 8)),
+      0xDF => Some((Instruction::RST(0x18), 16)),
+
+      0xE0 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A8, LoadByteSource::A)), 12)),
+      0xE1 => Some((Instruction::POP(StackTarget::HL), 12)),
+      0xE2 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::ADRC, LoadByteSource::A)), 8)),
+      0xE5 => Some((Instruction::PUSH(StackTarget::HL), 16)),
+      0xE6 => Some((Instruction::AND(ArithmeticTarget::N8), 8)),
+      0xE7 => Some((Instruction::RST(0x20), 16)),
+      0xE8 => Some((Instruction::ADDSP, 16)),
+      0xE9 => Some((Instruction::JPHL, 4)),
+      0xEA => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::D16, LoadByteSource::A)), 16)),
+      0xEE => Some((Instruction::XOR(ArithmeticTarget::N8), 8)),
+      0xEF => Some((Instruction::RST(0x28), 16)),
+
+      0xF0 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::A8)), 12)),
+      0xF1 => Some((Instruction::POP(StackTarget::AF), 12)),
+      0xF2 => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::ADRC)), 8)),
+      0xF3 => Some((Instruction::DI, 4)),
+      0xF5 => Some((Instruction::PUSH(StackTarget::AF), 16)),
+      0xF6 => Some((Instruction::OR(ArithmeticTarget::N8), 8)),
+      0xF7 => Some((Instruction::RST(0x30), 16)),
+      0xF8 => Some((Instruction::LD(LoadType::Word(LoadWordTarget::HL, LoadWordSource::SP8)), 12)),
+      0xF9 => Some((Instruction::LD(LoadType::Word(LoadWordTarget::SP, LoadWordSource::HL)), 8)),
+      0xFA => Some((Instruction::LD(LoadType::Byte(LoadByteTarget::A, LoadByteSource::D16)), 16)),
+      0xFB => Some((Instruction::EI, 4)),
+      0xFE => Some((Instruction::CP(ArithmeticTarget::N8), 8)),
+      0xFF => Some((Instruction::RST(0x38), 16)),
 
       _ => None
     }
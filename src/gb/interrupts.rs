@@ -0,0 +1,131 @@
+pub const INTERRUPT_FLAG_ADDRESS: u16 = 0xFF0F;
+pub const INTERRUPT_ENABLE_ADDRESS: u16 = 0xFFFF;
+
+// Pushing pc and jumping to the vector takes 5 M-cycles
+pub const DISPATCH_CYCLES: u8 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interrupt {
+    VBlank,
+    LcdStat,
+    Timer,
+    Serial,
+    Joypad
+}
+
+impl Interrupt {
+    // In priority order, VBlank is serviced first
+    const ALL: [Interrupt; 5] = [
+        Interrupt::VBlank,
+        Interrupt::LcdStat,
+        Interrupt::Timer,
+        Interrupt::Serial,
+        Interrupt::Joypad
+    ];
+
+    pub fn bit(&self) -> u8 {
+        match self {
+            Interrupt::VBlank  => 0b0000_0001,
+            Interrupt::LcdStat => 0b0000_0010,
+            Interrupt::Timer   => 0b0000_0100,
+            Interrupt::Serial  => 0b0000_1000,
+            Interrupt::Joypad  => 0b0001_0000,
+        }
+    }
+
+    pub fn vector(&self) -> u16 {
+        match self {
+            Interrupt::VBlank  => 0x40,
+            Interrupt::LcdStat => 0x48,
+            Interrupt::Timer   => 0x50,
+            Interrupt::Serial  => 0x58,
+            Interrupt::Joypad  => 0x60,
+        }
+    }
+}
+
+/// The IE and IF registers
+#[derive(Debug, Default)]
+pub struct Interrupts {
+    pub enable: u8,
+    pub flag: u8,
+}
+
+impl Interrupts {
+    pub fn request(&mut self, interrupt: Interrupt) {
+        self.flag |= interrupt.bit();
+    }
+
+    pub fn acknowledge(&mut self, interrupt: Interrupt) {
+        self.flag &= !interrupt.bit();
+    }
+
+    /// Interrupts that are both requested and enabled
+    pub fn pending(&self) -> u8 {
+        self.enable & self.flag & 0b0001_1111
+    }
+
+    /// Highest priority pending interrupt, if any
+    pub fn next(&self) -> Option<Interrupt> {
+        let pending = self.pending();
+        Interrupt::ALL.into_iter().find(|interrupt| pending & interrupt.bit() != 0)
+    }
+
+    pub fn read_flag(&self) -> u8 {
+        // the top 3 bits are unused and always read back as 1
+        self.flag | 0b1110_0000
+    }
+
+    pub fn write_flag(&mut self, val: u8) {
+        self.flag = val & 0b0001_1111;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highest_priority_goes_first() {
+        let mut interrupts = Interrupts {enable: 0xFF, flag: 0};
+        interrupts.request(Interrupt::Joypad);
+        interrupts.request(Interrupt::Timer);
+        interrupts.request(Interrupt::LcdStat);
+
+        let mut order = Vec::new();
+        while let Some(interrupt) = interrupts.next() {
+            order.push(interrupt);
+            interrupts.acknowledge(interrupt);
+        }
+        assert_eq!(order, [Interrupt::LcdStat, Interrupt::Timer, Interrupt::Joypad]);
+    }
+
+    #[test]
+    fn only_enabled_requests_are_pending() {
+        let mut interrupts = Interrupts {enable: Interrupt::Serial.bit(), flag: 0};
+        interrupts.request(Interrupt::VBlank);
+        assert_eq!(interrupts.pending(), 0);
+        assert_eq!(interrupts.next(), None);
+
+        interrupts.request(Interrupt::Serial);
+        assert_eq!(interrupts.pending(), Interrupt::Serial.bit());
+        assert_eq!(interrupts.next(), Some(Interrupt::Serial));
+
+        // Unused IE bits don't make anything pending
+        interrupts.enable = 0xE0;
+        assert_eq!(interrupts.pending(), 0);
+    }
+
+    #[test]
+    fn unused_flag_bits_read_as_set() {
+        let mut interrupts = Interrupts::default();
+        assert_eq!(interrupts.read_flag(), 0xE0);
+
+        interrupts.write_flag(0xFF);
+        assert_eq!(interrupts.flag, 0x1F);
+        assert_eq!(interrupts.read_flag(), 0xFF);
+
+        interrupts.write_flag(Interrupt::Timer.bit());
+        assert_eq!(interrupts.read_flag(), 0xE4);
+    }
+}
@@ -0,0 +1,206 @@
+use super::interrupts::{Interrupt, Interrupts};
+
+pub const JOYP_ADDRESS: u16 = 0xFF00;
+
+// Written as 0 to put that group of buttons on the input lines, P14 and P15
+const SELECT_DIRECTIONS: u8 = 0b0001_0000;
+const SELECT_ACTIONS: u8 = 0b0010_0000;
+const SELECT_MASK: u8 = SELECT_DIRECTIONS | SELECT_ACTIONS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    Right,
+    Left,
+    Up,
+    Down,
+    A,
+    B,
+    Select,
+    Start
+}
+
+impl Button {
+    pub const ALL: [Button; 8] = [
+        Button::Right,
+        Button::Left,
+        Button::Up,
+        Button::Down,
+        Button::A,
+        Button::B,
+        Button::Select,
+        Button::Start
+    ];
+
+    /// Bit in a mask of held buttons, the directions are the low nibble and the
+    /// rest the high one, each in the order they come out of JOYP
+    pub fn bit(&self) -> u8 {
+        match self {
+            Button::Right  => 0b0000_0001,
+            Button::Left   => 0b0000_0010,
+            Button::Up     => 0b0000_0100,
+            Button::Down   => 0b0000_1000,
+            Button::A      => 0b0001_0000,
+            Button::B      => 0b0010_0000,
+            Button::Select => 0b0100_0000,
+            Button::Start  => 0b1000_0000,
+        }
+    }
+}
+
+/// The JOYP register. The buttons are a 2x4 matrix, the game picks a row with the
+/// select bits and reads the four input lines, which are pulled low while pressed.
+#[derive(Debug)]
+pub struct Joypad {
+    select: u8,
+    // Held buttons, as a mask of Button::bit
+    pressed: u8,
+}
+
+impl Joypad {
+    pub fn new() -> Self {
+        Joypad {
+            select: SELECT_MASK,
+            pressed: 0,
+        }
+    }
+
+    pub fn pressed(&self) -> u8 {
+        self.pressed
+    }
+
+    /// Replaces the held buttons with `pressed`, a mask of Button::bit
+    pub fn set_pressed(&mut self, pressed: u8, interrupts: &mut Interrupts) {
+        let lines = self.lines();
+        self.pressed = pressed;
+        self.check_interrupt(lines, interrupts);
+    }
+
+    pub fn press(&mut self, button: Button, interrupts: &mut Interrupts) {
+        self.set_pressed(self.pressed | button.bit(), interrupts);
+    }
+
+    pub fn release(&mut self, button: Button) {
+        // Lines only go high here, which never interrupts
+        self.pressed &= !button.bit();
+    }
+
+    pub fn read_register(&self) -> u8 {
+        self.select | self.lines()
+    }
+
+    pub fn write_register(&mut self, val: u8, interrupts: &mut Interrupts) {
+        // Selecting a row with a button already held pulls its line low too
+        let lines = self.lines();
+        self.select = val & SELECT_MASK;
+        self.check_interrupt(lines, interrupts);
+    }
+
+    // The low nibble of JOYP, a line reads 0 if a button on any selected row is down
+    fn lines(&self) -> u8 {
+        let mut held = 0;
+        if self.select & SELECT_DIRECTIONS == 0 {
+            held |= self.pressed & 0b0000_1111;
+        }
+        if self.select & SELECT_ACTIONS == 0 {
+            held |= self.pressed >> 4;
+        }
+        !held & 0b0000_1111
+    }
+
+    // Any line going from high to low requests the interrupt
+    fn check_interrupt(&self, before: u8, interrupts: &mut Interrupts) {
+        if before & !self.lines() != 0 {
+            interrupts.request(Interrupt::Joypad);
+        }
+    }
+}
+
+impl Default for Joypad {
+    fn default() -> Self {
+        Joypad::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gb::cartridge::{tests::build_rom, Cartridge};
+    use crate::gb::ram::MemoryBus;
+
+    fn interrupted(interrupts: &Interrupts) -> bool {
+        interrupts.flag & Interrupt::Joypad.bit() != 0
+    }
+
+    #[test]
+    fn select_bits_pick_the_row() {
+        let mut interrupts = Interrupts::default();
+        let mut joypad = Joypad::new();
+        joypad.set_pressed(Button::Right.bit() | Button::Start.bit(), &mut interrupts);
+
+        // Nothing selected, every line reads high
+        assert_eq!(joypad.read_register(), 0b0011_1111);
+
+        joypad.write_register(0b0010_0000, &mut interrupts);
+        assert_eq!(joypad.read_register(), 0b0010_1110);
+
+        joypad.write_register(0b0001_0000, &mut interrupts);
+        assert_eq!(joypad.read_register(), 0b0001_0111);
+
+        // Both rows at once share the lines
+        joypad.write_register(0b0000_0000, &mut interrupts);
+        assert_eq!(joypad.read_register(), 0b0000_0110);
+    }
+
+    #[test]
+    fn lines_are_active_low() {
+        let mut interrupts = Interrupts::default();
+        let mut joypad = Joypad::new();
+        joypad.write_register(0b0010_0000, &mut interrupts);
+
+        for button in &Button::ALL[..4] {
+            joypad.set_pressed(button.bit(), &mut interrupts);
+            assert_eq!(joypad.read_register() & 0b1111, !button.bit() & 0b1111, "{:?}", button);
+        }
+
+        joypad.release(Button::Down);
+        assert_eq!(joypad.read_register() & 0b1111, 0b1111);
+    }
+
+    #[test]
+    fn unused_bits_read_1_on_the_bus() {
+        let mut bus = MemoryBus::new(Cartridge::new(build_rom(0x00, 0x00, 0x00)).unwrap());
+        bus.write_byte(JOYP_ADDRESS, 0x00);
+        assert_eq!(bus.read_byte(JOYP_ADDRESS), 0b1100_1111);
+
+        // Only the select bits take a write
+        bus.write_byte(JOYP_ADDRESS, 0xFF);
+        assert_eq!(bus.read_byte(JOYP_ADDRESS), 0xFF);
+    }
+
+    #[test]
+    fn interrupt_on_a_line_going_low() {
+        let mut interrupts = Interrupts::default();
+        let mut joypad = Joypad::new();
+
+        // Not while its row isn't selected
+        joypad.press(Button::A, &mut interrupts);
+        assert!(!interrupted(&interrupts));
+
+        // Selecting the row with it held pulls the line low
+        joypad.write_register(0b0001_0000, &mut interrupts);
+        assert!(interrupted(&interrupts));
+
+        // Another button on a line that's already low doesn't, Right shares A's line
+        interrupts = Interrupts::default();
+        joypad.write_register(0b0000_0000, &mut interrupts);
+        joypad.press(Button::Right, &mut interrupts);
+        assert!(!interrupted(&interrupts));
+
+        // Nor does letting go
+        joypad.set_pressed(0, &mut interrupts);
+        assert!(!interrupted(&interrupts));
+
+        joypad.press(Button::Start, &mut interrupts);
+        assert!(interrupted(&interrupts));
+    }
+}
@@ -0,0 +1,100 @@
+pub mod rom_only;
+pub mod mbc1;
+pub mod mbc2;
+pub mod mbc3;
+pub mod mbc5;
+
+use std::fmt;
+
+use super::cartridge::{CartridgeHeader, MapperType};
+use super::clock::Clock;
+use rom_only::RomOnly;
+use mbc1::Mbc1;
+use mbc2::Mbc2;
+use mbc3::Mbc3;
+use mbc5::Mbc5;
+
+pub const ROM_BANK_SIZE: usize = 0x4000;
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+/// Memory bank controller sitting between the bus and the cartridge rom/ram.
+/// Addresses are passed through unchanged, 0x0000-0x7FFF for rom and 0xA000-0xBFFF for ram.
+pub trait Mapper: fmt::Debug {
+    fn read_rom(&self, address: u16) -> u8;
+
+    /// Writes to rom land in the banking registers
+    fn write_rom(&mut self, address: u16, val: u8);
+
+    fn read_ram(&self, address: u16) -> u8;
+
+    fn write_ram(&mut self, address: u16, val: u8);
+
+    /// Contents of the cartridge ram, followed by any extra state like the MBC3 RTC
+    fn save_data(&self) -> Vec<u8>;
+
+    /// Just the cartridge ram part of `save_data`, which only changes when the game writes to it
+    fn ram_data(&self) -> Vec<u8> {
+        self.save_data()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]);
+
+    /// Whether the rumble motor is currently switched on
+    fn rumble(&self) -> bool {
+        false
+    }
+}
+
+pub fn new_mapper(header: &CartridgeHeader, rom: Vec<u8>, clock: Box<dyn Clock>) -> Box<dyn Mapper> {
+    let cartridge_type = &header.cartridge_type;
+    match cartridge_type.mapper {
+        MapperType::Mbc1 => Box::new(Mbc1::new(rom, header.ram_size)),
+        MapperType::Mbc2 => Box::new(Mbc2::new(rom)),
+        MapperType::Mbc3 => {
+            let clock = if cartridge_type.timer {Some(clock)} else {None};
+            Box::new(Mbc3::new(rom, header.ram_size, clock))
+        },
+        MapperType::Mbc5 => Box::new(Mbc5::new(rom, header.ram_size, cartridge_type.rumble)),
+        // Anything else at least gets its first 32KiB mapped
+        _ => Box::new(RomOnly::new(rom, header.ram_size))
+    }
+}
+
+pub(crate) fn read_rom_bank(rom: &[u8], bank: usize, address: u16) -> u8 {
+    let offset = (address as usize) & (ROM_BANK_SIZE - 1);
+    let banks = (rom.len() / ROM_BANK_SIZE).max(1);
+    *rom.get((bank % banks) * ROM_BANK_SIZE + offset).unwrap_or(&0xFF)
+}
+
+pub(crate) fn ram_bank_address(ram: &[u8], bank: usize, address: u16) -> Option<usize> {
+    if ram.is_empty() {
+        return None;
+    }
+
+    let offset = (address as usize) & (RAM_BANK_SIZE - 1);
+    Some((bank * RAM_BANK_SIZE + offset) % ram.len())
+}
+
+pub(crate) fn load_ram(ram: &mut [u8], data: &[u8]) {
+    let len = ram.len().min(data.len());
+    ram[..len].copy_from_slice(&data[..len]);
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A rom of `banks` banks, each starting with its own number
+    pub(crate) fn numbered_rom(banks: usize) -> Vec<u8> {
+        let mut rom = vec![0; banks * ROM_BANK_SIZE];
+        for (bank, data) in rom.chunks_mut(ROM_BANK_SIZE).enumerate() {
+            data[..2].copy_from_slice(&(bank as u16).to_be_bytes());
+        }
+        rom
+    }
+
+    /// The bank a numbered rom has mapped at `address`, 0x0000 or 0x4000
+    pub(crate) fn bank_at(mapper: &dyn Mapper, address: u16) -> u16 {
+        u16::from_be_bytes([mapper.read_rom(address), mapper.read_rom(address + 1)])
+    }
+}
@@ -0,0 +1,212 @@
+use super::{Mapper, ROM_BANK_SIZE, load_ram, ram_bank_address, read_rom_bank};
+
+const LOGO: std::ops::Range<usize> = 0x0104..0x0134;
+const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+const MULTICART_ROM_SIZE: usize = 0x10_0000;
+
+#[derive(Debug)]
+pub struct Mbc1 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    // 5 bit register at 0x2000-0x3FFF
+    rom_bank: u8,
+    // 2 bit register at 0x4000-0x5FFF, either the ram bank or the upper rom bank bits
+    bank_2: u8,
+    advanced_banking: bool,
+    // MBC1M carts only wire up 4 bits of the rom bank register
+    multicart: bool,
+}
+
+impl Mbc1 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        let multicart = is_multicart(&rom);
+        Mbc1 {
+            rom,
+            ram: vec![0; ram_size],
+            ram_enabled: false,
+            rom_bank: 1,
+            bank_2: 0,
+            advanced_banking: false,
+            multicart,
+        }
+    }
+
+    fn ram_address(&self, address: u16) -> Option<usize> {
+        if !self.ram_enabled {
+            return None;
+        }
+
+        let bank = if self.advanced_banking {self.bank_2 as usize} else {0};
+        ram_bank_address(&self.ram, bank, address)
+    }
+
+    fn lower_bank_bits(&self) -> usize {
+        if self.multicart {
+            (self.rom_bank & 0b0000_1111) as usize
+        } else {
+            self.rom_bank as usize
+        }
+    }
+
+    fn upper_bank_bits(&self) -> usize {
+        (self.bank_2 as usize) << (if self.multicart {4} else {5})
+    }
+}
+
+impl Mapper for Mbc1 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = if address < 0x4000 {
+            // Mode 1 lets the upper bits reach bank 0x20/0x40/0x60 in the fixed area
+            if self.advanced_banking {self.upper_bank_bits()} else {0}
+        } else {
+            self.upper_bank_bits() | self.lower_bank_bits()
+        };
+
+        read_rom_bank(&self.rom, bank, address)
+    }
+
+    fn write_rom(&mut self, address: u16, val: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = val & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                // Writing 0 selects bank 1, which is why 0x20/0x40/0x60 can't be mapped here
+                let bank = val & 0b0001_1111;
+                self.rom_bank = if bank == 0 {1} else {bank};
+            },
+            0x4000..=0x5FFF => self.bank_2 = val & 0b0000_0011,
+            _ => self.advanced_banking = val & 0b0000_0001 != 0,
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        match self.ram_address(address) {
+            Some(index) => self.ram[index],
+            None => 0xFF
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, val: u8) {
+        if let Some(index) = self.ram_address(address) {
+            self.ram[index] = val;
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.clone()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
+
+// MBC1M carts are 1MiB with a second game header, logo included, at the start of bank 0x10.
+// Both have to be the real logo, two blank ones matching each other say nothing
+fn is_multicart(rom: &[u8]) -> bool {
+    if rom.len() != MULTICART_ROM_SIZE {
+        return false;
+    }
+
+    let game_start = 0x10 * ROM_BANK_SIZE;
+    rom[LOGO] == NINTENDO_LOGO && rom[game_start + LOGO.start..game_start + LOGO.end] == NINTENDO_LOGO
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gb::mapper::tests::{bank_at, numbered_rom};
+
+    // A numbered rom with a logo in the header, and in bank 0x10's too if it is a multicart
+    fn rom(banks: usize, multicart: bool) -> Vec<u8> {
+        let mut rom = numbered_rom(banks);
+        rom[LOGO].copy_from_slice(&NINTENDO_LOGO);
+        if multicart {
+            let game_start = 0x10 * ROM_BANK_SIZE;
+            rom[game_start + LOGO.start..game_start + LOGO.end].copy_from_slice(&NINTENDO_LOGO);
+        }
+        rom
+    }
+
+    #[test]
+    fn bank_0_maps_to_bank_1() {
+        let mut mbc = Mbc1::new(rom(128, false), 0);
+        assert_eq!(bank_at(&mbc, 0x4000), 1);
+
+        mbc.write_rom(0x2000, 0x05);
+        assert_eq!(bank_at(&mbc, 0x4000), 5);
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(bank_at(&mbc, 0x4000), 1);
+
+        // Only the low 5 bits are looked at for the remapping, so 0x20 goes to 0x21 as well
+        mbc.write_rom(0x4000, 0x01);
+        mbc.write_rom(0x2000, 0x20);
+        assert_eq!(bank_at(&mbc, 0x4000), 0x21);
+    }
+
+    #[test]
+    fn advanced_banking_moves_the_fixed_area() {
+        let mut mbc = Mbc1::new(rom(128, false), 0);
+        mbc.write_rom(0x4000, 0x02);
+        assert_eq!(bank_at(&mbc, 0x0000), 0);
+
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(bank_at(&mbc, 0x0000), 0x40);
+        assert_eq!(bank_at(&mbc, 0x4000), 0x41);
+    }
+
+    #[test]
+    fn ram_banks_need_advanced_banking() {
+        let mut mbc = Mbc1::new(rom(4, false), 0x8000);
+        assert_eq!(mbc.read_ram(0xA000), 0xFF);
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_ram(0xA000, 0x11);
+        mbc.write_rom(0x4000, 0x03);
+        assert_eq!(mbc.read_ram(0xA000), 0x11);
+
+        mbc.write_rom(0x6000, 0x01);
+        mbc.write_ram(0xA000, 0x33);
+        assert_eq!(mbc.save_data()[3 * 0x2000], 0x33);
+        assert_eq!(mbc.save_data()[0], 0x11);
+    }
+
+    #[test]
+    fn multicart_is_spotted_by_its_second_logo() {
+        assert!(is_multicart(&rom(64, true)));
+        assert!(!is_multicart(&rom(64, false)));
+        // Only 1MiB carts were ever made as MBC1M
+        assert!(!is_multicart(&rom(128, true)));
+
+        // Two matching blank logos aren't enough
+        let mut blank = rom(64, true);
+        blank[LOGO].fill(0);
+        blank[0x10 * ROM_BANK_SIZE + LOGO.start..0x10 * ROM_BANK_SIZE + LOGO.end].fill(0);
+        assert!(!is_multicart(&blank));
+        assert!(!is_multicart(&vec![0; MULTICART_ROM_SIZE]));
+    }
+
+    #[test]
+    fn multicart_bank_bits() {
+        let mut mbc = Mbc1::new(rom(64, true), 0);
+        mbc.write_rom(0x4000, 0x01);
+        mbc.write_rom(0x2000, 0x12);
+        // Bit 4 of the rom bank isn't wired, the upper bits start there instead
+        assert_eq!(bank_at(&mbc, 0x4000), 0x12);
+        mbc.write_rom(0x2000, 0x02);
+        assert_eq!(bank_at(&mbc, 0x4000), 0x12);
+
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(bank_at(&mbc, 0x0000), 0x10);
+
+        // The same writes on a plain 1MiB cart
+        let mut plain = Mbc1::new(rom(64, false), 0);
+        plain.write_rom(0x4000, 0x01);
+        plain.write_rom(0x2000, 0x02);
+        assert_eq!(bank_at(&plain, 0x4000), 0x22);
+    }
+}
@@ -0,0 +1,116 @@
+use super::{Mapper, load_ram, read_rom_bank};
+
+// 512 half-bytes of ram built into the MBC2 chip itself
+const RAM_SIZE: usize = 0x200;
+
+#[derive(Debug)]
+pub struct Mbc2 {
+    rom: Vec<u8>,
+    ram: [u8; RAM_SIZE],
+    ram_enabled: bool,
+    rom_bank: u8,
+}
+
+impl Mbc2 {
+    pub fn new(rom: Vec<u8>) -> Self {
+        Mbc2 {
+            rom,
+            ram: [0; RAM_SIZE],
+            ram_enabled: false,
+            rom_bank: 1,
+        }
+    }
+}
+
+impl Mapper for Mbc2 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = if address < 0x4000 {0} else {self.rom_bank as usize};
+        read_rom_bank(&self.rom, bank, address)
+    }
+
+    fn write_rom(&mut self, address: u16, val: u8) {
+        if address >= 0x4000 {
+            return;
+        }
+
+        // Address bit 8 picks between the ram enable and rom bank registers
+        if address & 0x0100 == 0 {
+            self.ram_enabled = val & 0x0F == 0x0A;
+        } else {
+            let bank = val & 0b0000_1111;
+            self.rom_bank = if bank == 0 {1} else {bank};
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+
+        // Only the low nibble exists, and the 512 bytes echo through the whole 0xA000-0xBFFF range
+        self.ram[(address as usize) & (RAM_SIZE - 1)] | 0xF0
+    }
+
+    fn write_ram(&mut self, address: u16, val: u8) {
+        if self.ram_enabled {
+            self.ram[(address as usize) & (RAM_SIZE - 1)] = val & 0x0F;
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.to_vec()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gb::mapper::tests::{bank_at, numbered_rom};
+
+    #[test]
+    fn address_bit_8_picks_the_register() {
+        let mut mbc = Mbc2::new(numbered_rom(16));
+
+        mbc.write_rom(0x2100, 0x03);
+        assert_eq!(bank_at(&mbc, 0x4000), 3);
+        // Anywhere below 0x4000 with bit 8 set
+        mbc.write_rom(0x0100, 0x05);
+        assert_eq!(bank_at(&mbc, 0x4000), 5);
+
+        // With it clear it's the ram enable, even above 0x2000
+        mbc.write_rom(0x2000, 0x0A);
+        assert_eq!(bank_at(&mbc, 0x4000), 5);
+        assert_eq!(mbc.read_ram(0xA000), 0xF0);
+
+        // 4 bits of bank, 0 still meaning 1
+        mbc.write_rom(0x0100, 0x13);
+        assert_eq!(bank_at(&mbc, 0x4000), 3);
+        mbc.write_rom(0x0100, 0x00);
+        assert_eq!(bank_at(&mbc, 0x4000), 1);
+    }
+
+    #[test]
+    fn ram_is_half_bytes() {
+        let mut mbc = Mbc2::new(numbered_rom(2));
+        mbc.write_ram(0xA000, 0x0C);
+        assert_eq!(mbc.read_ram(0xA000), 0xFF);
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_ram(0xA000, 0xAB);
+        assert_eq!(mbc.read_ram(0xA000), 0xFB);
+
+        // The 512 of them repeat across the whole area
+        assert_eq!(mbc.read_ram(0xA200), 0xFB);
+        assert_eq!(mbc.read_ram(0xBE00), 0xFB);
+        mbc.write_ram(0xBFFF, 0x07);
+        assert_eq!(mbc.read_ram(0xA1FF), 0xF7);
+
+        let saved = mbc.save_data();
+        assert_eq!(saved.len(), RAM_SIZE);
+        assert_eq!((saved[0], saved[0x1FF]), (0x0B, 0x07));
+    }
+}
@@ -0,0 +1,390 @@
+use super::{Mapper, load_ram, ram_bank_address, read_rom_bank};
+use crate::gb::clock::Clock;
+
+const RTC_SECONDS: u8 = 0x08;
+const RTC_MINUTES: u8 = 0x09;
+const RTC_HOURS: u8 = 0x0A;
+const RTC_DAY_LOW: u8 = 0x0B;
+const RTC_DAY_HIGH: u8 = 0x0C;
+
+// Live and latched registers as 5 little endian u32 each, then a u64 timestamp,
+// the same footer layout VBA and BGB append to their .sav files
+const RTC_SAVE_SIZE: usize = 48;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct RtcRegisters {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    days: u16,
+    halt: bool,
+    day_carry: bool,
+}
+
+impl RtcRegisters {
+    fn read(&self, register: u8) -> u8 {
+        match register {
+            RTC_SECONDS => self.seconds,
+            RTC_MINUTES => self.minutes,
+            RTC_HOURS => self.hours,
+            RTC_DAY_LOW => (self.days & 0xFF) as u8,
+            _ => {
+                ((self.days >> 8) & 0b1) as u8 |
+                (if self.halt {1} else {0}) << 6 |
+                (if self.day_carry {1} else {0}) << 7
+            }
+        }
+    }
+
+    fn write(&mut self, register: u8, val: u8) {
+        match register {
+            RTC_SECONDS => self.seconds = val & 0b0011_1111,
+            RTC_MINUTES => self.minutes = val & 0b0011_1111,
+            RTC_HOURS => self.hours = val & 0b0001_1111,
+            RTC_DAY_LOW => self.days = (self.days & 0x100) | val as u16,
+            _ => {
+                self.days = (self.days & 0xFF) | ((val as u16 & 0b1) << 8);
+                self.halt = val & 0b0100_0000 != 0;
+                self.day_carry = val & 0b1000_0000 != 0;
+            }
+        }
+    }
+
+    fn advance(&mut self, elapsed: u64) {
+        let total = elapsed
+            + self.seconds as u64
+            + self.minutes as u64 * 60
+            + self.hours as u64 * 60 * 60
+            + self.days as u64 * 60 * 60 * 24;
+
+        self.seconds = (total % 60) as u8;
+        self.minutes = (total / 60 % 60) as u8;
+        self.hours = (total / (60 * 60) % 24) as u8;
+
+        // The 9 bit day counter wraps and sets the carry, which stays set until the game clears it
+        let days = total / (60 * 60 * 24);
+        if days > 0x1FF {
+            self.day_carry = true;
+        }
+        self.days = (days & 0x1FF) as u16;
+    }
+}
+
+#[derive(Debug)]
+struct Rtc {
+    clock: Box<dyn Clock>,
+    live: RtcRegisters,
+    latched: RtcRegisters,
+    last_update: u64,
+}
+
+impl Rtc {
+    fn new(clock: Box<dyn Clock>) -> Self {
+        let last_update = clock.now();
+        Rtc {
+            clock,
+            live: RtcRegisters::default(),
+            latched: RtcRegisters::default(),
+            last_update,
+        }
+    }
+
+    // Catch the live registers up with the wall clock
+    fn update(&mut self) {
+        let now = self.clock.now();
+        if !self.live.halt {
+            self.live.advance(now.saturating_sub(self.last_update));
+        }
+        self.last_update = now;
+    }
+
+    fn latch(&mut self) {
+        self.update();
+        self.latched = self.live;
+    }
+
+    fn write(&mut self, register: u8, val: u8) {
+        self.update();
+        self.live.write(register, val);
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(RTC_SAVE_SIZE);
+        for registers in [&self.live, &self.latched] {
+            for register in RTC_SECONDS..=RTC_DAY_HIGH {
+                data.extend_from_slice(&(registers.read(register) as u32).to_le_bytes());
+            }
+        }
+        data.extend_from_slice(&self.last_update.to_le_bytes());
+        data
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        if data.len() < RTC_SAVE_SIZE {
+            return;
+        }
+
+        let word = |i: usize| data[i * 4];
+        for (i, register) in (RTC_SECONDS..=RTC_DAY_HIGH).enumerate() {
+            self.live.write(register, word(i));
+            self.latched.write(register, word(i + 5));
+        }
+
+        let mut timestamp = [0; 8];
+        timestamp.copy_from_slice(&data[40..48]);
+        self.last_update = u64::from_le_bytes(timestamp);
+
+        // Account for the time spent switched off
+        self.update();
+    }
+}
+
+#[derive(Debug)]
+pub struct Mbc3 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    // Gates both the ram and the rtc registers
+    ram_enabled: bool,
+    rom_bank: u8,
+    // 0x00-0x03 selects a ram bank, 0x08-0x0C an rtc register
+    ram_bank: u8,
+    latch_armed: bool,
+    rtc: Option<Rtc>,
+}
+
+impl Mbc3 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, clock: Option<Box<dyn Clock>>) -> Self {
+        Mbc3 {
+            rom,
+            ram: vec![0; ram_size],
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            latch_armed: false,
+            rtc: clock.map(Rtc::new),
+        }
+    }
+
+    fn ram_address(&self, address: u16) -> Option<usize> {
+        if self.ram_bank > 0x03 {
+            return None;
+        }
+
+        ram_bank_address(&self.ram, self.ram_bank as usize, address)
+    }
+}
+
+impl Mapper for Mbc3 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = if address < 0x4000 {0} else {self.rom_bank as usize};
+        read_rom_bank(&self.rom, bank, address)
+    }
+
+    fn write_rom(&mut self, address: u16, val: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = val & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                let bank = val & 0b0111_1111;
+                self.rom_bank = if bank == 0 {1} else {bank};
+            },
+            0x4000..=0x5FFF => self.ram_bank = val,
+            _ => {
+                // Writing 0 then 1 copies the live clock into the readable registers
+                if self.latch_armed && val == 0x01 {
+                    if let Some(rtc) = self.rtc.as_mut() {
+                        rtc.latch();
+                    }
+                }
+                self.latch_armed = val == 0x00;
+            }
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+
+        match (self.ram_bank, &self.rtc) {
+            (RTC_SECONDS..=RTC_DAY_HIGH, Some(rtc)) => rtc.latched.read(self.ram_bank),
+            _ => match self.ram_address(address) {
+                Some(index) => self.ram[index],
+                None => 0xFF
+            }
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, val: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+
+        match (self.ram_bank, self.rtc.as_mut()) {
+            (RTC_SECONDS..=RTC_DAY_HIGH, Some(rtc)) => rtc.write(self.ram_bank, val),
+            _ => {
+                if let Some(index) = self.ram_address(address) {
+                    self.ram[index] = val;
+                }
+            }
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.clone();
+        if let Some(rtc) = &self.rtc {
+            data.extend(rtc.save_data());
+        }
+        data
+    }
+
+    fn ram_data(&self) -> Vec<u8> {
+        self.ram.clone()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+
+        if let Some(rtc) = self.rtc.as_mut() {
+            let ram_len = self.ram.len().min(data.len());
+            rtc.load_save_data(&data[ram_len..]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gb::clock::tests::FakeClock;
+
+    const DAY: u64 = 60 * 60 * 24;
+
+    fn with_rtc(clock: &FakeClock) -> Mbc3 {
+        let mut mbc = Mbc3::new(vec![0; 0x8000], 0x2000, Some(Box::new(clock.clone())));
+        mbc.write_rom(0x0000, 0x0A);
+        mbc
+    }
+
+    fn latch(mbc: &mut Mbc3) {
+        mbc.write_rom(0x6000, 0x00);
+        mbc.write_rom(0x6000, 0x01);
+    }
+
+    fn read_rtc(mbc: &mut Mbc3, register: u8) -> u8 {
+        mbc.write_rom(0x4000, register);
+        mbc.read_ram(0xA000)
+    }
+
+    fn write_rtc(mbc: &mut Mbc3, register: u8, val: u8) {
+        mbc.write_rom(0x4000, register);
+        mbc.write_ram(0xA000, val);
+    }
+
+    #[test]
+    fn registers_only_change_when_latched() {
+        let clock = FakeClock::default();
+        let mut mbc = with_rtc(&clock);
+
+        clock.advance(5);
+        assert_eq!(read_rtc(&mut mbc, RTC_SECONDS), 0);
+        latch(&mut mbc);
+        assert_eq!(read_rtc(&mut mbc, RTC_SECONDS), 5);
+
+        clock.advance(60 + 60 * 60 + 2 * DAY);
+        assert_eq!(read_rtc(&mut mbc, RTC_SECONDS), 5);
+
+        // A 1 without the 0 first isn't a latch
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(read_rtc(&mut mbc, RTC_MINUTES), 0);
+
+        latch(&mut mbc);
+        assert_eq!(read_rtc(&mut mbc, RTC_SECONDS), 5);
+        assert_eq!(read_rtc(&mut mbc, RTC_MINUTES), 1);
+        assert_eq!(read_rtc(&mut mbc, RTC_HOURS), 1);
+        assert_eq!(read_rtc(&mut mbc, RTC_DAY_LOW), 2);
+    }
+
+    #[test]
+    fn halt_stops_the_clock() {
+        let clock = FakeClock::default();
+        let mut mbc = with_rtc(&clock);
+
+        write_rtc(&mut mbc, RTC_DAY_HIGH, 0b0100_0000);
+        clock.advance(100);
+        latch(&mut mbc);
+        assert_eq!(read_rtc(&mut mbc, RTC_SECONDS), 0);
+        assert_eq!(read_rtc(&mut mbc, RTC_DAY_HIGH), 0b0100_0000);
+
+        // Time spent halted is never made up
+        write_rtc(&mut mbc, RTC_DAY_HIGH, 0x00);
+        clock.advance(10);
+        latch(&mut mbc);
+        assert_eq!(read_rtc(&mut mbc, RTC_SECONDS), 10);
+    }
+
+    #[test]
+    fn day_counter_overflow_sets_the_carry() {
+        let clock = FakeClock::default();
+        let mut mbc = with_rtc(&clock);
+
+        write_rtc(&mut mbc, RTC_DAY_LOW, 0xFF);
+        write_rtc(&mut mbc, RTC_DAY_HIGH, 0x01);
+        write_rtc(&mut mbc, RTC_HOURS, 23);
+        write_rtc(&mut mbc, RTC_MINUTES, 59);
+        write_rtc(&mut mbc, RTC_SECONDS, 59);
+
+        clock.advance(1);
+        latch(&mut mbc);
+        assert_eq!(read_rtc(&mut mbc, RTC_DAY_LOW), 0);
+        assert_eq!(read_rtc(&mut mbc, RTC_DAY_HIGH), 0b1000_0000);
+
+        // It stays set until the game clears it
+        clock.advance(DAY);
+        latch(&mut mbc);
+        assert_eq!(read_rtc(&mut mbc, RTC_DAY_LOW), 1);
+        assert_eq!(read_rtc(&mut mbc, RTC_DAY_HIGH), 0b1000_0000);
+
+        write_rtc(&mut mbc, RTC_DAY_HIGH, 0x00);
+        latch(&mut mbc);
+        assert_eq!(read_rtc(&mut mbc, RTC_DAY_HIGH), 0);
+    }
+
+    #[test]
+    fn rtc_is_saved_after_the_ram() {
+        let clock = FakeClock::default();
+        clock.advance(1_000_000);
+        let mut mbc = with_rtc(&clock);
+
+        mbc.write_rom(0x4000, 0x00);
+        mbc.write_ram(0xA000, 0x42);
+        write_rtc(&mut mbc, RTC_MINUTES, 30);
+        latch(&mut mbc);
+        write_rtc(&mut mbc, RTC_SECONDS, 7);
+
+        let data = mbc.save_data();
+        assert_eq!(data.len(), 0x2000 + RTC_SAVE_SIZE);
+        assert_eq!(data[0], 0x42);
+        let footer = &data[0x2000..];
+        // Live seconds and minutes, then the latched ones
+        assert_eq!(footer[0..8], [7, 0, 0, 0, 30, 0, 0, 0]);
+        assert_eq!(footer[20..28], [0, 0, 0, 0, 30, 0, 0, 0]);
+        assert_eq!(footer[40..48], 1_000_000u64.to_le_bytes());
+
+        // Loaded an hour later it has kept time
+        clock.advance(60 * 60);
+        let mut loaded = with_rtc(&clock);
+        loaded.load_save_data(&data);
+        assert_eq!(read_rtc(&mut loaded, RTC_MINUTES), 30);
+        latch(&mut loaded);
+        assert_eq!(read_rtc(&mut loaded, RTC_SECONDS), 7);
+        assert_eq!(read_rtc(&mut loaded, RTC_MINUTES), 30);
+        assert_eq!(read_rtc(&mut loaded, RTC_HOURS), 1);
+        loaded.write_rom(0x4000, 0x00);
+        assert_eq!(loaded.read_ram(0xA000), 0x42);
+    }
+
+    #[test]
+    fn without_a_timer_only_the_ram_is_saved() {
+        let mbc = Mbc3::new(vec![0; 0x8000], 0x2000, None);
+        assert_eq!(mbc.save_data().len(), 0x2000);
+    }
+}
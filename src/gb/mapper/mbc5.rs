@@ -0,0 +1,145 @@
+use super::{Mapper, load_ram, ram_bank_address, read_rom_bank};
+
+#[derive(Debug)]
+pub struct Mbc5 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    // 9 bits, split over 0x2000-0x2FFF and 0x3000-0x3FFF. Unlike MBC1, bank 0 can be mapped here
+    rom_bank: u16,
+    ram_bank: u8,
+    // Rumble carts steal bit 3 of the ram bank register for the motor
+    has_rumble: bool,
+    rumble: bool,
+}
+
+impl Mbc5 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, has_rumble: bool) -> Self {
+        Mbc5 {
+            rom,
+            ram: vec![0; ram_size],
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            has_rumble,
+            rumble: false,
+        }
+    }
+}
+
+impl Mapper for Mbc5 {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = if address < 0x4000 {0} else {self.rom_bank as usize};
+        read_rom_bank(&self.rom, bank, address)
+    }
+
+    fn write_rom(&mut self, address: u16, val: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = val == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | val as u16,
+            0x3000..=0x3FFF => self.rom_bank = (self.rom_bank & 0xFF) | ((val as u16 & 0b1) << 8),
+            0x4000..=0x5FFF => {
+                if self.has_rumble {
+                    self.rumble = val & 0b0000_1000 != 0;
+                    self.ram_bank = val & 0b0000_0111;
+                } else {
+                    self.ram_bank = val & 0b0000_1111;
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+
+        match ram_bank_address(&self.ram, self.ram_bank as usize, address) {
+            Some(index) => self.ram[index],
+            None => 0xFF
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, val: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+
+        if let Some(index) = ram_bank_address(&self.ram, self.ram_bank as usize, address) {
+            self.ram[index] = val;
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.clone()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+
+    fn rumble(&self) -> bool {
+        self.rumble
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gb::mapper::tests::{bank_at, numbered_rom};
+
+    #[test]
+    fn rom_bank_is_9_bits() {
+        let mut mbc = Mbc5::new(numbered_rom(512), 0, false);
+        mbc.write_rom(0x2000, 0x05);
+        mbc.write_rom(0x3000, 0x01);
+        assert_eq!(bank_at(&mbc, 0x4000), 0x105);
+
+        // Each half can be written on its own
+        mbc.write_rom(0x2000, 0xFF);
+        assert_eq!(bank_at(&mbc, 0x4000), 0x1FF);
+        mbc.write_rom(0x3000, 0xFE);
+        assert_eq!(bank_at(&mbc, 0x4000), 0x0FF);
+
+        // Bank 0 can go in the switchable area
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(bank_at(&mbc, 0x4000), 0);
+    }
+
+    #[test]
+    fn rumble_takes_a_ram_bank_bit() {
+        let mut mbc = Mbc5::new(numbered_rom(2), 0x20000, true);
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0b0000_1011);
+        assert!(mbc.rumble());
+        mbc.write_ram(0xA000, 0x42);
+        assert_eq!(mbc.save_data()[3 * 0x2000], 0x42);
+
+        let mut plain = Mbc5::new(numbered_rom(2), 0x20000, false);
+        plain.write_rom(0x0000, 0x0A);
+        plain.write_rom(0x4000, 0b0000_1011);
+        assert!(!plain.rumble());
+        plain.write_ram(0xA000, 0x42);
+        assert_eq!(plain.save_data()[11 * 0x2000], 0x42);
+    }
+
+    #[test]
+    fn rumble_bit_does_not_select_a_bank() {
+        let mut mbc = Mbc5::new(numbered_rom(2), 0x20000, true);
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x02);
+        mbc.write_ram(0xA000, 0x42);
+
+        // Turning the motor on keeps bank 2 mapped
+        mbc.write_rom(0x4000, 0b0000_1010);
+        assert!(mbc.rumble());
+        assert_eq!(mbc.read_ram(0xA000), 0x42);
+
+        mbc.write_rom(0x4000, 0b0000_1000);
+        assert_eq!(mbc.read_ram(0xA000), 0x00);
+        mbc.write_rom(0x4000, 0b0000_0010);
+        assert!(!mbc.rumble());
+        assert_eq!(mbc.read_ram(0xA000), 0x42);
+    }
+}
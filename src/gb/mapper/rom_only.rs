@@ -0,0 +1,84 @@
+use super::{Mapper, load_ram, ram_bank_address, read_rom_bank};
+
+/// 32KiB carts with no banking, optionally with a single bank of ram
+#[derive(Debug)]
+pub struct RomOnly {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+}
+
+impl RomOnly {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        RomOnly {
+            rom,
+            ram: vec![0; ram_size],
+        }
+    }
+}
+
+impl Mapper for RomOnly {
+    fn read_rom(&self, address: u16) -> u8 {
+        let bank = if address < 0x4000 {0} else {1};
+        read_rom_bank(&self.rom, bank, address)
+    }
+
+    fn write_rom(&mut self, _address: u16, _val: u8) {}
+
+    fn read_ram(&self, address: u16) -> u8 {
+        match ram_bank_address(&self.ram, 0, address) {
+            Some(index) => self.ram[index],
+            None => 0xFF
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, val: u8) {
+        if let Some(index) = ram_bank_address(&self.ram, 0, address) {
+            self.ram[index] = val;
+        }
+    }
+
+    fn save_data(&self) -> Vec<u8> {
+        self.ram.clone()
+    }
+
+    fn load_save_data(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gb::mapper::tests::{bank_at, numbered_rom};
+
+    #[test]
+    fn rom_writes_do_not_switch_banks() {
+        let mut mbc = RomOnly::new(numbered_rom(2), 0);
+        assert_eq!(bank_at(&mbc, 0x0000), 0);
+        assert_eq!(bank_at(&mbc, 0x4000), 1);
+
+        for address in [0x0000, 0x2000, 0x4000, 0x6000] {
+            mbc.write_rom(address, 0x03);
+        }
+        assert_eq!(bank_at(&mbc, 0x0000), 0);
+        assert_eq!(bank_at(&mbc, 0x4000), 1);
+    }
+
+    #[test]
+    fn ram_needs_no_enable() {
+        let mut mbc = RomOnly::new(numbered_rom(2), 0x2000);
+        mbc.write_ram(0xA000, 0x12);
+        mbc.write_ram(0xBFFF, 0x34);
+        assert_eq!(mbc.read_ram(0xA000), 0x12);
+        assert_eq!(mbc.read_ram(0xBFFF), 0x34);
+        assert_eq!(mbc.save_data()[0x1FFF], 0x34);
+    }
+
+    #[test]
+    fn missing_ram_reads_open_bus() {
+        let mut mbc = RomOnly::new(numbered_rom(2), 0);
+        mbc.write_ram(0xA000, 0x12);
+        assert_eq!(mbc.read_ram(0xA000), 0xFF);
+        assert!(mbc.save_data().is_empty());
+    }
+}
@@ -0,0 +1,851 @@
+use super::interrupts::{Interrupt, Interrupts};
+use super::ram::{OAM_START, VRAM_START};
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+pub const LCDC_ADDRESS: u16 = 0xFF40;
+pub const STAT_ADDRESS: u16 = 0xFF41;
+pub const SCY_ADDRESS: u16 = 0xFF42;
+pub const SCX_ADDRESS: u16 = 0xFF43;
+pub const LY_ADDRESS: u16 = 0xFF44;
+pub const LYC_ADDRESS: u16 = 0xFF45;
+pub const BGP_ADDRESS: u16 = 0xFF47;
+pub const OBP0_ADDRESS: u16 = 0xFF48;
+pub const OBP1_ADDRESS: u16 = 0xFF49;
+pub const WY_ADDRESS: u16 = 0xFF4A;
+pub const WX_ADDRESS: u16 = 0xFF4B;
+
+// Frame buffer pixels carry the palette that shaded them above the 2-bit shade
+pub const SHADE_MASK: u8 = 0b0011;
+pub const LAYER_MASK: u8 = 0b1100;
+pub const BG_LAYER: u8 = 0b0000;
+pub const OBJ0_LAYER: u8 = 0b0100;
+pub const OBJ1_LAYER: u8 = 0b1000;
+
+const VRAM_SIZE: usize = 0x2000;
+const OAM_SIZE: usize = 0xA0;
+
+const DOTS_PER_LINE: u16 = 456;
+const OAM_SCAN_DOTS: u16 = 80;
+// Shortest possible pixel transfer, scrolling and sprites stretch it
+const DRAWING_DOTS: u16 = 172;
+const LINES_PER_FRAME: u8 = 154;
+// Roughly what each sprite on the line costs the pixel fetcher
+const SPRITE_DOTS: u16 = 6;
+
+const SPRITES_PER_LINE: usize = 10;
+const SPRITE_COUNT: usize = 40;
+
+const LCDC_ENABLE: u8 = 0b1000_0000;
+const LCDC_WINDOW_TILE_MAP: u8 = 0b0100_0000;
+const LCDC_WINDOW_ENABLE: u8 = 0b0010_0000;
+const LCDC_TILE_DATA: u8 = 0b0001_0000;
+const LCDC_BG_TILE_MAP: u8 = 0b0000_1000;
+const LCDC_OBJ_SIZE: u8 = 0b0000_0100;
+const LCDC_OBJ_ENABLE: u8 = 0b0000_0010;
+const LCDC_BG_WINDOW_ENABLE: u8 = 0b0000_0001;
+
+const OBJ_BG_PRIORITY: u8 = 0b1000_0000;
+const OBJ_Y_FLIP: u8 = 0b0100_0000;
+const OBJ_X_FLIP: u8 = 0b0010_0000;
+const OBJ_PALETTE: u8 = 0b0001_0000;
+
+// Offsets into vram
+const TILE_MAP_0: usize = 0x1800;
+const TILE_MAP_1: usize = 0x1C00;
+const TILE_DATA_UNSIGNED: usize = 0x0000;
+const TILE_DATA_SIGNED: usize = 0x1000;
+
+const STAT_LYC_INTERRUPT: u8 = 0b0100_0000;
+const STAT_OAM_INTERRUPT: u8 = 0b0010_0000;
+const STAT_VBLANK_INTERRUPT: u8 = 0b0001_0000;
+const STAT_HBLANK_INTERRUPT: u8 = 0b0000_1000;
+const STAT_LYC_EQUAL: u8 = 0b0000_0100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PpuMode {
+    HBlank,
+    VBlank,
+    OamScan,
+    Drawing
+}
+
+impl PpuMode {
+    fn bits(&self) -> u8 {
+        match self {
+            PpuMode::HBlank  => 0,
+            PpuMode::VBlank  => 1,
+            PpuMode::OamScan => 2,
+            PpuMode::Drawing => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Sprite {
+    y: u8,
+    x: u8,
+    tile: u8,
+    attributes: u8,
+}
+
+#[derive(Debug)]
+pub struct Ppu {
+    vram: Vec<u8>,
+    oam: [u8; OAM_SIZE],
+
+    lcdc: u8,
+    // Only the interrupt select bits, mode and coincidence are worked out on read
+    stat: u8,
+    scy: u8,
+    scx: u8,
+    ly: u8,
+    lyc: u8,
+    bgp: u8,
+    obp0: u8,
+    obp1: u8,
+    wy: u8,
+    wx: u8,
+
+    mode: PpuMode,
+    // Position within the current line, 0-455
+    dot: u16,
+    drawing_dots: u16,
+    // STAT interrupts fire on the rising edge of all the enabled sources OR'd together
+    stat_line: bool,
+
+    // Sprites picked during OAM scan for the current line, in OAM order
+    line_sprites: [Sprite; SPRITES_PER_LINE],
+    line_sprite_count: usize,
+    // Height the OAM scan picked them with, LCDC can be changed again before the line is drawn
+    line_sprite_height: u8,
+    // The window has its own line counter that only moves on lines it was drawn on
+    window_line: u8,
+    window_triggered: bool,
+
+    // 2-bit shades after BGP/OBP have been applied, tagged with the layer, row major
+    frame_buffer: Vec<u8>,
+    frame_ready: bool,
+}
+
+impl Ppu {
+    pub fn new() -> Self {
+        Ppu {
+            vram: vec![0; VRAM_SIZE],
+            oam: [0; OAM_SIZE],
+            // State the boot rom leaves behind
+            lcdc: 0x91,
+            stat: 0,
+            scy: 0,
+            scx: 0,
+            ly: 0,
+            lyc: 0,
+            bgp: 0xFC,
+            obp0: 0,
+            obp1: 0,
+            wy: 0,
+            wx: 0,
+            mode: PpuMode::OamScan,
+            dot: 0,
+            drawing_dots: DRAWING_DOTS,
+            stat_line: false,
+            line_sprites: [Sprite::default(); SPRITES_PER_LINE],
+            line_sprite_count: 0,
+            line_sprite_height: 8,
+            window_line: 0,
+            window_triggered: false,
+            frame_buffer: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            frame_ready: false,
+        }
+    }
+
+    pub fn read_vram(&self, address: u16) -> u8 {
+        self.vram[(address - VRAM_START) as usize]
+    }
+
+    pub fn write_vram(&mut self, address: u16, val: u8) {
+        self.vram[(address - VRAM_START) as usize] = val;
+    }
+
+    pub fn read_oam(&self, address: u16) -> u8 {
+        self.oam[(address - OAM_START) as usize]
+    }
+
+    pub fn write_oam(&mut self, address: u16, val: u8) {
+        self.oam[(address - OAM_START) as usize] = val;
+    }
+
+    pub fn read_register(&self, address: u16) -> u8 {
+        match address {
+            LCDC_ADDRESS => self.lcdc,
+            STAT_ADDRESS => {
+                let mode = if self.lcd_enabled() {self.mode.bits()} else {0};
+                let coincidence = if self.ly == self.lyc {STAT_LYC_EQUAL} else {0};
+                0b1000_0000 | self.stat | coincidence | mode
+            },
+            SCY_ADDRESS => self.scy,
+            SCX_ADDRESS => self.scx,
+            LY_ADDRESS => self.ly,
+            LYC_ADDRESS => self.lyc,
+            BGP_ADDRESS => self.bgp,
+            OBP0_ADDRESS => self.obp0,
+            OBP1_ADDRESS => self.obp1,
+            WY_ADDRESS => self.wy,
+            WX_ADDRESS => self.wx,
+            _ => 0xFF
+        }
+    }
+
+    pub fn write_register(&mut self, address: u16, val: u8) {
+        match address {
+            LCDC_ADDRESS => {
+                let was_enabled = self.lcd_enabled();
+                self.lcdc = val;
+                if was_enabled && !self.lcd_enabled() {
+                    // Switching the lcd off resets it to the top of the screen
+                    self.ly = 0;
+                    self.dot = 0;
+                    self.mode = PpuMode::HBlank;
+                } else if !was_enabled && self.lcd_enabled() {
+                    self.start_frame();
+                }
+            },
+            STAT_ADDRESS => self.stat = val & 0b0111_1000,
+            SCY_ADDRESS => self.scy = val,
+            SCX_ADDRESS => self.scx = val,
+            // LY is read only
+            LY_ADDRESS => {},
+            LYC_ADDRESS => self.lyc = val,
+            BGP_ADDRESS => self.bgp = val,
+            OBP0_ADDRESS => self.obp0 = val,
+            OBP1_ADDRESS => self.obp1 = val,
+            WY_ADDRESS => self.wy = val,
+            WX_ADDRESS => self.wx = val,
+            _ => {}
+        }
+    }
+
+    pub fn mode(&self) -> PpuMode {
+        self.mode
+    }
+
+    /// Hands out the frame buffer once per finished frame
+    pub fn take_frame(&mut self) -> Option<&[u8]> {
+        if self.frame_ready {
+            self.frame_ready = false;
+            Some(&self.frame_buffer)
+        } else {
+            None
+        }
+    }
+
+    pub fn frame_buffer(&self) -> &[u8] {
+        &self.frame_buffer
+    }
+
+    pub fn tick(&mut self, cycles: u8, interrupts: &mut Interrupts) {
+        if !self.lcd_enabled() {
+            return;
+        }
+
+        let mut remaining = cycles as u16;
+        while remaining > 0 {
+            let step = remaining.min(self.mode_end() - self.dot);
+            self.dot += step;
+            remaining -= step;
+
+            if self.dot == self.mode_end() {
+                self.next_mode(interrupts);
+            }
+        }
+
+        self.update_stat_line(interrupts);
+    }
+
+    fn lcd_enabled(&self) -> bool {
+        self.lcdc & LCDC_ENABLE != 0
+    }
+
+    // Dot at which the current mode finishes
+    fn mode_end(&self) -> u16 {
+        match self.mode {
+            PpuMode::OamScan => OAM_SCAN_DOTS,
+            PpuMode::Drawing => OAM_SCAN_DOTS + self.drawing_dots,
+            PpuMode::HBlank | PpuMode::VBlank => DOTS_PER_LINE,
+        }
+    }
+
+    fn next_mode(&mut self, interrupts: &mut Interrupts) {
+        match self.mode {
+            PpuMode::OamScan => {
+                self.oam_scan();
+                self.drawing_dots = DRAWING_DOTS
+                    + (self.scx % 8) as u16
+                    + self.line_sprite_count as u16 * SPRITE_DOTS;
+                self.mode = PpuMode::Drawing;
+            },
+            PpuMode::Drawing => {
+                self.render_scanline();
+                self.mode = PpuMode::HBlank;
+            },
+            PpuMode::HBlank => {
+                self.ly += 1;
+                if self.ly as usize == SCREEN_HEIGHT {
+                    self.dot = 0;
+                    self.mode = PpuMode::VBlank;
+                    self.frame_ready = true;
+                    interrupts.request(Interrupt::VBlank);
+                } else {
+                    self.start_line();
+                }
+            },
+            PpuMode::VBlank => {
+                self.ly += 1;
+                if self.ly == LINES_PER_FRAME {
+                    self.start_frame();
+                } else {
+                    self.dot = 0;
+                }
+            },
+        }
+
+        // Each mode change can raise the STAT line, it has to be checked before moving on
+        self.update_stat_line(interrupts);
+    }
+
+    fn start_frame(&mut self) {
+        self.ly = 0;
+        self.window_line = 0;
+        self.window_triggered = false;
+        self.start_line();
+    }
+
+    fn start_line(&mut self) {
+        self.dot = 0;
+        self.mode = PpuMode::OamScan;
+        // Once LY has matched WY the window stays live for the rest of the frame
+        if self.ly == self.wy {
+            self.window_triggered = true;
+        }
+    }
+
+    fn sprite_height(&self) -> u8 {
+        if self.lcdc & LCDC_OBJ_SIZE != 0 {16} else {8}
+    }
+
+    // Picks the first 10 sprites in OAM that overlap this line
+    fn oam_scan(&mut self) {
+        self.line_sprite_height = self.sprite_height();
+        let height = self.line_sprite_height as u16;
+        let line = self.ly as u16 + 16;
+
+        self.line_sprite_count = 0;
+        for entry in self.oam.chunks_exact(4).take(SPRITE_COUNT) {
+            let y = entry[0] as u16;
+            if line < y || line >= y + height {
+                continue;
+            }
+
+            self.line_sprites[self.line_sprite_count] = Sprite {
+                y: entry[0],
+                x: entry[1],
+                tile: entry[2],
+                attributes: entry[3],
+            };
+            self.line_sprite_count += 1;
+            if self.line_sprite_count == SPRITES_PER_LINE {
+                break;
+            }
+        }
+
+        // Smaller X wins on the DMG, ties go to whichever came first in OAM.
+        // The sort is stable so OAM order is kept for equal X
+        self.line_sprites[..self.line_sprite_count].sort_by_key(|sprite| sprite.x);
+    }
+
+    // Colour index 0-3 of one pixel in a tile row
+    fn tile_pixel(&self, tile_address: usize, row: usize, column: usize) -> u8 {
+        let lo = self.vram[tile_address + row * 2];
+        let hi = self.vram[tile_address + row * 2 + 1];
+        let bit = 7 - column;
+        (((hi >> bit) & 0b1) << 1) | ((lo >> bit) & 0b1)
+    }
+
+    // Background and window tiles can use either addressing mode, sprites are always unsigned
+    fn bg_tile_address(&self, tile: u8) -> usize {
+        if self.lcdc & LCDC_TILE_DATA != 0 {
+            TILE_DATA_UNSIGNED + tile as usize * 16
+        } else {
+            (TILE_DATA_SIGNED as isize + (tile as i8) as isize * 16) as usize
+        }
+    }
+
+    fn map_pixel(&self, map: usize, x: u8, y: u8) -> u8 {
+        let tile = self.vram[map + (y as usize / 8) * 32 + x as usize / 8];
+        self.tile_pixel(self.bg_tile_address(tile), (y % 8) as usize, (x % 8) as usize)
+    }
+
+    fn update_stat_line(&mut self, interrupts: &mut Interrupts) {
+        let line = (self.stat & STAT_LYC_INTERRUPT != 0 && self.ly == self.lyc)
+            || (self.stat & STAT_OAM_INTERRUPT != 0 && self.mode == PpuMode::OamScan)
+            || (self.stat & STAT_VBLANK_INTERRUPT != 0 && self.mode == PpuMode::VBlank)
+            || (self.stat & STAT_HBLANK_INTERRUPT != 0 && self.mode == PpuMode::HBlank);
+
+        if line && !self.stat_line {
+            interrupts.request(Interrupt::LcdStat);
+        }
+        self.stat_line = line;
+    }
+
+    fn render_scanline(&mut self) {
+        // Colour indexes before BGP, sprites need these for BG-over-OBJ priority
+        let mut bg_colours = [0u8; SCREEN_WIDTH];
+        let bg_enabled = self.lcdc & LCDC_BG_WINDOW_ENABLE != 0;
+
+        if bg_enabled {
+            let bg_map = if self.lcdc & LCDC_BG_TILE_MAP != 0 {TILE_MAP_1} else {TILE_MAP_0};
+            let y = self.ly.wrapping_add(self.scy);
+            for (x, colour) in bg_colours.iter_mut().enumerate() {
+                *colour = self.map_pixel(bg_map, (x as u8).wrapping_add(self.scx), y);
+            }
+
+            // WX is offset by 7, anything past 166 is off screen
+            let window_x = self.wx as i16 - 7;
+            if self.lcdc & LCDC_WINDOW_ENABLE != 0 && self.window_triggered && window_x < SCREEN_WIDTH as i16 {
+                let window_map = if self.lcdc & LCDC_WINDOW_TILE_MAP != 0 {TILE_MAP_1} else {TILE_MAP_0};
+                for x in window_x.max(0)..SCREEN_WIDTH as i16 {
+                    bg_colours[x as usize] = self.map_pixel(window_map, (x - window_x) as u8, self.window_line);
+                }
+                self.window_line += 1;
+            }
+        }
+
+        let start = self.ly as usize * SCREEN_WIDTH;
+        for (x, &colour) in bg_colours.iter().enumerate() {
+            // With the background off the DMG shows plain white, whatever BGP says
+            let shade = if bg_enabled {apply_palette(self.bgp, colour)} else {0};
+            self.frame_buffer[start + x] = BG_LAYER | shade;
+        }
+
+        if self.lcdc & LCDC_OBJ_ENABLE != 0 {
+            self.render_sprites(&bg_colours);
+        }
+    }
+
+    fn render_sprites(&mut self, bg_colours: &[u8; SCREEN_WIDTH]) {
+        let height = self.line_sprite_height;
+        let start = self.ly as usize * SCREEN_WIDTH;
+
+        for (x, &bg_colour) in bg_colours.iter().enumerate() {
+            // Sprites are already in priority order, the first opaque pixel wins
+            // even if it then ends up hidden behind the background
+            for sprite in &self.line_sprites[..self.line_sprite_count] {
+                let column = x as i16 - (sprite.x as i16 - 8);
+                if !(0..8).contains(&column) {
+                    continue;
+                }
+
+                let mut row = self.ly + 16 - sprite.y;
+                if sprite.attributes & OBJ_Y_FLIP != 0 {
+                    row = height - 1 - row;
+                }
+                let column = if sprite.attributes & OBJ_X_FLIP != 0 {7 - column} else {column};
+
+                // 8x16 sprites ignore bit 0 of the tile index
+                let tile = if height == 16 {sprite.tile & 0xFE} else {sprite.tile};
+                let tile_address = TILE_DATA_UNSIGNED + tile as usize * 16;
+                let colour = self.tile_pixel(tile_address, row as usize, column as usize);
+                if colour == 0 {
+                    continue;
+                }
+
+                if sprite.attributes & OBJ_BG_PRIORITY == 0 || bg_colour == 0 {
+                    let (palette, layer) = if sprite.attributes & OBJ_PALETTE != 0 {
+                        (self.obp1, OBJ1_LAYER)
+                    } else {
+                        (self.obp0, OBJ0_LAYER)
+                    };
+                    self.frame_buffer[start + x] = layer | apply_palette(palette, colour);
+                }
+                break;
+            }
+        }
+    }
+}
+
+impl Default for Ppu {
+    fn default() -> Self {
+        Ppu::new()
+    }
+}
+
+fn apply_palette(palette: u8, colour: u8) -> u8 {
+    (palette >> (colour * 2)) & 0b11
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE: u32 = DOTS_PER_LINE as u32;
+
+    // Ticks the ppu a whole M-cycle at a time, the way the cpu drives it
+    fn run(ppu: &mut Ppu, interrupts: &mut Interrupts, dots: u32) {
+        for _ in 0..dots / 4 {
+            ppu.tick(4, interrupts);
+        }
+    }
+
+    fn stat_mode(ppu: &Ppu) -> u8 {
+        ppu.read_register(STAT_ADDRESS) & 0b11
+    }
+
+    // Fills every row of an unsigned tile with one colour
+    fn solid_tile(ppu: &mut Ppu, tile: u8, colour: u8) {
+        let lo = if colour & 0b01 != 0 {0xFF} else {0x00};
+        let hi = if colour & 0b10 != 0 {0xFF} else {0x00};
+        for row in 0..8 {
+            let address = VRAM_START + tile as u16 * 16 + row * 2;
+            ppu.write_vram(address, lo);
+            ppu.write_vram(address + 1, hi);
+        }
+    }
+
+    fn sprite(ppu: &mut Ppu, index: u16, y: u8, x: u8, tile: u8, attributes: u8) {
+        for (offset, val) in (0..).zip([y, x, tile, attributes]) {
+            ppu.write_oam(OAM_START + index * 4 + offset, val);
+        }
+    }
+
+    fn pixel(ppu: &Ppu, x: usize, y: usize) -> u8 {
+        ppu.frame_buffer()[y * SCREEN_WIDTH + x]
+    }
+
+    // A ppu with sprites on and palettes that leave colours as they are
+    fn drawing(lcdc: u8) -> Ppu {
+        let mut ppu = Ppu::new();
+        ppu.write_register(LCDC_ADDRESS, lcdc);
+        for address in [BGP_ADDRESS, OBP0_ADDRESS, OBP1_ADDRESS] {
+            ppu.write_register(address, 0xE4);
+        }
+        ppu
+    }
+
+    fn map_row(ppu: &mut Ppu, map: usize, row: u16, tile: u8) {
+        for column in 0..32 {
+            ppu.write_vram(VRAM_START + map as u16 + row * 32 + column, tile);
+        }
+    }
+
+    #[test]
+    fn modes_follow_each_other_through_a_line() {
+        let mut ppu = Ppu::new();
+        let mut interrupts = Interrupts::default();
+
+        let mut modes = Vec::new();
+        for _ in 0..LINE / 4 {
+            modes.push(ppu.mode());
+            ppu.tick(4, &mut interrupts);
+        }
+        let dots = |mode| modes.iter().filter(|&&m| m == mode).count() as u16 * 4;
+        assert_eq!(dots(PpuMode::OamScan), OAM_SCAN_DOTS);
+        assert_eq!(dots(PpuMode::Drawing), DRAWING_DOTS);
+        assert_eq!(dots(PpuMode::HBlank), DOTS_PER_LINE - OAM_SCAN_DOTS - DRAWING_DOTS);
+        modes.dedup();
+        assert_eq!(modes, [PpuMode::OamScan, PpuMode::Drawing, PpuMode::HBlank]);
+
+        assert_eq!(ppu.read_register(LY_ADDRESS), 1);
+        assert_eq!(stat_mode(&ppu), PpuMode::OamScan.bits());
+    }
+
+    #[test]
+    fn scrolling_stretches_the_drawing_mode() {
+        let mut ppu = Ppu::new();
+        let mut interrupts = Interrupts::default();
+        ppu.write_register(SCX_ADDRESS, 3);
+
+        run(&mut ppu, &mut interrupts, OAM_SCAN_DOTS as u32 + DRAWING_DOTS as u32);
+        assert_eq!(ppu.mode(), PpuMode::Drawing);
+        run(&mut ppu, &mut interrupts, 4);
+        assert_eq!(ppu.mode(), PpuMode::HBlank);
+
+        // The line itself stays the same length
+        run(&mut ppu, &mut interrupts, LINE - OAM_SCAN_DOTS as u32 - DRAWING_DOTS as u32 - 4);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 1);
+    }
+
+    #[test]
+    fn vblank_starts_at_line_144() {
+        let mut ppu = Ppu::new();
+        let mut interrupts = Interrupts::default();
+
+        run(&mut ppu, &mut interrupts, SCREEN_HEIGHT as u32 * LINE - 4);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 143);
+        assert_eq!(interrupts.flag & Interrupt::VBlank.bit(), 0);
+        assert!(ppu.take_frame().is_none());
+
+        run(&mut ppu, &mut interrupts, 4);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 144);
+        assert_eq!(ppu.mode(), PpuMode::VBlank);
+        assert_eq!(stat_mode(&ppu), PpuMode::VBlank.bits());
+        assert_eq!(interrupts.flag, Interrupt::VBlank.bit());
+        assert!(ppu.take_frame().is_some());
+        assert!(ppu.take_frame().is_none());
+
+        // Ten lines of vblank, then back to the top
+        interrupts.flag = 0;
+        run(&mut ppu, &mut interrupts, 10 * LINE - 4);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 153);
+        assert_eq!(ppu.mode(), PpuMode::VBlank);
+        run(&mut ppu, &mut interrupts, 4);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 0);
+        assert_eq!(ppu.mode(), PpuMode::OamScan);
+        assert_eq!(interrupts.flag, 0);
+    }
+
+    #[test]
+    fn coincidence_flag_follows_ly() {
+        let mut ppu = Ppu::new();
+        let mut interrupts = Interrupts::default();
+        ppu.write_register(LYC_ADDRESS, 2);
+
+        assert_eq!(ppu.read_register(STAT_ADDRESS) & STAT_LYC_EQUAL, 0);
+        run(&mut ppu, &mut interrupts, 2 * LINE);
+        assert_eq!(ppu.read_register(STAT_ADDRESS) & STAT_LYC_EQUAL, STAT_LYC_EQUAL);
+        run(&mut ppu, &mut interrupts, LINE);
+        assert_eq!(ppu.read_register(STAT_ADDRESS) & STAT_LYC_EQUAL, 0);
+
+        // No interrupt unless it's selected
+        assert_eq!(interrupts.flag & Interrupt::LcdStat.bit(), 0);
+    }
+
+    #[test]
+    fn stat_interrupt_fires_on_the_rising_edge_only() {
+        let mut ppu = Ppu::new();
+        let mut interrupts = Interrupts::default();
+        ppu.write_register(STAT_ADDRESS, STAT_LYC_INTERRUPT | STAT_HBLANK_INTERRUPT);
+        ppu.write_register(LYC_ADDRESS, 0);
+
+        run(&mut ppu, &mut interrupts, 4);
+        assert_eq!(interrupts.flag, Interrupt::LcdStat.bit());
+
+        // HBlank on line 0 comes while LY=LYC still holds the line high
+        interrupts.flag = 0;
+        run(&mut ppu, &mut interrupts, LINE - 4);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 1);
+        assert_eq!(interrupts.flag, 0);
+
+        // On line 1 the line drops first, so HBlank raises it again
+        run(&mut ppu, &mut interrupts, OAM_SCAN_DOTS as u32 + DRAWING_DOTS as u32);
+        assert_eq!(ppu.mode(), PpuMode::HBlank);
+        assert_eq!(interrupts.flag, Interrupt::LcdStat.bit());
+    }
+
+    #[test]
+    fn switching_the_lcd_off_resets_it() {
+        let mut ppu = Ppu::new();
+        let mut interrupts = Interrupts::default();
+        run(&mut ppu, &mut interrupts, 5 * LINE + 100);
+
+        ppu.write_register(LCDC_ADDRESS, 0x11);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 0);
+        assert_eq!(stat_mode(&ppu), 0);
+
+        // Nothing moves while it's off
+        run(&mut ppu, &mut interrupts, 200 * LINE);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 0);
+        assert_eq!(interrupts.flag, 0);
+
+        ppu.write_register(LCDC_ADDRESS, 0x91);
+        assert_eq!(ppu.mode(), PpuMode::OamScan);
+        run(&mut ppu, &mut interrupts, LINE);
+        assert_eq!(ppu.read_register(LY_ADDRESS), 1);
+    }
+
+    #[test]
+    fn sprite_height_is_kept_from_the_oam_scan() {
+        let mut ppu = Ppu::new();
+        let mut interrupts = Interrupts::default();
+        ppu.write_register(LCDC_ADDRESS, 0x91 | LCDC_OBJ_ENABLE | LCDC_OBJ_SIZE);
+        ppu.write_register(OBP0_ADDRESS, 0xE4);
+        solid_tile(&mut ppu, 4, 1);
+        solid_tile(&mut ppu, 5, 2);
+        sprite(&mut ppu, 0, 16, 8, 5, OBJ_Y_FLIP);
+
+        // Back to 8x8 sprites after the scan has picked this one for the last row of 16
+        run(&mut ppu, &mut interrupts, 15 * LINE + OAM_SCAN_DOTS as u32 + 4);
+        ppu.write_register(LCDC_ADDRESS, 0x91 | LCDC_OBJ_ENABLE);
+        run(&mut ppu, &mut interrupts, LINE);
+
+        // Flipped, the bottom row comes from the top of the first tile
+        assert_eq!(pixel(&ppu, 0, 15), OBJ0_LAYER | 1);
+    }
+
+    #[test]
+    fn background_off_is_white_under_any_palette() {
+        let mut ppu = Ppu::new();
+        let mut interrupts = Interrupts::default();
+        ppu.write_register(BGP_ADDRESS, 0x1B);
+        ppu.write_register(OBP0_ADDRESS, 0xE4);
+        solid_tile(&mut ppu, 0, 1);
+        solid_tile(&mut ppu, 1, 2);
+        sprite(&mut ppu, 0, 16, 8, 1, OBJ_BG_PRIORITY);
+
+        run(&mut ppu, &mut interrupts, LINE);
+        assert_eq!(pixel(&ppu, 0, 0), BG_LAYER | 2);
+
+        // Sprites behind the background still show, it counts as colour 0
+        ppu.write_register(LCDC_ADDRESS, 0x90 | LCDC_OBJ_ENABLE);
+        run(&mut ppu, &mut interrupts, LINE);
+        assert_eq!(pixel(&ppu, 0, 1), OBJ0_LAYER | 2);
+        assert_eq!(pixel(&ppu, 8, 1), BG_LAYER);
+    }
+
+    #[test]
+    fn scrolling_wraps_around_the_map() {
+        let mut ppu = drawing(0x91);
+        let mut interrupts = Interrupts::default();
+        solid_tile(&mut ppu, 1, 3);
+        ppu.write_vram(VRAM_START + TILE_MAP_0 as u16 + 31 * 32 + 31, 1);
+        ppu.write_register(SCX_ADDRESS, 248);
+        ppu.write_register(SCY_ADDRESS, 248);
+
+        run(&mut ppu, &mut interrupts, 9 * LINE);
+        for y in 0..8 {
+            assert_eq!(pixel(&ppu, 7, y), 3);
+            assert_eq!(pixel(&ppu, 8, y), 0);
+        }
+        assert_eq!(pixel(&ppu, 0, 8), 0);
+    }
+
+    #[test]
+    fn window_line_only_counts_lines_it_was_drawn_on() {
+        let lcdc = 0x91 | LCDC_WINDOW_TILE_MAP | LCDC_WINDOW_ENABLE;
+        let mut ppu = drawing(lcdc);
+        let mut interrupts = Interrupts::default();
+        for (row, colour) in (0..3).zip([3, 2, 1]) {
+            solid_tile(&mut ppu, row as u8 + 1, colour);
+            map_row(&mut ppu, TILE_MAP_1, row, row as u8 + 1);
+        }
+        ppu.write_register(WX_ADDRESS, 80 + 7);
+        ppu.write_register(WY_ADDRESS, 4);
+
+        run(&mut ppu, &mut interrupts, 8 * LINE);
+        assert_eq!(pixel(&ppu, 80, 3), 0);
+        assert_eq!(pixel(&ppu, 79, 4), 0);
+        assert_eq!(pixel(&ppu, 80, 4), 3);
+        assert_eq!(pixel(&ppu, 159, 7), 3);
+
+        // Off for eight lines, then back on where it left off
+        ppu.write_register(LCDC_ADDRESS, lcdc & !LCDC_WINDOW_ENABLE);
+        run(&mut ppu, &mut interrupts, 8 * LINE);
+        ppu.write_register(LCDC_ADDRESS, lcdc);
+        run(&mut ppu, &mut interrupts, LINE);
+        assert_eq!(pixel(&ppu, 80, 15), 0);
+        assert_eq!(pixel(&ppu, 80, 16), 3);
+        run(&mut ppu, &mut interrupts, 4 * LINE);
+        assert_eq!(pixel(&ppu, 80, 20), 2);
+    }
+
+    #[test]
+    fn only_ten_sprites_per_line() {
+        let mut ppu = drawing(0x91 | LCDC_OBJ_ENABLE);
+        let mut interrupts = Interrupts::default();
+        solid_tile(&mut ppu, 1, 3);
+        for index in 0..11 {
+            sprite(&mut ppu, index, 16, 8 + index as u8 * 12, 1, 0);
+        }
+
+        run(&mut ppu, &mut interrupts, LINE);
+        for index in 0..10 {
+            assert_eq!(pixel(&ppu, index * 12, 0), OBJ0_LAYER | 3);
+        }
+        assert_eq!(pixel(&ppu, 120, 0), BG_LAYER);
+    }
+
+    #[test]
+    fn smaller_x_wins_and_ties_go_to_oam_order() {
+        let mut ppu = drawing(0x91 | LCDC_OBJ_ENABLE);
+        let mut interrupts = Interrupts::default();
+        solid_tile(&mut ppu, 1, 3);
+        sprite(&mut ppu, 0, 16, 12, 1, OBJ_PALETTE);
+        sprite(&mut ppu, 1, 16, 10, 1, 0);
+        sprite(&mut ppu, 2, 16, 50, 1, OBJ_PALETTE);
+        sprite(&mut ppu, 3, 16, 50, 1, 0);
+
+        run(&mut ppu, &mut interrupts, LINE);
+        assert_eq!(pixel(&ppu, 2, 0) & LAYER_MASK, OBJ0_LAYER);
+        assert_eq!(pixel(&ppu, 9, 0) & LAYER_MASK, OBJ0_LAYER);
+        assert_eq!(pixel(&ppu, 10, 0) & LAYER_MASK, OBJ1_LAYER);
+        assert_eq!(pixel(&ppu, 42, 0) & LAYER_MASK, OBJ1_LAYER);
+    }
+
+    #[test]
+    fn sprites_flip_both_ways() {
+        let mut ppu = drawing(0x91 | LCDC_OBJ_ENABLE);
+        let mut interrupts = Interrupts::default();
+        // Just the top left pixel set
+        ppu.write_vram(VRAM_START + 16, 0b1000_0000);
+        ppu.write_vram(VRAM_START + 17, 0b1000_0000);
+        for (index, flip) in (0..).zip([0, OBJ_X_FLIP, OBJ_Y_FLIP, OBJ_X_FLIP | OBJ_Y_FLIP]) {
+            sprite(&mut ppu, index, 16, 8 + index as u8 * 16, 1, flip);
+        }
+
+        run(&mut ppu, &mut interrupts, 8 * LINE);
+        let lit: Vec<_> = (0..8)
+            .flat_map(|y| (0..64).map(move |x| (x, y)))
+            .filter(|&(x, y)| pixel(&ppu, x, y) != BG_LAYER)
+            .collect();
+        assert_eq!(lit, [(0, 0), (23, 0), (32, 7), (55, 7)]);
+    }
+
+    #[test]
+    fn tall_sprites_ignore_bit_0_of_the_tile() {
+        let mut ppu = drawing(0x91 | LCDC_OBJ_ENABLE | LCDC_OBJ_SIZE);
+        let mut interrupts = Interrupts::default();
+        solid_tile(&mut ppu, 2, 1);
+        solid_tile(&mut ppu, 3, 2);
+        sprite(&mut ppu, 0, 16, 8, 3, 0);
+
+        run(&mut ppu, &mut interrupts, 17 * LINE);
+        assert_eq!(pixel(&ppu, 0, 0), OBJ0_LAYER | 1);
+        assert_eq!(pixel(&ppu, 0, 7), OBJ0_LAYER | 1);
+        assert_eq!(pixel(&ppu, 0, 8), OBJ0_LAYER | 2);
+        assert_eq!(pixel(&ppu, 0, 15), OBJ0_LAYER | 2);
+        assert_eq!(pixel(&ppu, 0, 16), BG_LAYER);
+    }
+
+    #[test]
+    fn background_priority_only_hides_behind_colours_1_to_3() {
+        let mut ppu = drawing(0x91 | LCDC_OBJ_ENABLE);
+        let mut interrupts = Interrupts::default();
+        // Colour 0 shaded black, so the priority check has to use the colour and not the shade
+        ppu.write_register(BGP_ADDRESS, 0xE7);
+        solid_tile(&mut ppu, 1, 1);
+        solid_tile(&mut ppu, 2, 2);
+        ppu.write_vram(VRAM_START + TILE_MAP_0 as u16, 1);
+        sprite(&mut ppu, 0, 16, 12, 2, OBJ_BG_PRIORITY);
+
+        run(&mut ppu, &mut interrupts, LINE);
+        assert_eq!(pixel(&ppu, 3, 0), BG_LAYER | 1);
+        assert_eq!(pixel(&ppu, 7, 0), BG_LAYER | 1);
+        assert_eq!(pixel(&ppu, 8, 0), OBJ0_LAYER | 2);
+        assert_eq!(pixel(&ppu, 11, 0), OBJ0_LAYER | 2);
+        assert_eq!(pixel(&ppu, 12, 0), BG_LAYER | 3);
+    }
+
+    #[test]
+    fn sprites_pick_their_palette() {
+        let mut ppu = drawing(0x91 | LCDC_OBJ_ENABLE);
+        let mut interrupts = Interrupts::default();
+        ppu.write_register(OBP1_ADDRESS, 0x1B);
+        solid_tile(&mut ppu, 1, 1);
+        sprite(&mut ppu, 0, 16, 8, 1, 0);
+        sprite(&mut ppu, 1, 16, 16, 1, OBJ_PALETTE);
+
+        run(&mut ppu, &mut interrupts, LINE);
+        assert_eq!(pixel(&ppu, 0, 0), OBJ0_LAYER | 1);
+        assert_eq!(pixel(&ppu, 8, 0), OBJ1_LAYER | 2);
+    }
+}
@@ -1,19 +1,305 @@
+use super::apu::{Apu, NR10_ADDRESS, WAVE_RAM_END};
+use super::cartridge::{Cartridge, CartridgeHeader};
+use super::clock::{Clock, SystemClock};
+use super::mapper::{Mapper, new_mapper};
+use super::ppu::{Ppu, LCDC_ADDRESS, LYC_ADDRESS, BGP_ADDRESS, WX_ADDRESS};
+use super::interrupts::{Interrupts, INTERRUPT_ENABLE_ADDRESS, INTERRUPT_FLAG_ADDRESS};
+use super::joypad::{Joypad, JOYP_ADDRESS};
+use super::serial::{Serial, SB_ADDRESS, SC_ADDRESS};
+use super::timer::{Timer, DIV_ADDRESS, TAC_ADDRESS};
+
+pub const ROM_BANK_0_START: u16 = 0x0000;
+pub const ROM_BANK_N_END: u16 = 0x7FFF;
+pub const VRAM_START: u16 = 0x8000;
+pub const VRAM_END: u16 = 0x9FFF;
+pub const EXTERNAL_RAM_START: u16 = 0xA000;
+pub const EXTERNAL_RAM_END: u16 = 0xBFFF;
+pub const WRAM_START: u16 = 0xC000;
+pub const WRAM_END: u16 = 0xDFFF;
+pub const ECHO_RAM_START: u16 = 0xE000;
+pub const ECHO_RAM_END: u16 = 0xFDFF;
+pub const OAM_START: u16 = 0xFE00;
+pub const OAM_END: u16 = 0xFE9F;
+pub const UNUSABLE_START: u16 = 0xFEA0;
+pub const UNUSABLE_END: u16 = 0xFEFF;
+pub const IO_START: u16 = 0xFF00;
+pub const IO_END: u16 = 0xFF7F;
+pub const HRAM_START: u16 = 0xFF80;
+pub const HRAM_END: u16 = 0xFFFE;
+
+const WRAM_SIZE: usize = 0x2000;
+const OAM_SIZE: usize = 0xA0;
+const IO_SIZE: usize = 0x80;
+const HRAM_SIZE: usize = 0x7F;
+
+const DMA_ADDRESS: u16 = 0xFF46;
+
+// The APU frame sequencer steps on the falling edge of DIV bit 4
+const FRAME_SEQUENCER_BIT: u16 = 1 << 12;
+
+// Bits that are not wired up on the DMG and always read back as 1, indexed from 0xFF00
+const IO_UNUSED_BITS: [u8; IO_SIZE] = [
+    0xC0, 0x00, 0x7E, 0xFF, 0x00, 0x00, 0x00, 0xF8, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xE0, // 0xFF00
+    0x80, 0x3F, 0x00, 0xFF, 0xBF, 0xFF, 0x3F, 0x00, 0xFF, 0xBF, 0x7F, 0xFF, 0x9F, 0xFF, 0xBF, 0xFF, // 0xFF10
+    0xFF, 0x00, 0x00, 0xBF, 0x00, 0x00, 0x70, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // 0xFF20
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // 0xFF30
+    0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, // 0xFF40
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // 0xFF50
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // 0xFF60
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // 0xFF70
+];
+
 #[derive(Debug)]
 pub struct MemoryBus {
-    memory: [u8; 0xFFFF]
+    header: CartridgeHeader,
+    mapper: Box<dyn Mapper>,
+    // Kept on the heap, the ESP32 main task stack is small
+    wram: Vec<u8>,
+    io: [u8; IO_SIZE],
+    hram: [u8; HRAM_SIZE],
+    pub interrupts: Interrupts,
+    pub joypad: Joypad,
+    pub serial: Serial,
+    pub timer: Timer,
+    pub ppu: Ppu,
+    pub apu: Apu,
 }
 
 impl MemoryBus {
+    pub fn new(cartridge: Cartridge) -> Self {
+        MemoryBus::with_clock(cartridge, Box::new(SystemClock))
+    }
+
+    /// Lets the cartridge RTC run off something other than the system time
+    pub fn with_clock(cartridge: Cartridge, clock: Box<dyn Clock>) -> Self {
+        let (header, rom) = cartridge.into_parts();
+        let mapper = new_mapper(&header, rom, clock);
+        MemoryBus {
+            header,
+            mapper,
+            wram: vec![0; WRAM_SIZE],
+            io: [0; IO_SIZE],
+            hram: [0; HRAM_SIZE],
+            interrupts: Interrupts::default(),
+            joypad: Joypad::new(),
+            serial: Serial::new(),
+            timer: Timer::new(),
+            ppu: Ppu::new(),
+            apu: Apu::default(),
+        }
+    }
+
     pub fn read_byte(&self, address: u16) -> u8 {
-        self.memory[address as usize]
+        match address {
+            ROM_BANK_0_START..=ROM_BANK_N_END => self.mapper.read_rom(address),
+            VRAM_START..=VRAM_END => self.ppu.read_vram(address),
+            EXTERNAL_RAM_START..=EXTERNAL_RAM_END => self.mapper.read_ram(address),
+            WRAM_START..=WRAM_END => self.wram[(address - WRAM_START) as usize],
+            ECHO_RAM_START..=ECHO_RAM_END => self.wram[(address - ECHO_RAM_START) as usize],
+            OAM_START..=OAM_END => self.ppu.read_oam(address),
+            UNUSABLE_START..=UNUSABLE_END => 0x00,
+            INTERRUPT_FLAG_ADDRESS => self.interrupts.read_flag(),
+            LCDC_ADDRESS..=LYC_ADDRESS | BGP_ADDRESS..=WX_ADDRESS => self.ppu.read_register(address),
+            DIV_ADDRESS..=TAC_ADDRESS => {
+                self.timer.read_register(address) | IO_UNUSED_BITS[(address - IO_START) as usize]
+            },
+            NR10_ADDRESS..=WAVE_RAM_END => {
+                self.apu.read_register(address) | IO_UNUSED_BITS[(address - IO_START) as usize]
+            },
+            IO_START..=IO_END => self.read_io(address),
+            HRAM_START..=HRAM_END => self.hram[(address - HRAM_START) as usize],
+            INTERRUPT_ENABLE_ADDRESS => self.interrupts.enable,
+        }
     }
 
     pub fn write_byte(&mut self, address: u16, val: u8) {
-        self.memory[address as usize] = val;
+        match address {
+            // Writes to rom go to the mapper's banking registers
+            ROM_BANK_0_START..=ROM_BANK_N_END => self.mapper.write_rom(address, val),
+            VRAM_START..=VRAM_END => self.ppu.write_vram(address, val),
+            EXTERNAL_RAM_START..=EXTERNAL_RAM_END => self.mapper.write_ram(address, val),
+            WRAM_START..=WRAM_END => self.wram[(address - WRAM_START) as usize] = val,
+            ECHO_RAM_START..=ECHO_RAM_END => self.wram[(address - ECHO_RAM_START) as usize] = val,
+            OAM_START..=OAM_END => self.ppu.write_oam(address, val),
+            UNUSABLE_START..=UNUSABLE_END => {},
+            INTERRUPT_FLAG_ADDRESS => self.interrupts.write_flag(val),
+            LCDC_ADDRESS..=LYC_ADDRESS | BGP_ADDRESS..=WX_ADDRESS => self.ppu.write_register(address, val),
+            DIV_ADDRESS..=TAC_ADDRESS => {
+                let counter = self.timer.counter();
+                self.timer.write_register(address, val);
+                self.clock_frame_sequencer(counter);
+            },
+            NR10_ADDRESS..=WAVE_RAM_END => self.apu.write_register(address, val),
+            IO_START..=IO_END => self.write_io(address, val),
+            HRAM_START..=HRAM_END => self.hram[(address - HRAM_START) as usize] = val,
+            INTERRUPT_ENABLE_ADDRESS => self.interrupts.enable = val,
+        }
+    }
+
+    pub fn header(&self) -> &CartridgeHeader {
+        &self.header
+    }
+
+    /// Advances everything clocked alongside the cpu
+    pub fn tick(&mut self, cycles: u8) {
+        let counter = self.timer.counter();
+        self.timer.tick(cycles, &mut self.interrupts);
+        self.clock_frame_sequencer(counter);
+
+        self.serial.tick(cycles, &mut self.interrupts);
+        self.ppu.tick(cycles, &mut self.interrupts);
+        self.apu.tick(cycles);
+    }
+
+    /// Battery backed ram and RTC state to write out, None if the cart has no battery
+    pub fn save_data(&self) -> Option<Vec<u8>> {
+        if self.header.cartridge_type.battery {
+            Some(self.mapper.save_data())
+        } else {
+            None
+        }
+    }
+
+    /// The cartridge ram out of `save_data`, without anything like the RTC that moves on its own
+    pub fn save_ram(&self) -> Option<Vec<u8>> {
+        if self.header.cartridge_type.battery {
+            Some(self.mapper.ram_data())
+        } else {
+            None
+        }
+    }
+
+    pub fn load_save_data(&mut self, data: &[u8]) {
+        self.mapper.load_save_data(data);
+    }
+
+    pub fn rumble(&self) -> bool {
+        self.mapper.rumble()
+    }
+
+    pub fn read_word(&self, address: u16) -> u16 {
+        let lsb = self.read_byte(address) as u16;
+        let msb = self.read_byte(address.wrapping_add(1)) as u16;
+
+        (msb << 8) | lsb
     }
 
     pub fn write_word(&mut self, address: u16, val: u16) {
-        self.memory[address as usize] = (val & 0xFF) as u8;//lsb
-        self.memory[address as usize + 1] = (val >> 8) as u8;//msb
+        self.write_byte(address, (val & 0xFF) as u8);//lsb
+        self.write_byte(address.wrapping_add(1), (val >> 8) as u8);//msb
+    }
+
+    fn read_io(&self, address: u16) -> u8 {
+        let index = (address - IO_START) as usize;
+        match address {
+            JOYP_ADDRESS => self.joypad.read_register() | IO_UNUSED_BITS[index],
+            SB_ADDRESS | SC_ADDRESS => self.serial.read_register(address) | IO_UNUSED_BITS[index],
+            _ => self.io[index] | IO_UNUSED_BITS[index]
+        }
+    }
+
+    fn write_io(&mut self, address: u16, val: u8) {
+        let index = (address - IO_START) as usize;
+        match address {
+            JOYP_ADDRESS => self.joypad.write_register(val, &mut self.interrupts),
+            SB_ADDRESS | SC_ADDRESS => self.serial.write_register(address, val),
+            DMA_ADDRESS => {
+                self.io[index] = val;
+                self.oam_dma(val);
+            },
+            _ => self.io[index] = val & !IO_UNUSED_BITS[index]
+        }
+    }
+
+    // Steps the APU if DIV bit 4 fell since the counter was at `before`, resetting DIV counts too
+    fn clock_frame_sequencer(&mut self, before: u16) {
+        if before & FRAME_SEQUENCER_BIT != 0 && self.timer.counter() & FRAME_SEQUENCER_BIT == 0 {
+            self.apu.clock_frame_sequencer();
+        }
+    }
+
+    // Copies 0xXX00-0xXX9F into OAM in one go
+    fn oam_dma(&mut self, source: u8) {
+        let start = (source as u16) << 8;
+        for i in 0..OAM_SIZE as u16 {
+            let val = self.read_byte(start.wrapping_add(i));
+            self.ppu.write_oam(OAM_START + i, val);
+        }
     }
  }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gb::cartridge::tests::build_rom;
+
+    fn bus() -> MemoryBus {
+        MemoryBus::new(Cartridge::new(build_rom(0x00, 0x00, 0x00)).unwrap())
+    }
+
+    #[test]
+    fn echo_ram_mirrors_work_ram_both_ways() {
+        let mut bus = bus();
+        bus.write_byte(0xC123, 0x12);
+        assert_eq!(bus.read_byte(0xE123), 0x12);
+
+        bus.write_byte(ECHO_RAM_END, 0x34);
+        assert_eq!(bus.read_byte(0xDDFF), 0x34);
+        bus.write_byte(ECHO_RAM_START, 0x56);
+        assert_eq!(bus.read_byte(WRAM_START), 0x56);
+    }
+
+    #[test]
+    fn unusable_area_ignores_writes() {
+        let mut bus = bus();
+        for address in UNUSABLE_START..=UNUSABLE_END {
+            bus.write_byte(address, 0xAB);
+            assert_eq!(bus.read_byte(address), 0x00);
+        }
+    }
+
+    #[test]
+    fn unused_io_bits_read_as_set() {
+        let mut bus = bus();
+        for (address, unused) in [(SC_ADDRESS, 0x7E), (TAC_ADDRESS, 0xF8), (INTERRUPT_FLAG_ADDRESS, 0xE0), (0xFF03, 0xFF), (0xFF4C, 0xFF)] {
+            bus.write_byte(address, 0x00);
+            assert_eq!(bus.read_byte(address), unused, "{:04X}", address);
+            bus.write_byte(address, 0xFF);
+            assert_eq!(bus.read_byte(address), 0xFF, "{:04X}", address);
+        }
+        assert_eq!(bus.read_byte(0xFF7F), 0xFF);
+    }
+
+    #[test]
+    fn interrupt_enable_round_trips() {
+        let mut bus = bus();
+        for val in [0x00, 0x1F, 0xFF] {
+            bus.write_byte(INTERRUPT_ENABLE_ADDRESS, val);
+            assert_eq!(bus.read_byte(INTERRUPT_ENABLE_ADDRESS), val);
+            assert_eq!(bus.interrupts.enable, val);
+        }
+    }
+
+    #[test]
+    fn high_ram_keeps_its_bytes() {
+        let mut bus = bus();
+        bus.write_byte(HRAM_START, 0x12);
+        bus.write_byte(HRAM_END, 0x34);
+        assert_eq!(bus.read_byte(HRAM_START), 0x12);
+        assert_eq!(bus.read_byte(HRAM_END), 0x34);
+    }
+
+    #[test]
+    fn words_are_little_endian_across_regions() {
+        let mut bus = bus();
+        // Last byte of work ram, then the first of echo ram which is 0xC000 again
+        bus.write_byte(WRAM_END, 0x34);
+        bus.write_byte(WRAM_START, 0x12);
+        assert_eq!(bus.read_word(WRAM_END), 0x1234);
+
+        bus.write_word(HRAM_END, 0xABCD);
+        assert_eq!(bus.read_byte(HRAM_END), 0xCD);
+        assert_eq!(bus.read_byte(INTERRUPT_ENABLE_ADDRESS), 0xAB);
+        assert_eq!(bus.read_word(HRAM_END), 0xABCD);
+    }
+}
@@ -14,7 +14,7 @@ pub struct Registers {
     pub h: u8,
     pub l: u8,
 }
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct FlagsRegister {
     pub zero: bool,
     pub subtract: bool,
@@ -48,6 +48,20 @@ impl std::convert::From<u8> for FlagsRegister {
 }
 
 impl Registers {
+    /// Register values the DMG boot rom hands over with
+    pub fn new() -> Self {
+        Registers {
+            a: 0x01,
+            b: 0x00,
+            c: 0x13,
+            d: 0x00,
+            e: 0xD8,
+            f: 0xB0.into(),
+            h: 0x01,
+            l: 0x4D,
+        }
+    }
+
     pub fn set_af(&mut self, val: u16) {
         self.a = ((val & 0xFF00) >> 8) as u8;
         self.f = ((val & 0xFF) as u8).into();
@@ -69,6 +83,10 @@ impl Registers {
         self.l = (val & 0xFF) as u8;
     } 
 
+    pub fn get_af(&self) -> u16 {
+        (self.a as u16) << 8 | u8::from(self.f) as u16
+    }
+
     pub fn get_bc(&self) -> u16 {
         (self.b as u16) << 8 | self.c as u16
     }
@@ -81,3 +99,9 @@ impl Registers {
         (self.h as u16) << 8 | self.l as u16
     }
 }
+
+impl Default for Registers {
+    fn default() -> Self {
+        Registers::new()
+    }
+}
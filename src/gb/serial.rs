@@ -0,0 +1,251 @@
+use std::fmt;
+
+use super::interrupts::{Interrupt, Interrupts};
+
+pub const SB_ADDRESS: u16 = 0xFF01;
+pub const SC_ADDRESS: u16 = 0xFF02;
+
+const SC_TRANSFER: u8 = 0b1000_0000;
+const SC_INTERNAL_CLOCK: u8 = 0b0000_0001;
+
+// Eight bits at 8192Hz
+const TRANSFER_CYCLES: u32 = 4096;
+// The link is checked for messages about once per bit, any more often is wasted on the ESP32
+const POLL_CYCLES: u32 = 512;
+// Text kept from the serial port, the oldest half goes when it fills up
+const MAX_OUTPUT: usize = 16 * 1024;
+
+/// What goes over the link cable, one byte transfer is a Transfer one way and a Reply back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkMessage {
+    /// The other side started a transfer on its own clock, shifting out its SB
+    Transfer(u8),
+    /// The other side's SB, shifted out in answer to our Transfer
+    Reply(u8),
+}
+
+/// Carries link cable messages to another emulator
+pub trait LinkTransport: fmt::Debug {
+    fn send(&mut self, message: LinkMessage);
+
+    /// The next message in from the other side, without waiting for one
+    fn receive(&mut self) -> Option<LinkMessage>;
+
+    /// False once the other side has gone, transfers then act like the cable was pulled
+    fn connected(&self) -> bool {
+        true
+    }
+}
+
+/// SB and SC. The Game Boy clocking the transfer shifts its SB out while the other one's
+/// shifts in, the other side only takes part if it has a transfer waiting on the external clock.
+#[derive(Debug)]
+pub struct Serial {
+    sb: u8,
+    sc: u8,
+    // Cycles left on a transfer on our clock
+    countdown: u32,
+    // The other side's answer to it, once it has come back
+    reply: Option<u8>,
+    poll_timer: u32,
+    link: Option<Box<dyn LinkTransport>>,
+    // Every byte sent on our clock, which is how test roms print
+    output: String,
+}
+
+impl Serial {
+    pub fn new() -> Self {
+        Serial {
+            sb: 0,
+            sc: 0,
+            countdown: 0,
+            reply: None,
+            poll_timer: 0,
+            link: None,
+            output: String::new(),
+        }
+    }
+
+    /// Plugs in a link cable, replacing any already connected
+    pub fn connect(&mut self, link: Box<dyn LinkTransport>) {
+        self.link = Some(link);
+    }
+
+    pub fn disconnect(&mut self) -> Option<Box<dyn LinkTransport>> {
+        self.link.take()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.link.as_ref().is_some_and(|link| link.connected())
+    }
+
+    /// Bytes the game has sent out on its own clock, as text. Blargg's test roms print their
+    /// results this way, with or without a link cable plugged in.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// Hands over the output so far, leaving it empty
+    pub fn take_output(&mut self) -> String {
+        std::mem::take(&mut self.output)
+    }
+
+    pub fn read_register(&self, address: u16) -> u8 {
+        match address {
+            SB_ADDRESS => self.sb,
+            SC_ADDRESS => self.sc,
+            _ => 0xFF
+        }
+    }
+
+    pub fn write_register(&mut self, address: u16, val: u8) {
+        match address {
+            SB_ADDRESS => self.sb = val,
+            SC_ADDRESS => {
+                self.sc = val & (SC_TRANSFER | SC_INTERNAL_CLOCK);
+                if self.clocking() {
+                    self.countdown = TRANSFER_CYCLES;
+                    self.reply = None;
+                    self.capture(self.sb);
+                    if let Some(link) = self.link.as_mut() {
+                        link.send(LinkMessage::Transfer(self.sb));
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    pub fn tick(&mut self, cycles: u8, interrupts: &mut Interrupts) {
+        self.poll_timer += cycles as u32;
+        if self.poll_timer >= POLL_CYCLES {
+            self.poll_timer -= POLL_CYCLES;
+            self.poll(interrupts);
+        }
+
+        if self.clocking() {
+            self.countdown = self.countdown.saturating_sub(cycles as u32);
+            if self.countdown == 0 {
+                // Without anything on the other end the line floats high
+                let received = if self.is_connected() {self.reply} else {Some(0xFF)};
+                if let Some(byte) = received {
+                    self.finish(byte, interrupts);
+                }
+            }
+        }
+    }
+
+    // A transfer is waiting on our own clock
+    fn clocking(&self) -> bool {
+        self.sc & (SC_TRANSFER | SC_INTERNAL_CLOCK) == SC_TRANSFER | SC_INTERNAL_CLOCK
+    }
+
+    fn capture(&mut self, byte: u8) {
+        if self.output.len() >= MAX_OUTPUT {
+            let half = self.output.char_indices().map(|(i, _)| i).find(|&i| i >= MAX_OUTPUT / 2).unwrap_or(0);
+            self.output.drain(..half);
+        }
+        // Anything outside ASCII comes through as Latin-1, game data isn't text anyway
+        self.output.push(byte as char);
+    }
+
+    fn finish(&mut self, received: u8, interrupts: &mut Interrupts) {
+        self.sb = received;
+        self.sc &= !SC_TRANSFER;
+        interrupts.request(Interrupt::Serial);
+    }
+
+    fn poll(&mut self, interrupts: &mut Interrupts) {
+        let Some(mut link) = self.link.take() else {
+            return;
+        };
+
+        while let Some(message) = link.receive() {
+            match message {
+                LinkMessage::Transfer(byte) => {
+                    // The other side's clock shifts our SB out whether or not we're ready for it
+                    link.send(LinkMessage::Reply(self.sb));
+                    if self.sc & (SC_TRANSFER | SC_INTERNAL_CLOCK) == SC_TRANSFER {
+                        self.finish(byte, interrupts);
+                    }
+                },
+                LinkMessage::Reply(byte) => {
+                    if self.clocking() {
+                        self.reply = Some(byte);
+                    }
+                },
+            }
+        }
+        self.link = Some(link);
+    }
+}
+
+impl Default for Serial {
+    fn default() -> Self {
+        Serial::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send(serial: &mut Serial, byte: u8, sc: u8) {
+        serial.write_register(SB_ADDRESS, byte);
+        serial.write_register(SC_ADDRESS, sc);
+    }
+
+    #[test]
+    fn only_transfers_on_our_clock_are_captured() {
+        let mut serial = Serial::new();
+        send(&mut serial, b'H', SC_TRANSFER | SC_INTERNAL_CLOCK);
+        send(&mut serial, b'X', SC_TRANSFER);
+        send(&mut serial, b'Y', SC_INTERNAL_CLOCK);
+        send(&mut serial, b'i', SC_TRANSFER | SC_INTERNAL_CLOCK);
+        assert_eq!(serial.output(), "Hi");
+    }
+
+    #[test]
+    fn take_output_leaves_it_empty() {
+        let mut serial = Serial::new();
+        send(&mut serial, b'A', SC_TRANSFER | SC_INTERNAL_CLOCK);
+        assert_eq!(serial.take_output(), "A");
+        assert_eq!(serial.output(), "");
+
+        send(&mut serial, b'B', SC_TRANSFER | SC_INTERNAL_CLOCK);
+        assert_eq!(serial.take_output(), "B");
+    }
+
+    #[test]
+    fn full_output_keeps_the_newest_half() {
+        let mut serial = Serial::new();
+        for _ in 0..MAX_OUTPUT / 2 {
+            serial.capture(b'a');
+        }
+        for _ in 0..MAX_OUTPUT / 2 {
+            serial.capture(b'b');
+        }
+        serial.capture(b'c');
+
+        assert_eq!(serial.output().len(), MAX_OUTPUT / 2 + 1);
+        assert!(serial.output().starts_with('b'));
+        assert!(serial.output().ends_with("bc"));
+    }
+
+    #[test]
+    fn full_output_drains_on_a_char_boundary() {
+        // One ASCII byte puts every two byte 'é' on an odd index, so half way isn't a boundary
+        let mut serial = Serial::new();
+        serial.capture(b'a');
+        for _ in 0..MAX_OUTPUT / 2 {
+            serial.capture(0xE9);
+        }
+        serial.capture(b'z');
+
+        let output = serial.output();
+        assert!(output.len() <= MAX_OUTPUT / 2 + 1);
+        assert!(output.starts_with('é'));
+        assert!(output.ends_with("éz"));
+        assert_eq!(output.chars().count(), MAX_OUTPUT / 4 + 1);
+    }
+}
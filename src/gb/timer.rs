@@ -0,0 +1,237 @@
+use super::interrupts::{Interrupt, Interrupts};
+
+pub const DIV_ADDRESS: u16 = 0xFF04;
+pub const TIMA_ADDRESS: u16 = 0xFF05;
+pub const TMA_ADDRESS: u16 = 0xFF06;
+pub const TAC_ADDRESS: u16 = 0xFF07;
+
+const TAC_ENABLE: u8 = 0b0000_0100;
+
+// Bit of the system counter TIMA watches for each TAC clock select, 4096Hz, 262144Hz, 65536Hz and 16384Hz
+const TAC_BITS: [u16; 4] = [1 << 9, 1 << 3, 1 << 5, 1 << 7];
+
+/// DIV and TIMA, both driven off the 16-bit system counter that goes up every cycle.
+/// TIMA counts on the falling edge of one of the counter's bits, ANDed with the enable,
+/// which is why writes to DIV and TAC can bump it.
+#[derive(Debug)]
+pub struct Timer {
+    counter: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+    // TIMA overflowed last M-cycle, it reads 0 until TMA is loaded on the next one
+    overflow_pending: bool,
+    // TMA went into TIMA this M-cycle, TIMA writes are ignored and TMA writes go straight through
+    reloading: bool,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Timer {
+            // Where the DMG boot rom leaves it, DIV reads 0xAB at 0x0100
+            counter: 0xABCC,
+            tima: 0,
+            tma: 0,
+            tac: 0,
+            overflow_pending: false,
+            reloading: false,
+        }
+    }
+
+    /// The full system counter, DIV is the top 8 bits
+    pub fn counter(&self) -> u16 {
+        self.counter
+    }
+
+    pub fn read_register(&self, address: u16) -> u8 {
+        match address {
+            DIV_ADDRESS => (self.counter >> 8) as u8,
+            TIMA_ADDRESS => self.tima,
+            TMA_ADDRESS => self.tma,
+            TAC_ADDRESS => self.tac,
+            _ => 0xFF
+        }
+    }
+
+    pub fn write_register(&mut self, address: u16, val: u8) {
+        match address {
+            DIV_ADDRESS => {
+                let input = self.input();
+                self.counter = 0;
+                self.detect_edge(input);
+            },
+            // A write in the gap before the reload cancels it, and the interrupt with it
+            TIMA_ADDRESS if !self.reloading => {
+                self.tima = val;
+                self.overflow_pending = false;
+            },
+            TMA_ADDRESS => {
+                self.tma = val;
+                if self.reloading {
+                    self.tima = val;
+                }
+            },
+            TAC_ADDRESS => {
+                let input = self.input();
+                self.tac = val & 0b0000_0111;
+                self.detect_edge(input);
+            },
+            _ => {}
+        }
+    }
+
+    pub fn tick(&mut self, cycles: u8, interrupts: &mut Interrupts) {
+        for _ in 0..cycles / 4 {
+            self.reloading = false;
+            if self.overflow_pending {
+                self.overflow_pending = false;
+                self.tima = self.tma;
+                self.reloading = true;
+                interrupts.request(Interrupt::Timer);
+            }
+
+            let input = self.input();
+            self.counter = self.counter.wrapping_add(4);
+            self.detect_edge(input);
+        }
+    }
+
+    // What the falling edge detector sees, the selected counter bit while the timer is enabled
+    fn input(&self) -> bool {
+        self.tac & TAC_ENABLE != 0 && self.counter & TAC_BITS[(self.tac & 0b11) as usize] != 0
+    }
+
+    fn detect_edge(&mut self, before: bool) {
+        if before && !self.input() {
+            let (tima, overflow) = self.tima.overflowing_add(1);
+            self.tima = tima;
+            self.overflow_pending |= overflow;
+        }
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Timer::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Enabled and counting on bit 3, once every 16 cycles
+    const TAC_FAST: u8 = 0b0000_0101;
+
+    fn interrupted(interrupts: &Interrupts) -> bool {
+        interrupts.flag & Interrupt::Timer.bit() != 0
+    }
+
+    // Counter at 0, about to overflow TIMA on the fourth M-cycle
+    fn about_to_overflow() -> Timer {
+        let mut timer = Timer::new();
+        timer.write_register(DIV_ADDRESS, 0);
+        timer.write_register(TAC_ADDRESS, TAC_FAST);
+        timer.write_register(TIMA_ADDRESS, 0xFF);
+        timer.write_register(TMA_ADDRESS, 0x42);
+        timer
+    }
+
+    // Ticks up to the M-cycle TIMA overflows on
+    fn overflow(timer: &mut Timer, interrupts: &mut Interrupts) {
+        timer.tick(12, interrupts);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 0xFF);
+        timer.tick(4, interrupts);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 0x00);
+    }
+
+    #[test]
+    fn counts_on_the_selected_bit() {
+        let mut timer = Timer::new();
+        let mut interrupts = Interrupts::default();
+        timer.write_register(DIV_ADDRESS, 0);
+        timer.write_register(TAC_ADDRESS, 0b0000_0100);
+
+        timer.tick(252, &mut interrupts);
+        timer.tick(252, &mut interrupts);
+        timer.tick(252, &mut interrupts);
+        timer.tick(252, &mut interrupts);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 0);
+        timer.tick(16, &mut interrupts);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 1);
+        assert_eq!(timer.read_register(DIV_ADDRESS), 4);
+    }
+
+    #[test]
+    fn div_write_can_bump_tima() {
+        let mut timer = Timer::new();
+        timer.write_register(TAC_ADDRESS, TAC_FAST);
+        // Bit 3 of 0xABCC is set, so clearing the counter is a falling edge
+        timer.write_register(DIV_ADDRESS, 0);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 1);
+        assert_eq!(timer.read_register(DIV_ADDRESS), 0);
+
+        // Not again with the bit already clear
+        timer.write_register(DIV_ADDRESS, 0);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 1);
+    }
+
+    #[test]
+    fn tac_write_can_bump_tima() {
+        let mut timer = Timer::new();
+        timer.write_register(TAC_ADDRESS, TAC_FAST);
+
+        // Switching it off drops the input
+        timer.write_register(TAC_ADDRESS, 0b0000_0001);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 1);
+
+        // So does moving from a set bit to a clear one, bit 5 of 0xABCC is clear
+        timer.write_register(TAC_ADDRESS, TAC_FAST);
+        timer.write_register(TAC_ADDRESS, 0b0000_0110);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 2);
+        assert_eq!(timer.read_register(TAC_ADDRESS), 0b0000_0110);
+    }
+
+    #[test]
+    fn reload_comes_an_m_cycle_after_the_overflow() {
+        let mut timer = about_to_overflow();
+        let mut interrupts = Interrupts::default();
+        overflow(&mut timer, &mut interrupts);
+        assert!(!interrupted(&interrupts));
+
+        timer.tick(4, &mut interrupts);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 0x42);
+        assert!(interrupted(&interrupts));
+    }
+
+    #[test]
+    fn tima_write_before_the_reload_cancels_it() {
+        let mut timer = about_to_overflow();
+        let mut interrupts = Interrupts::default();
+        overflow(&mut timer, &mut interrupts);
+
+        timer.write_register(TIMA_ADDRESS, 0x10);
+        timer.tick(4, &mut interrupts);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 0x10);
+        assert!(!interrupted(&interrupts));
+    }
+
+    #[test]
+    fn writes_during_the_reload() {
+        let mut timer = about_to_overflow();
+        let mut interrupts = Interrupts::default();
+        overflow(&mut timer, &mut interrupts);
+        timer.tick(4, &mut interrupts);
+
+        // TIMA writes are lost, TMA ones go straight through
+        timer.write_register(TIMA_ADDRESS, 0x99);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 0x42);
+        timer.write_register(TMA_ADDRESS, 0x55);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 0x55);
+
+        // Only for that M-cycle
+        timer.tick(4, &mut interrupts);
+        timer.write_register(TIMA_ADDRESS, 0x99);
+        assert_eq!(timer.read_register(TIMA_ADDRESS), 0x99);
+    }
+}
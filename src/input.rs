@@ -0,0 +1,4 @@
+pub mod calibration;
+pub mod overlay;
+#[cfg(target_os = "espidf")]
+pub mod xpt2046;
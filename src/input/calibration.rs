@@ -0,0 +1,263 @@
+#[cfg(target_os = "espidf")]
+pub mod store;
+
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Line, PrimitiveStyle},
+};
+
+use crate::display::presenter::{PANEL_HEIGHT, PANEL_WIDTH};
+
+/// Where the crosshairs go, spread out towards the corners so small errors in the taps
+/// matter less. There is one more than the transform needs, to catch a bad tap.
+pub const TARGETS: [Point; 4] = [
+    Point::new(32, 24),
+    Point::new(288, 24),
+    Point::new(288, 216),
+    Point::new(32, 216),
+];
+
+/// Taps further than this from their crosshair, once fitted, mean the calibration is redone
+pub const MAX_TAP_ERROR: f32 = 8.0;
+
+// Half the length of a crosshair's arms
+const CROSSHAIR_REACH: i32 = 10;
+
+const BYTES: usize = 24;
+
+/// Affine transform from raw touch readings to panel pixels. It covers the panel
+/// being rotated or mirrored relative to the touch layer as well as scaled.
+///
+/// x = a * raw_x + b * raw_y + c, y = d * raw_x + e * raw_y + f
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Calibration {
+    /// Least squares fit of taps, raw readings paired with the points tapped.
+    /// None with fewer than three taps or if they all fall along a line.
+    pub fn from_points(taps: &[((u16, u16), Point)]) -> Option<Self> {
+        if taps.len() < 3 {
+            return None;
+        }
+
+        // Normal equations, the same matrix solves for both x and y
+        let mut m = [[0.0f64; 3]; 3];
+        let mut rhs_x = [0.0f64; 3];
+        let mut rhs_y = [0.0f64; 3];
+        for &((raw_x, raw_y), point) in taps {
+            let row = [raw_x as f64, raw_y as f64, 1.0];
+            for i in 0..3 {
+                for j in 0..3 {
+                    m[i][j] += row[i] * row[j];
+                }
+                rhs_x[i] += row[i] * point.x as f64;
+                rhs_y[i] += row[i] * point.y as f64;
+            }
+        }
+
+        let [a, b, c] = solve(m, rhs_x)?;
+        let [d, e, f] = solve(m, rhs_y)?;
+        Some(Calibration {
+            a: a as f32,
+            b: b as f32,
+            c: c as f32,
+            d: d as f32,
+            e: e as f32,
+            f: f as f32,
+        })
+    }
+
+    /// The panel pixel under a raw reading, touches just past the edge land on it
+    pub fn to_panel(&self, (raw_x, raw_y): (u16, u16)) -> Point {
+        let (x, y) = self.transform(raw_x as f32, raw_y as f32);
+        Point::new(
+            (x.round() as i32).clamp(0, PANEL_WIDTH as i32 - 1),
+            (y.round() as i32).clamp(0, PANEL_HEIGHT as i32 - 1),
+        )
+    }
+
+    /// Furthest any of the taps lands from where it should, in pixels
+    pub fn max_error(&self, taps: &[((u16, u16), Point)]) -> f32 {
+        taps.iter()
+            .map(|&((raw_x, raw_y), point)| {
+                let (x, y) = self.transform(raw_x as f32, raw_y as f32);
+                (x - point.x as f32).hypot(y - point.y as f32)
+            })
+            .fold(0.0, f32::max)
+    }
+
+    /// The coefficients in order as little endian floats, for storing
+    pub fn to_bytes(&self) -> [u8; BYTES] {
+        let mut bytes = [0; BYTES];
+        for (chunk, value) in bytes.chunks_exact_mut(4).zip(self.coefficients()) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Reads back to_bytes, None if it isn't a sensible transform
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != BYTES {
+            return None;
+        }
+
+        let mut values = bytes.chunks_exact(4).map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()));
+        let [a, b, c, d, e, f] = core::array::from_fn(|_| values.next().unwrap());
+        let calibration = Calibration { a, b, c, d, e, f };
+
+        let finite = calibration.coefficients().iter().all(|value| value.is_finite());
+        // A transform that squashes everything onto a line can't have come from a calibration
+        if finite && calibration.a * calibration.e - calibration.b * calibration.d != 0.0 {
+            Some(calibration)
+        } else {
+            None
+        }
+    }
+
+    fn coefficients(&self) -> [f32; 6] {
+        [self.a, self.b, self.c, self.d, self.e, self.f]
+    }
+
+    fn transform(&self, raw_x: f32, raw_y: f32) -> (f32, f32) {
+        (
+            self.a * raw_x + self.b * raw_y + self.c,
+            self.d * raw_x + self.e * raw_y + self.f,
+        )
+    }
+}
+
+impl Default for Calibration {
+    /// Rough numbers for a CYD in the inverted landscape orientation main.rs uses, good
+    /// enough to get about until it has been calibrated. Raw x runs from about 3700 on
+    /// the left to 200 on the right, raw y from 3800 at the top to 240 at the bottom.
+    fn default() -> Self {
+        Calibration {
+            a: -0.091_428_57,
+            b: 0.0,
+            c: 338.285_7,
+            d: 0.0,
+            e: -0.067_415_73,
+            f: 256.179_8,
+        }
+    }
+}
+
+/// A cross centred on `centre` for the user to tap
+pub fn draw_crosshair<D>(target: &mut D, centre: Point, colour: Rgb565) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    let style = PrimitiveStyle::with_stroke(colour, 1);
+    let horizontal = Point::new(CROSSHAIR_REACH, 0);
+    let vertical = Point::new(0, CROSSHAIR_REACH);
+
+    Line::new(centre - horizontal, centre + horizontal).into_styled(style).draw(target)?;
+    Line::new(centre - vertical, centre + vertical).into_styled(style).draw(target)
+}
+
+// Cramer's rule, None if the matrix is singular
+fn solve(m: [[f64; 3]; 3], rhs: [f64; 3]) -> Option<[f64; 3]> {
+    let det = determinant(m);
+    // Relative to the size of the entries, raw readings go up to 4095 so the matrix is big
+    let scale = m.iter().flatten().fold(0.0f64, |max, value| max.max(value.abs()));
+    if det.abs() <= scale.powi(3) * 1e-12 {
+        return None;
+    }
+
+    Some(core::array::from_fn(|column| {
+        let mut replaced = m;
+        for (row, value) in replaced.iter_mut().zip(rhs) {
+            row[column] = value;
+        }
+        determinant(replaced) / det
+    }))
+}
+
+fn determinant(m: [[f64; 3]; 3]) -> f64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Raw readings a made up panel would give for each target, rotated a quarter turn
+    // and mirrored so raw x follows the panel's y and raw y runs against its x
+    fn rotated_taps() -> Vec<((u16, u16), Point)> {
+        TARGETS.iter().map(|&point| (((point.y * 15 + 150) as u16, (3900 - point.x * 12) as u16), point)).collect()
+    }
+
+    #[test]
+    fn fits_a_rotated_panel() {
+        let taps = rotated_taps();
+        let calibration = Calibration::from_points(&taps).unwrap();
+
+        assert!(calibration.max_error(&taps) < 0.01);
+        for &(raw, point) in &taps {
+            assert_eq!(calibration.to_panel(raw), point);
+        }
+        // Somewhere between the targets
+        assert_eq!(calibration.to_panel((150 + 120 * 15, 3900 - 160 * 12)), Point::new(160, 120));
+    }
+
+    #[test]
+    fn averages_out_noisy_taps() {
+        let mut taps = rotated_taps();
+        taps[0].0 .0 += 20;
+        taps[2].0 .1 -= 20;
+        let calibration = Calibration::from_points(&taps).unwrap();
+
+        let error = calibration.max_error(&taps);
+        assert!(error > 0.5 && error < MAX_TAP_ERROR, "{}", error);
+    }
+
+    #[test]
+    fn notices_a_bad_tap() {
+        // The last crosshair tapped somewhere near the first
+        let mut taps = rotated_taps();
+        taps[3].0 = taps[0].0;
+        let calibration = Calibration::from_points(&taps).unwrap();
+        assert!(calibration.max_error(&taps) > MAX_TAP_ERROR);
+    }
+
+    #[test]
+    fn needs_taps_off_a_line() {
+        let taps: Vec<_> = TARGETS.iter().map(|&point| ((1000, 2000), point)).collect();
+        assert_eq!(Calibration::from_points(&taps), None);
+
+        let line: Vec<_> = (0..4).map(|i| ((100 + i * 1000, 200 + i * 500), Point::new(i as i32, 0))).collect();
+        assert_eq!(Calibration::from_points(&line), None);
+
+        assert_eq!(Calibration::from_points(&rotated_taps()[..2]), None);
+    }
+
+    #[test]
+    fn clamps_to_the_panel() {
+        let calibration = Calibration::default();
+        assert_eq!(calibration.to_panel((4095, 4095)), Point::new(0, 0));
+        assert_eq!(calibration.to_panel((0, 0)), Point::new(319, 239));
+        assert_eq!(calibration.to_panel((1950, 2020)), Point::new(160, 120));
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let calibration = Calibration::from_points(&rotated_taps()).unwrap();
+        assert_eq!(Calibration::from_bytes(&calibration.to_bytes()), Some(calibration));
+
+        assert_eq!(Calibration::from_bytes(&calibration.to_bytes()[..20]), None);
+        assert_eq!(Calibration::from_bytes(&[0; BYTES]), None);
+        let mut nan = calibration.to_bytes();
+        nan[4..8].copy_from_slice(&f32::NAN.to_le_bytes());
+        assert_eq!(Calibration::from_bytes(&nan), None);
+    }
+}
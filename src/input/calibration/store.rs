@@ -0,0 +1,30 @@
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault};
+use esp_idf_sys::EspError;
+
+use super::Calibration;
+
+const NAMESPACE: &str = "touch";
+const KEY: &str = "calibration";
+
+/// Keeps the touch calibration in NVS so it survives a power cycle
+pub struct CalibrationStore {
+    nvs: EspNvs<NvsDefault>,
+}
+
+impl CalibrationStore {
+    pub fn new(partition: EspDefaultNvsPartition) -> Result<Self, EspError> {
+        Ok(CalibrationStore {
+            nvs: EspNvs::new(partition, NAMESPACE, true)?,
+        })
+    }
+
+    /// The saved calibration, None if there isn't one yet or it didn't read back
+    pub fn load(&self) -> Result<Option<Calibration>, EspError> {
+        let mut buffer = [0; 32];
+        Ok(self.nvs.get_blob(KEY, &mut buffer)?.and_then(Calibration::from_bytes))
+    }
+
+    pub fn save(&mut self, calibration: &Calibration) -> Result<(), EspError> {
+        self.nvs.set_blob(KEY, &calibration.to_bytes())
+    }
+}
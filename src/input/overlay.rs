@@ -0,0 +1,209 @@
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyleBuilder, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+};
+
+use crate::display::presenter::PANEL_WIDTH;
+use crate::display::scaling::Scaling;
+use crate::gb::joypad::Button;
+
+// Narrowest a column of buttons can be and still take a thumb. Crop2x leaves no
+// border at all, so there they sit over the edges of the picture instead.
+const MIN_COLUMN_WIDTH: u32 = 40;
+
+const OUTLINE: Rgb565 = Rgb565::new(12, 24, 12);
+const HELD: Rgb565 = Rgb565::new(6, 12, 6);
+
+/// A patch of the panel that holds a button down while it is touched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Zone {
+    pub button: Button,
+    pub area: Rectangle,
+}
+
+impl Zone {
+    pub fn new(button: Button, top_left: Point, size: Size) -> Self {
+        Zone {
+            button,
+            area: Rectangle::new(top_left, size),
+        }
+    }
+}
+
+/// The on-screen gamepad, drawn in the border the scaled picture leaves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overlay {
+    zones: Vec<Zone>,
+    // Zones over the picture still work but aren't drawn, the next frame would cover them
+    picture: Rectangle,
+}
+
+impl Overlay {
+    pub fn new(zones: Vec<Zone>, picture: Rectangle) -> Self {
+        Overlay { zones, picture }
+    }
+
+    /// The D-pad and Select down the left of the picture, A, B and Start down the right
+    pub fn for_scaling(scaling: Scaling) -> Self {
+        let picture = scaling.area();
+        let width = (picture.top_left.x as u32).max(MIN_COLUMN_WIDTH);
+        let half = width / 2;
+        let left = 0;
+        let right = (PANEL_WIDTH - width) as i32;
+
+        let zones = vec![
+            Zone::new(Button::Up, Point::new(left, 48), Size::new(width, 40)),
+            Zone::new(Button::Left, Point::new(left, 88), Size::new(half, 48)),
+            Zone::new(Button::Right, Point::new(left + half as i32, 88), Size::new(width - half, 48)),
+            Zone::new(Button::Down, Point::new(left, 136), Size::new(width, 40)),
+            Zone::new(Button::Select, Point::new(left, 192), Size::new(width, 40)),
+            // A above B, the way they sit on the handheld
+            Zone::new(Button::A, Point::new(right, 48), Size::new(width, 56)),
+            Zone::new(Button::B, Point::new(right, 120), Size::new(width, 56)),
+            Zone::new(Button::Start, Point::new(right, 192), Size::new(width, 40)),
+        ];
+        Overlay::new(zones, picture)
+    }
+
+    pub fn zones(&self) -> &[Zone] {
+        &self.zones
+    }
+
+    /// Buttons held by a touch at `point`, as a mask of Button::bit
+    pub fn buttons_at(&self, point: Point) -> u8 {
+        self.zones
+            .iter()
+            .filter(|zone| zone.area.contains(point))
+            .fold(0, |buttons, zone| buttons | zone.button.bit())
+    }
+
+    /// Draws every zone clear of the picture, filling in the ones in `pressed`
+    pub fn draw<D>(&self, target: &mut D, pressed: u8) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let label_style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+        let centred = TextStyleBuilder::new()
+            .alignment(Alignment::Center)
+            .baseline(Baseline::Middle)
+            .build();
+
+        for zone in self.zones.iter().filter(|zone| self.picture.intersection(&zone.area).size == Size::zero()) {
+            let fill = if pressed & zone.button.bit() != 0 {HELD} else {Rgb565::BLACK};
+            let style = PrimitiveStyleBuilder::new()
+                .fill_color(fill)
+                .stroke_color(OUTLINE)
+                .stroke_width(1)
+                .build();
+
+            zone.area.into_styled(style).draw(target)?;
+            Text::with_text_style(label(zone.button), zone.area.center(), label_style, centred).draw(target)?;
+        }
+        Ok(())
+    }
+}
+
+fn label(button: Button) -> &'static str {
+    match button {
+        Button::Right  => ">",
+        Button::Left   => "<",
+        Button::Up     => "^",
+        Button::Down   => "v",
+        Button::A      => "A",
+        Button::B      => "B",
+        Button::Select => "SEL",
+        Button::Start  => "START",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+
+    use crate::display::presenter::PANEL_HEIGHT;
+
+    // Only remembers which pixels were drawn to
+    struct Touched(Vec<Point>);
+
+    impl OriginDimensions for Touched {
+        fn size(&self) -> Size {
+            Size::new(PANEL_WIDTH, PANEL_HEIGHT)
+        }
+    }
+
+    impl DrawTarget for Touched {
+        type Color = Rgb565;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Self::Color>>,
+        {
+            self.0.extend(pixels.into_iter().map(|Pixel(point, _)| point));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn every_button_has_a_zone() {
+        for scaling in [Scaling::Centred, Scaling::Crop2x, Scaling::Fit, Scaling::SmoothFit] {
+            let overlay = Overlay::for_scaling(scaling);
+            for button in Button::ALL {
+                let zone = overlay.zones().iter().find(|zone| zone.button == button).unwrap();
+                assert_eq!(overlay.buttons_at(zone.area.center()), button.bit(), "{:?} in {:?}", button, scaling);
+            }
+        }
+    }
+
+    #[test]
+    fn fit_leaves_the_picture_alone() {
+        let overlay = Overlay::for_scaling(Scaling::Fit);
+        let picture = Scaling::Fit.area();
+        assert!(overlay.zones().iter().all(|zone| picture.intersection(&zone.area).size == Size::zero()));
+
+        assert_eq!(overlay.buttons_at(picture.center()), 0);
+        assert_eq!(overlay.buttons_at(picture.top_left), 0);
+        // Right up against the edges of the border
+        assert_eq!(overlay.buttons_at(Point::new(39, 100)), Button::Right.bit());
+        assert_eq!(overlay.buttons_at(Point::new(280, 60)), Button::A.bit());
+        // The gaps between zones and off the panel
+        assert_eq!(overlay.buttons_at(Point::new(300, 110)), 0);
+        assert_eq!(overlay.buttons_at(Point::new(10, 10)), 0);
+        assert_eq!(overlay.buttons_at(Point::new(-5, 100)), 0);
+        assert_eq!(overlay.buttons_at(Point::new(320, 60)), 0);
+    }
+
+    #[test]
+    fn zones_can_overlap() {
+        // A corner of the D-pad that holds two directions at once
+        let picture = Scaling::Fit.area();
+        let overlay = Overlay::new(vec![
+            Zone::new(Button::Up, Point::new(0, 0), Size::new(40, 40)),
+            Zone::new(Button::Left, Point::new(0, 20), Size::new(20, 40)),
+        ], picture);
+
+        assert_eq!(overlay.buttons_at(Point::new(10, 30)), Button::Up.bit() | Button::Left.bit());
+        assert_eq!(overlay.buttons_at(Point::new(30, 30)), Button::Up.bit());
+        assert_eq!(overlay.buttons_at(Point::new(10, 50)), Button::Left.bit());
+    }
+
+    #[test]
+    fn drawing_stays_in_the_border() {
+        let picture = Scaling::Fit.area();
+        let mut target = Touched(Vec::new());
+        Overlay::for_scaling(Scaling::Fit).draw(&mut target, Button::A.bit()).unwrap();
+        assert!(!target.0.is_empty());
+        assert!(target.0.iter().all(|&point| !picture.contains(point)));
+
+        // Crop2x has no border, nothing is drawn but the buttons still respond
+        let mut target = Touched(Vec::new());
+        let overlay = Overlay::for_scaling(Scaling::Crop2x);
+        overlay.draw(&mut target, 0).unwrap();
+        assert!(target.0.is_empty());
+        assert_eq!(overlay.buttons_at(Point::new(300, 60)), Button::A.bit());
+    }
+}
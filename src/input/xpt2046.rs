@@ -0,0 +1,61 @@
+use embedded_graphics::prelude::Point;
+
+use esp_idf_hal::{
+    gpio::{AnyInputPin, Input, PinDriver},
+    spi::{SpiDeviceDriver, SpiDriver},
+};
+use esp_idf_sys::EspError;
+
+use super::calibration::Calibration;
+
+// Control bytes, a start bit then the channel, in 12-bit differential mode with
+// the converter powered down between readings so the pen interrupt stays on
+const READ_X: u8 = 0b1101_0000;
+const READ_Y: u8 = 0b1001_0000;
+
+// Readings averaged for each touch, the resistive panel is noisy
+const SAMPLES: u32 = 4;
+
+/// The CYD's resistive touch controller, on its own SPI bus
+pub struct Xpt2046<'d> {
+    spi: SpiDeviceDriver<'d, SpiDriver<'d>>,
+    // Pulled low by the controller while the panel is pressed
+    irq: PinDriver<'d, AnyInputPin, Input>,
+    calibration: Calibration,
+}
+
+impl<'d> Xpt2046<'d> {
+    pub fn new(spi: SpiDeviceDriver<'d, SpiDriver<'d>>, irq: PinDriver<'d, AnyInputPin, Input>, calibration: Calibration) -> Self {
+        Xpt2046 { spi, irq, calibration }
+    }
+
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.calibration = calibration;
+    }
+
+    /// The 12-bit x and y readings, None while the panel isn't being pressed
+    pub fn read_raw(&mut self) -> Result<Option<(u16, u16)>, EspError> {
+        if self.irq.is_high() {
+            return Ok(None);
+        }
+
+        let (mut x, mut y) = (0, 0);
+        for _ in 0..SAMPLES {
+            x += self.sample(READ_X)? as u32;
+            y += self.sample(READ_Y)? as u32;
+        }
+        Ok(Some(((x / SAMPLES) as u16, (y / SAMPLES) as u16)))
+    }
+
+    /// Where on the panel is being pressed, if anywhere
+    pub fn read(&mut self) -> Result<Option<Point>, EspError> {
+        Ok(self.read_raw()?.map(|raw| self.calibration.to_panel(raw)))
+    }
+
+    fn sample(&mut self, command: u8) -> Result<u16, EspError> {
+        let mut read = [0; 3];
+        self.spi.transfer(&mut read, &[command, 0, 0])?;
+        // The reading comes back across the next two bytes after a busy clock
+        Ok(u16::from_be_bytes([read[1], read[2]]) >> 3)
+    }
+}
@@ -0,0 +1,6 @@
+pub mod gb;
+pub mod display;
+pub mod audio;
+pub mod input;
+pub mod link;
+pub mod save;
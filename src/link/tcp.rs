@@ -0,0 +1,235 @@
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use crate::gb::serial::{LinkMessage, LinkTransport};
+
+const TRANSFER_TAG: u8 = 0x01;
+const REPLY_TAG: u8 = 0x02;
+
+/// A link cable over TCP, each message goes as a tag byte then the data byte.
+/// Incoming messages are read on their own thread so the emulator never waits on the socket.
+#[derive(Debug)]
+pub struct TcpLink {
+    stream: TcpStream,
+    incoming: Receiver<LinkMessage>,
+    connected: bool,
+}
+
+impl TcpLink {
+    /// Waits for the other emulator to connect to `address`
+    pub fn listen<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let (stream, peer) = TcpListener::bind(address)?.accept()?;
+        log::info!("Link cable connected from {}", peer);
+        TcpLink::new(stream)
+    }
+
+    pub fn connect<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        TcpLink::new(TcpStream::connect(address)?)
+    }
+
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        // Every transfer waits on a round trip, they can't sit in a buffer
+        stream.set_nodelay(true)?;
+
+        let mut reader = stream.try_clone()?;
+        let (sender, incoming) = mpsc::channel();
+        thread::Builder::new()
+            .name("link".into())
+            .stack_size(4096)
+            .spawn(move || {
+                let mut frame = [0; 2];
+                while reader.read_exact(&mut frame).is_ok() {
+                    match decode(frame) {
+                        Some(message) => if sender.send(message).is_err() {
+                            break;
+                        },
+                        None => log::warn!("Unknown link cable message {:02X?}", frame),
+                    }
+                }
+            })?;
+
+        Ok(TcpLink {
+            stream,
+            incoming,
+            connected: true,
+        })
+    }
+}
+
+impl LinkTransport for TcpLink {
+    fn send(&mut self, message: LinkMessage) {
+        if !self.connected {
+            return;
+        }
+
+        if let Err(e) = self.stream.write_all(&encode(message)) {
+            log::warn!("Link cable dropped: {}", e);
+            self.connected = false;
+        }
+    }
+
+    fn receive(&mut self) -> Option<LinkMessage> {
+        match self.incoming.try_recv() {
+            Ok(message) => Some(message),
+            Err(TryRecvError::Empty) => None,
+            // The reader stops when the other side hangs up
+            Err(TryRecvError::Disconnected) => {
+                self.connected = false;
+                None
+            }
+        }
+    }
+
+    fn connected(&self) -> bool {
+        self.connected
+    }
+}
+
+impl Drop for TcpLink {
+    fn drop(&mut self) {
+        // Wakes the reader thread so it can finish
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+fn encode(message: LinkMessage) -> [u8; 2] {
+    match message {
+        LinkMessage::Transfer(byte) => [TRANSFER_TAG, byte],
+        LinkMessage::Reply(byte) => [REPLY_TAG, byte],
+    }
+}
+
+fn decode(frame: [u8; 2]) -> Option<LinkMessage> {
+    match frame[0] {
+        TRANSFER_TAG => Some(LinkMessage::Transfer(frame[1])),
+        REPLY_TAG => Some(LinkMessage::Reply(frame[1])),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    use crate::gb::interrupts::{Interrupt, Interrupts};
+    use crate::gb::serial::{Serial, SB_ADDRESS, SC_ADDRESS};
+
+    // An emulator's worth of serial port, linked to another over loopback
+    struct Side {
+        serial: Serial,
+        interrupts: Interrupts,
+    }
+
+    impl Side {
+        fn new(link: TcpLink) -> Self {
+            let mut serial = Serial::new();
+            serial.connect(Box::new(link));
+            Side {
+                serial,
+                interrupts: Interrupts::default(),
+            }
+        }
+
+        fn start(&mut self, sb: u8, sc: u8) {
+            self.serial.write_register(SB_ADDRESS, sb);
+            self.serial.write_register(SC_ADDRESS, sc);
+        }
+
+        fn busy(&self) -> bool {
+            self.serial.read_register(SC_ADDRESS) & 0b1000_0000 != 0
+        }
+
+        fn interrupted(&self) -> bool {
+            self.interrupts.flag & Interrupt::Serial.bit() != 0
+        }
+    }
+
+    fn linked_pair() -> (Side, Side) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = thread::spawn(move || TcpLink::connect(address).unwrap());
+        let (stream, _) = listener.accept().unwrap();
+
+        (Side::new(TcpLink::new(stream).unwrap()), Side::new(client.join().unwrap()))
+    }
+
+    // Runs both sides in lockstep until the master's transfer is done
+    fn run(master: &mut Side, slave: &mut Side) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while master.busy() {
+            assert!(Instant::now() < deadline, "transfer never finished");
+            for _ in 0..128 {
+                master.serial.tick(4, &mut master.interrupts);
+                slave.serial.tick(4, &mut slave.interrupts);
+            }
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn swaps_bytes_between_two_emulators() {
+        let (mut master, mut slave) = linked_pair();
+
+        slave.start(0x42, 0x80);
+        master.start(0x17, 0x81);
+        run(&mut master, &mut slave);
+
+        assert_eq!(master.serial.read_register(SB_ADDRESS), 0x42);
+        assert!(master.interrupted());
+
+        // The reply goes out as soon as the transfer comes in, so the slave is done too
+        assert_eq!(slave.serial.read_register(SB_ADDRESS), 0x17);
+        assert!(!slave.busy());
+        assert!(slave.interrupted());
+
+        // Then the other way round
+        master.interrupts = Interrupts::default();
+        slave.interrupts = Interrupts::default();
+        master.start(0x99, 0x80);
+        slave.start(0x5A, 0x81);
+        run(&mut slave, &mut master);
+        assert_eq!(slave.serial.read_register(SB_ADDRESS), 0x99);
+        assert_eq!(master.serial.read_register(SB_ADDRESS), 0x5A);
+        assert!(master.interrupted() && slave.interrupted());
+    }
+
+    #[test]
+    fn slave_without_a_transfer_waiting_is_left_alone() {
+        let (mut master, mut slave) = linked_pair();
+
+        slave.start(0x33, 0x00);
+        master.start(0x17, 0x81);
+        run(&mut master, &mut slave);
+
+        // Its SB still goes out, but nothing comes in
+        assert_eq!(master.serial.read_register(SB_ADDRESS), 0x33);
+        assert_eq!(slave.serial.read_register(SB_ADDRESS), 0x33);
+        assert!(!slave.interrupted());
+    }
+
+    #[test]
+    fn acts_unplugged_once_the_other_side_goes() {
+        let (mut master, slave) = linked_pair();
+        drop(slave);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while master.serial.is_connected() {
+            assert!(Instant::now() < deadline, "never noticed the hang up");
+            master.serial.tick(128, &mut master.interrupts);
+            thread::yield_now();
+        }
+
+        master.start(0x17, 0x81);
+        for _ in 0..4096 / 4 - 1 {
+            master.serial.tick(4, &mut master.interrupts);
+        }
+        assert!(master.busy());
+        master.serial.tick(4, &mut master.interrupts);
+        assert!(!master.busy());
+        assert_eq!(master.serial.read_register(SB_ADDRESS), 0xFF);
+        assert!(master.interrupted());
+    }
+}
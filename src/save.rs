@@ -0,0 +1,147 @@
+#[cfg(target_os = "espidf")]
+pub mod store;
+
+use crate::gb::cartridge::CartridgeHeader;
+use crate::gb::ram::MemoryBus;
+
+// Frames between looking for changes to the cartridge ram, about 5 seconds. Writing
+// it out holds up the emulator and wears the flash, so it isn't done any more often.
+const CHECK_FRAMES: u32 = 300;
+
+/// Name the cartridge's save is kept under, NVS keys are at most 15 characters
+pub fn key(header: &CartridgeHeader) -> String {
+    format!("{:02X}{:04X}", header.header_checksum, header.global_checksum)
+}
+
+/// Decides when the battery backed ram needs writing out, every so often and only if it has changed.
+/// Only the ram is compared, an MBC3 RTC moves on every latch but is written out with the ram.
+#[derive(Debug)]
+pub struct Autosave {
+    // The ram as it was last written out, None until there is something in storage
+    ram: Option<Vec<u8>>,
+    frames: u32,
+}
+
+impl Autosave {
+    /// `saved` is what storage holds already and has been loaded into `bus`, empty if there's nothing there yet
+    pub fn new(bus: &MemoryBus, saved: &[u8]) -> Self {
+        Autosave {
+            ram: if saved.is_empty() {None} else {bus.save_ram()},
+            frames: 0,
+        }
+    }
+
+    /// Called once a frame, hands back the save data when it is due to be written out
+    pub fn frame(&mut self, bus: &MemoryBus) -> Option<Vec<u8>> {
+        self.frames += 1;
+        if self.frames < CHECK_FRAMES {
+            return None;
+        }
+        self.frames = 0;
+
+        let ram = bus.save_ram()?;
+        if self.ram.as_ref() == Some(&ram) {
+            return None;
+        }
+        self.ram = Some(ram);
+        bus.save_data()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gb::cartridge::Cartridge;
+    use crate::gb::clock::tests::FakeClock;
+
+    // A 32K cartridge with 8K of ram, with or without a battery behind it
+    fn cartridge(cartridge_type: u8) -> Cartridge {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0134..0x0138].copy_from_slice(b"SAVE");
+        rom[0x0147] = cartridge_type;
+        rom[0x0149] = 0x02;
+        rom[0x014D] = rom[0x0134..0x014D].iter().fold(0u8, |sum, &byte| sum.wrapping_sub(byte).wrapping_sub(1));
+        let global = rom.iter().fold(0u16, |sum, &byte| sum.wrapping_add(byte as u16));
+        rom[0x014E..0x0150].copy_from_slice(&global.to_be_bytes());
+        Cartridge::new(rom).unwrap()
+    }
+
+    fn bus(cartridge_type: u8) -> MemoryBus {
+        MemoryBus::new(cartridge(cartridge_type))
+    }
+
+    fn write_ram(bus: &mut MemoryBus, val: u8) {
+        bus.write_byte(0x0000, 0x0A);
+        bus.write_byte(0xA000, val);
+    }
+
+    // Runs the frames up to the next check
+    fn check(autosave: &mut Autosave, bus: &MemoryBus) -> Option<Vec<u8>> {
+        for _ in 1..CHECK_FRAMES {
+            assert_eq!(autosave.frame(bus), None);
+        }
+        autosave.frame(bus)
+    }
+
+    #[test]
+    fn saves_only_when_the_ram_changes() {
+        let mut bus = bus(0x03);
+        let mut autosave = Autosave::new(&bus, &bus.save_data().unwrap());
+        assert_eq!(check(&mut autosave, &bus), None);
+
+        write_ram(&mut bus, 0x42);
+        let saved = check(&mut autosave, &bus).unwrap();
+        assert_eq!(saved.len(), 0x2000);
+        assert_eq!(saved[0], 0x42);
+
+        assert_eq!(check(&mut autosave, &bus), None);
+    }
+
+    #[test]
+    fn first_save_goes_out_even_if_the_ram_is_blank() {
+        let bus = bus(0x03);
+        let mut autosave = Autosave::new(&bus, &[]);
+        assert_eq!(check(&mut autosave, &bus).map(|data| data.len()), Some(0x2000));
+    }
+
+    #[test]
+    fn nothing_to_save_without_a_battery() {
+        let mut bus = bus(0x02);
+        let mut autosave = Autosave::new(&bus, &[]);
+        write_ram(&mut bus, 0x42);
+        assert_eq!(check(&mut autosave, &bus), None);
+    }
+
+    #[test]
+    fn clock_latches_alone_do_not_save() {
+        // MBC3 with the timer, ram and battery
+        let clock = FakeClock::default();
+        let mut bus = MemoryBus::with_clock(cartridge(0x10), Box::new(clock.clone()));
+        let mut autosave = Autosave::new(&bus, &bus.save_data().unwrap());
+
+        bus.write_byte(0x0000, 0x0A);
+        bus.write_byte(0x4000, 0x08);
+        for _ in 0..10 {
+            // Latching and reading the seconds the way Pokémon Gold does
+            clock.advance(5);
+            bus.write_byte(0x6000, 0x00);
+            bus.write_byte(0x6000, 0x01);
+            bus.read_byte(0xA000);
+            assert_eq!(check(&mut autosave, &bus), None);
+        }
+
+        // A ram write saves the clock along with it
+        bus.write_byte(0x4000, 0x00);
+        bus.write_byte(0xA000, 0x42);
+        let saved = check(&mut autosave, &bus).unwrap();
+        assert_eq!(saved.len(), 0x2000 + 48);
+        assert_eq!(saved[0], 0x42);
+        assert_eq!(saved[0x2000], 50);
+    }
+
+    #[test]
+    fn keys_fit_in_nvs() {
+        let bus = bus(0x03);
+        assert_eq!(key(bus.header()).len(), 6);
+    }
+}
@@ -0,0 +1,34 @@
+use esp_idf_svc::nvs::{EspCustomNvsPartition, EspNvs, NvsCustom};
+use esp_idf_sys::EspError;
+
+// Its own partition, cartridge ram is too big for the default one
+const PARTITION: &str = "saves";
+const NAMESPACE: &str = "saves";
+
+/// Keeps cartridge saves in NVS, one per game under its `save::key`
+pub struct SaveStore {
+    nvs: EspNvs<NvsCustom>,
+}
+
+impl SaveStore {
+    pub fn new() -> Result<Self, EspError> {
+        let partition = EspCustomNvsPartition::take(PARTITION)?;
+        Ok(SaveStore {
+            nvs: EspNvs::new(partition, NAMESPACE, true)?,
+        })
+    }
+
+    /// The saved data, None if the game hasn't been saved yet
+    pub fn load(&self, key: &str) -> Result<Option<Vec<u8>>, EspError> {
+        let Some(len) = self.nvs.blob_len(key)? else {
+            return Ok(None);
+        };
+
+        let mut buffer = vec![0; len];
+        Ok(self.nvs.get_blob(key, &mut buffer)?.map(<[u8]>::to_vec))
+    }
+
+    pub fn save(&mut self, key: &str, data: &[u8]) -> Result<(), EspError> {
+        self.nvs.set_blob(key, data)
+    }
+}
@@ -0,0 +1,282 @@
+//! Runs Blargg's and mooneye's test roms headlessly and checks what they report.
+//!
+//! The roms aren't distributed with the crate, they are looked for under `test-roms/` or
+//! wherever `CYD_GAMEBOY_TEST_ROMS` points, laid out as
+//!
+//!     blargg/cpu_instrs/     the cpu_instrs directory from gb-test-roms, individual/ and all
+//!     blargg/instr_timing/
+//!     blargg/mem_timing/
+//!     mooneye/acceptance/    from a build of mooneye-test-suite, only the DMG's roms are run
+//!
+//! Any that are missing are skipped.
+
+use std::env;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use cyd_gameboy::gb::apu::CPU_CLOCK_HZ;
+use cyd_gameboy::gb::cartridge::Cartridge;
+use cyd_gameboy::gb::cpu::Cpu;
+use cyd_gameboy::gb::ram::MemoryBus;
+
+// Emulated seconds a rom gets before it counts as hung, the full cpu_instrs takes about a minute
+const BLARGG_SECONDS: u64 = 120;
+const MOONEYE_SECONDS: u64 = 20;
+
+// Mooneye's roms finish with LD B, B, passing leaves the Fibonacci numbers in the registers
+const LD_B_B: u8 = 0x40;
+const MOONEYE_PASS: [u8; 6] = [3, 5, 8, 13, 21, 34];
+const MOONEYE_FAIL: [u8; 6] = [0x42; 6];
+
+fn rom_dir() -> PathBuf {
+    env::var_os("CYD_GAMEBOY_TEST_ROMS")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("test-roms"))
+}
+
+// Every .gb file under `dir`, in a stable order
+fn find_roms(dir: &Path) -> Vec<PathBuf> {
+    let mut roms = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.is_dir() {
+                roms.extend(find_roms(&path));
+            } else if path.extension().is_some_and(|extension| extension == "gb") {
+                roms.push(path);
+            }
+        }
+    }
+    roms.sort();
+    roms
+}
+
+/// Runs the rom until `check` has an answer or `seconds` of emulated time go by.
+/// The cpu locking up on an opcode that doesn't exist fails the rom straight away,
+/// and a panic in the core is a failure rather than the end of the suite.
+fn run<F>(rom: Vec<u8>, seconds: u64, mut check: F) -> Result<(), String>
+where
+    F: FnMut(&Cpu) -> Option<Result<(), String>>,
+{
+    let cartridge = Cartridge::new(rom).map_err(|e| e.to_string())?;
+    let mut cpu = Cpu::new(MemoryBus::new(cartridge));
+    let budget = seconds * CPU_CLOCK_HZ as u64;
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut cycles = 0;
+        while cycles < budget {
+            if let Some(result) = check(&cpu) {
+                return result;
+            }
+            if cpu.is_locked() {
+                return Err(format!("locked up at pc 0x{:04X}", cpu.pc()));
+            }
+            cycles += cpu.step() as u64;
+        }
+        Err(format!("still going after {}s", seconds))
+    }));
+
+    let serial = cpu.bus().serial.output();
+    match result {
+        Ok(Err(reason)) if !serial.is_empty() => Err(format!("{}, serial output:\n{}", reason, serial)),
+        Ok(result) => result,
+        Err(panic) => {
+            let message = panic.downcast_ref::<String>().map(String::as_str)
+                .or_else(|| panic.downcast_ref::<&str>().copied())
+                .unwrap_or("unknown panic");
+            Err(format!("panicked at pc 0x{:04X}: {}", cpu.pc(), message))
+        }
+    }
+}
+
+/// Blargg's roms print their name, then "Passed" or "Failed" with details
+fn run_blargg(rom: Vec<u8>, seconds: u64) -> Result<(), String> {
+    let mut checked = 0;
+    run(rom, seconds, |cpu| {
+        let output = cpu.bus().serial.output();
+        // Only worth searching when something new has come out
+        if output.len() == checked {
+            return None;
+        }
+        checked = output.len();
+
+        if output.contains("Passed") {
+            Some(Ok(()))
+        } else if output.contains("Failed") && output.ends_with('\n') {
+            // Waits for the rest of the line, which says what went wrong
+            Some(Err("failed".to_string()))
+        } else {
+            None
+        }
+    })
+}
+
+/// Mooneye's roms name the models they pass on after the last dash, dmgABC or G (for DMG and
+/// GB Pocket) are the ones this emulates. No suffix means it passes on all of them.
+fn runs_on_dmg(path: &Path) -> bool {
+    let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+        return false;
+    };
+    match stem.rsplit_once('-') {
+        Some((_, models)) => models.contains("dmgABC") || models.contains('G'),
+        None => true,
+    }
+}
+
+fn run_mooneye(rom: Vec<u8>, seconds: u64) -> Result<(), String> {
+    run(rom, seconds, |cpu| {
+        if cpu.bus().read_byte(cpu.pc()) != LD_B_B {
+            return None;
+        }
+
+        let registers = cpu.registers();
+        let signature = [registers.b, registers.c, registers.d, registers.e, registers.h, registers.l];
+        Some(match signature {
+            MOONEYE_PASS => Ok(()),
+            MOONEYE_FAIL => Err("failed".to_string()),
+            _ => Err(format!("finished with BCDEHL {:02X?}", signature)),
+        })
+    })
+}
+
+/// Runs the roms in `suite` under the rom directory that `include` picks, failing with a report if any of them fail
+fn run_suite(suite: &str, include: fn(&Path) -> bool, runner: fn(Vec<u8>, u64) -> Result<(), String>, seconds: u64) {
+    let dir = rom_dir().join(suite);
+    let mut roms = find_roms(&dir);
+    roms.retain(|path| include(path));
+    if roms.is_empty() {
+        println!("Skipping {}, no roms in {}", suite, dir.display());
+        return;
+    }
+
+    let mut failures = Vec::new();
+    for path in &roms {
+        let name = path.strip_prefix(&dir).unwrap_or(path).display();
+        let result = fs::read(path).map_err(|e| e.to_string()).and_then(|rom| runner(rom, seconds));
+        match result {
+            Ok(()) => println!("PASS {}/{}", suite, name),
+            Err(reason) => {
+                println!("FAIL {}/{}: {}", suite, name, reason);
+                failures.push(format!("{}: {}", name, reason));
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} {} roms failed\n\n{}",
+        failures.len(),
+        roms.len(),
+        suite,
+        failures.join("\n\n")
+    );
+}
+
+#[test]
+fn blargg_cpu_instrs() {
+    run_suite("blargg/cpu_instrs", |_| true, run_blargg, BLARGG_SECONDS);
+}
+
+#[test]
+fn blargg_instr_timing() {
+    run_suite("blargg/instr_timing", |_| true, run_blargg, BLARGG_SECONDS);
+}
+
+#[test]
+fn blargg_mem_timing() {
+    run_suite("blargg/mem_timing", |_| true, run_blargg, BLARGG_SECONDS);
+}
+
+#[test]
+fn mooneye_acceptance() {
+    run_suite("mooneye/acceptance", runs_on_dmg, run_mooneye, MOONEYE_SECONDS);
+}
+
+// The harness checked against tiny roms that report the way the real ones do,
+// so it is known to work even where the real ones aren't around
+
+const ENTRY: usize = 0x0100;
+const CODE: usize = 0x0150;
+
+// A 32K rom only cartridge running `code` from 0x0150
+fn build_rom(code: &[u8]) -> Vec<u8> {
+    let mut rom = vec![0; 0x8000];
+    // NOP, JP 0x0150
+    rom[ENTRY..ENTRY + 4].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]);
+    rom[0x0134..0x0138].copy_from_slice(b"TEST");
+    rom[CODE..CODE + code.len()].copy_from_slice(code);
+
+    rom[0x014D] = rom[0x0134..0x014D].iter().fold(0u8, |sum, &byte| sum.wrapping_sub(byte).wrapping_sub(1));
+    let global = rom.iter().fold(0u16, |sum, &byte| sum.wrapping_add(byte as u16));
+    rom[0x014E..0x0150].copy_from_slice(&global.to_be_bytes());
+    rom
+}
+
+// Sends `text` out of the serial port a byte at a time, then spins
+fn printing(text: &str) -> Vec<u8> {
+    let mut code = Vec::new();
+    for byte in text.bytes() {
+        code.extend_from_slice(&[
+            0x3E, byte, // LD A, byte
+            0xE0, 0x01, // LDH (SB), A
+            0x3E, 0x81, // LD A, 0x81
+            0xE0, 0x02, // LDH (SC), A
+            0xF0, 0x02, // LDH A, (SC)
+            0xE6, 0x80, // AND 0x80
+            0x20, 0xFA, // JR NZ, back to the LDH
+        ]);
+    }
+    code.extend_from_slice(&[0x18, 0xFE]); // JR to itself
+    code
+}
+
+// Loads BCDEHL then hits the LD B, B breakpoint
+fn finishing_with(registers: [u8; 6]) -> Vec<u8> {
+    let mut code = Vec::new();
+    for (opcode, value) in [0x06, 0x0E, 0x16, 0x1E, 0x26, 0x2E].into_iter().zip(registers) {
+        code.extend_from_slice(&[opcode, value]);
+    }
+    code.extend_from_slice(&[LD_B_B, 0x18, 0xFE]);
+    code
+}
+
+#[test]
+fn harness_reads_blargg_results_from_serial() {
+    assert_eq!(run_blargg(build_rom(&printing("01-special\n\n\nPassed\n")), 1), Ok(()));
+
+    let failed = run_blargg(build_rom(&printing("01-special\n\nFailed #2\n")), 1).unwrap_err();
+    assert!(failed.contains("Failed #2"), "{}", failed);
+
+    let hung = run_blargg(build_rom(&printing("01-special\n")), 1).unwrap_err();
+    assert!(hung.starts_with("still going after 1s"), "{}", hung);
+}
+
+#[test]
+fn harness_reads_mooneye_results_from_registers() {
+    assert_eq!(run_mooneye(build_rom(&finishing_with(MOONEYE_PASS)), 1), Ok(()));
+    assert_eq!(run_mooneye(build_rom(&finishing_with(MOONEYE_FAIL)), 1), Err("failed".to_string()));
+
+    let odd = run_mooneye(build_rom(&finishing_with([1, 2, 3, 4, 5, 6])), 1).unwrap_err();
+    assert!(odd.contains("[01, 02, 03, 04, 05, 06]"), "{}", odd);
+}
+
+#[test]
+fn harness_survives_a_bad_rom() {
+    // 0xD3 isn't an opcode
+    let locked = run_mooneye(build_rom(&[0xD3]), 1).unwrap_err();
+    assert!(locked.starts_with("locked up at pc 0x0150"), "{}", locked);
+
+    let mut corrupt = build_rom(&finishing_with(MOONEYE_PASS));
+    corrupt[0x0200] ^= 0xFF;
+    assert!(run_mooneye(corrupt, 1).unwrap_err().contains("checksum"));
+}
+
+#[test]
+fn harness_only_picks_mooneye_roms_for_the_dmg() {
+    for name in ["ei_sequence.gb", "boot_regs-dmgABC.gb", "boot_div-dmgABCmgb.gb", "di_timing-GS.gb", "ppu/intr_2_0_timing.gb"] {
+        assert!(runs_on_dmg(Path::new(name)), "{}", name);
+    }
+    for name in ["boot_regs-dmg0.gb", "boot_regs-mgb.gb", "boot_regs-sgb.gb", "boot_regs-sgb2.gb", "boot_hwio-S.gb", "boot_hwio-C.gb", "boot_div-A.gb"] {
+        assert!(!runs_on_dmg(Path::new(name)), "{}", name);
+    }
+}